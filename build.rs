@@ -0,0 +1,190 @@
+//! Detects which atomic types are natively provided by the target.
+//!
+//! Matching on `target_arch` alone misses custom JSON targets, so the decision is based on the
+//! `target_has_atomic` values rustc derives from the target spec's `max-atomic-width`.
+//! When rustc is too old to report them, it falls back to the list of known architectures.
+//!
+//! WebAssembly reports every width even without the `atomics` target feature, lowering the atomics to
+//! plain memory accesses, which is only sound while the module is single-threaded. Without the
+//! feature every type is shimmed, as the shim's locks stay correct in that model too.
+//!
+//! Every shimmed type gets its own `atomic_shim_fallback_<type>` cfg, so the `force-shim-<type>`
+//! features can replace a single type, and `atomic_shim_fallback` is set when any of them is.
+//!
+//! Whenever a shim is selected, a `cargo:warning` is emitted describing the selection, as it is
+//! easy to ship lock-based atomics without noticing. Set `ATOMIC_SHIM_NO_WARN` to silence it.
+
+use std::env;
+
+/// Architectures known to lack 64-bit atomics, used when rustc does not report `target_has_atomic`.
+const FALLBACK_ARCHS: &[&str] = &["mips", "powerpc"];
+
+/// Types which have a shim implementation, and the `target_has_atomic` width they need to be native.
+const SHIMMED_TYPES: &[(&str, &str)] = &[
+    ("bool", "8"),
+    ("u8", "8"),
+    ("i8", "8"),
+    ("u16", "16"),
+    ("i16", "16"),
+    ("u32", "32"),
+    ("i32", "32"),
+    ("u64", "64"),
+    ("i64", "64"),
+    ("usize", "ptr"),
+    ("isize", "ptr"),
+    ("ptr", "ptr"),
+];
+
+/// Name of the implementation backing the shimmed types.
+const BACKEND: &str = "sharded-lock";
+
+/// Name of the implementation backing the 64-bit shims on 32-bit x86 Windows with `windows-intrinsics`.
+const WINDOWS_BACKEND: &str = "interlocked";
+
+/// Name of the implementation backing the 64-bit shims on targets with 32-bit atomics, whose loads don't lock.
+const SEQLOCK_BACKEND: &str = "seqlock";
+
+/// Name of the implementation backing the shimmed types with `weak-memory`, outside of loom.
+const WEAK_MEMORY_BACKEND: &str = "weak-memory";
+
+/// Environment variable which silences the warning emitted when the shim is selected.
+const NO_WARN_ENV: &str = "ATOMIC_SHIM_NO_WARN";
+
+/// Environment variable setting how many times the shim tries a busy lock before blocking on it.
+const SPIN_LIMIT_ENV: &str = "ATOMIC_SHIM_SPIN_LIMIT";
+
+/// Attempts made when `ATOMIC_SHIM_SPIN_LIMIT` isn't set, spinning up to 63 times in total.
+const DEFAULT_SPIN_LIMIT: u32 = 6;
+
+/// The highest spin limit accepted, as the spins double with every attempt.
+const MAX_SPIN_LIMIT: u32 = 16;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={}", NO_WARN_ENV);
+    println!("cargo:rerun-if-env-changed={}", SPIN_LIMIT_ENV);
+    println!("cargo:rustc-check-cfg=cfg(atomic_shim_fallback)");
+    println!("cargo:rustc-check-cfg=cfg(loom)");
+    for (ty, _) in SHIMMED_TYPES {
+        println!("cargo:rustc-check-cfg=cfg(atomic_shim_fallback_{})", ty);
+    }
+
+    println!(
+        "cargo:rustc-env=ATOMIC_SHIM_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    println!("cargo:rustc-env={}={}", SPIN_LIMIT_ENV, spin_limit());
+
+    let reason = if loom() {
+        "loom"
+    } else if wasm_without_atomics() {
+        "wasm-without-atomics-feature"
+    } else if !target_has_atomic("64") {
+        "target-without-64-bit-atomics"
+    } else if feature_enabled("mutex") {
+        "feature-mutex"
+    } else {
+        "feature-force-shim"
+    };
+
+    let mut shimmed = Vec::new();
+    for (ty, width) in SHIMMED_TYPES {
+        let forced = *width == "64" && feature_enabled("mutex")
+            || feature_enabled(&format!("force-shim-{}", ty));
+        if forced || !target_has_atomic(width) {
+            println!("cargo:rustc-cfg=atomic_shim_fallback_{}", ty);
+            shimmed.push(*ty);
+        }
+    }
+
+    if shimmed.is_empty() {
+        return;
+    }
+
+    println!("cargo:rustc-cfg=atomic_shim_fallback");
+
+    let backend = if feature_enabled("weak-memory") && !loom() {
+        WEAK_MEMORY_BACKEND
+    } else if env::var("CARGO_CFG_WINDOWS").is_ok()
+        && env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default() == "x86"
+        && feature_enabled("windows-intrinsics")
+    {
+        WINDOWS_BACKEND
+    } else if seqlock() && shimmed.iter().any(|ty| *ty == "u64" || *ty == "i64") {
+        SEQLOCK_BACKEND
+    } else {
+        BACKEND
+    };
+
+    println!("cargo:rustc-env=ATOMIC_SHIM_REASON={}", reason);
+
+    if env::var_os(NO_WARN_ENV).is_none() {
+        println!(
+            "cargo:warning=atomic-shim: shim=active backend={} types={} reason={} target={} (set {} to silence)",
+            backend,
+            shimmed.join(","),
+            reason,
+            env::var("TARGET").unwrap_or_default(),
+            NO_WARN_ENV,
+        );
+    }
+}
+
+/// The spin limit from the environment, failing the build when it isn't a number up to `MAX_SPIN_LIMIT`.
+fn spin_limit() -> u32 {
+    let limit = match env::var(SPIN_LIMIT_ENV) {
+        Ok(limit) => limit,
+        Err(_) => return DEFAULT_SPIN_LIMIT,
+    };
+    match limit.trim().parse() {
+        Ok(limit) if limit <= MAX_SPIN_LIMIT => limit,
+        _ => panic!(
+            "{} must be a number of attempts from 0 to {}, not {:?}",
+            SPIN_LIMIT_ENV, MAX_SPIN_LIMIT, limit
+        ),
+    }
+}
+
+fn feature_enabled(feature: &str) -> bool {
+    let name = feature.to_uppercase().replace('-', "_");
+    env::var_os(format!("CARGO_FEATURE_{}", name)).is_some()
+}
+
+/// Whether the target is WebAssembly compiled without the `atomics` target feature.
+///
+/// It checks the `wasm` target family and the target features instead of the triple, so targets
+/// which enable the feature by default, such as `wasm32-wasip1-threads`, keep the native atomics.
+fn wasm_without_atomics() -> bool {
+    let family = env::var("CARGO_CFG_TARGET_FAMILY").unwrap_or_default();
+    let features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    family.split(',').any(|f| f == "wasm") && !features.split(',').any(|f| f == "atomics")
+}
+
+/// Whether the crate is built with `--cfg loom`, which shims every type, so loom sees every access through its lock.
+fn loom() -> bool {
+    env::var_os("CARGO_CFG_LOOM").is_some()
+}
+
+/// Whether the 64-bit shims use the sequence lock, which needs 32-bit atomics, outside of the test-only backends.
+fn seqlock() -> bool {
+    let arm_linux = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default() == "arm"
+        && env::var("CARGO_CFG_TARGET_OS").unwrap_or_default() == "linux";
+    !feature_enabled("replay")
+        && !feature_enabled("weak-memory")
+        && !arm_linux
+        && target_has_atomic("32")
+}
+
+fn target_has_atomic(width: &str) -> bool {
+    if loom() || wasm_without_atomics() {
+        return false;
+    }
+    match env::var("CARGO_CFG_TARGET_HAS_ATOMIC") {
+        Ok(widths) => widths.split(',').any(|w| w == width),
+        Err(_) => {
+            let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+            width != "64" || !FALLBACK_ARCHS.contains(&arch.as_str())
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Runs operations from several threads against shared shimmed atomics.
+//!
+//! The interleaving is up to the scheduler, so it checks results which don't depend on it:
+//! the additions and subtractions of every thread sum up on one atomic, and the "xor"s cancel out on another.
+//! Run it with `cargo +nightly fuzz run concurrent_ops --sanitizer thread` to catch data races as well.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use atomic_shim::AtomicU64;
+use libfuzzer_sys::fuzz_target;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+/// Number of threads running the operations, bounded to keep runs short.
+const MAX_THREADS: usize = 4;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    FetchAdd(u64),
+    FetchSub(u64),
+    /// Adds with a `compare_exchange_weak` loop, like `fetch_update` does.
+    CasAdd(u64),
+    FetchXor(u64),
+    /// Xors with a `compare_exchange` loop.
+    CasXor(u64),
+    Load,
+}
+
+#[derive(Default)]
+struct Expected {
+    sum: u64,
+    bits: u64,
+}
+
+fn run(op: &Op, sum: &AtomicU64, bits: &AtomicU64, expected: &mut Expected) {
+    match *op {
+        Op::FetchAdd(v) => {
+            sum.fetch_add(v, Ordering::AcqRel);
+            expected.sum = expected.sum.wrapping_add(v);
+        }
+        Op::FetchSub(v) => {
+            sum.fetch_sub(v, Ordering::AcqRel);
+            expected.sum = expected.sum.wrapping_sub(v);
+        }
+        Op::CasAdd(v) => {
+            let mut current = sum.load(Ordering::Acquire);
+            while let Err(actual) = sum.compare_exchange_weak(
+                current,
+                current.wrapping_add(v),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                current = actual;
+            }
+            expected.sum = expected.sum.wrapping_add(v);
+        }
+        Op::FetchXor(v) => {
+            bits.fetch_xor(v, Ordering::AcqRel);
+            expected.bits ^= v;
+        }
+        Op::CasXor(v) => {
+            let mut current = bits.load(Ordering::Acquire);
+            while let Err(actual) =
+                bits.compare_exchange(current, current ^ v, Ordering::AcqRel, Ordering::Acquire)
+            {
+                current = actual;
+            }
+            expected.bits ^= v;
+        }
+        Op::Load => {
+            sum.load(Ordering::Acquire);
+            bits.load(Ordering::Acquire);
+        }
+    }
+}
+
+fuzz_target!(|input: (u64, u64, Vec<Vec<Op>>)| {
+    let (initial_sum, initial_bits, mut threads) = input;
+    threads.truncate(MAX_THREADS);
+
+    let sum = AtomicU64::new(initial_sum);
+    let bits = AtomicU64::new(initial_bits);
+
+    let expected = thread::scope(|scope| {
+        let handles: Vec<_> = threads
+            .iter()
+            .map(|ops| {
+                let (sum, bits) = (&sum, &bits);
+                scope.spawn(move || {
+                    let mut expected = Expected::default();
+                    for op in ops {
+                        run(op, sum, bits, &mut expected);
+                    }
+                    expected
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .fold(Expected::default(), |total, handle| {
+                let expected = handle.join().unwrap();
+                Expected {
+                    sum: total.sum.wrapping_add(expected.sum),
+                    bits: total.bits ^ expected.bits,
+                }
+            })
+    });
+
+    assert_eq!(sum.into_inner(), initial_sum.wrapping_add(expected.sum));
+    assert_eq!(bits.into_inner(), initial_bits ^ expected.bits);
+});
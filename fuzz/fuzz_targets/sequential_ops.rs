@@ -0,0 +1,50 @@
+//! Runs a sequence of operations against the shim and `std`'s `AtomicU64`, asserting identical results.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use std::sync::atomic::Ordering;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Load,
+    Store(u64),
+    Swap(u64),
+    CompareExchange(u64, u64),
+    FetchAdd(u64),
+    FetchSub(u64),
+    FetchAnd(u64),
+    FetchNand(u64),
+    FetchOr(u64),
+    FetchXor(u64),
+}
+
+fuzz_target!(|input: (u64, Vec<Op>)| {
+    let (initial, ops) = input;
+    let shim = atomic_shim::AtomicU64::new(initial);
+    let std = std::sync::atomic::AtomicU64::new(initial);
+    let order = Ordering::SeqCst;
+
+    for op in ops {
+        let (actual, expected) = match op {
+            Op::Load => (Ok(shim.load(order)), Ok(std.load(order))),
+            Op::Store(v) => {
+                shim.store(v, order);
+                std.store(v, order);
+                (Ok(v), Ok(v))
+            }
+            Op::Swap(v) => (Ok(shim.swap(v, order)), Ok(std.swap(v, order))),
+            Op::CompareExchange(current, new) => (
+                shim.compare_exchange(current, new, order, order),
+                std.compare_exchange(current, new, order, order),
+            ),
+            Op::FetchAdd(v) => (Ok(shim.fetch_add(v, order)), Ok(std.fetch_add(v, order))),
+            Op::FetchSub(v) => (Ok(shim.fetch_sub(v, order)), Ok(std.fetch_sub(v, order))),
+            Op::FetchAnd(v) => (Ok(shim.fetch_and(v, order)), Ok(std.fetch_and(v, order))),
+            Op::FetchNand(v) => (Ok(shim.fetch_nand(v, order)), Ok(std.fetch_nand(v, order))),
+            Op::FetchOr(v) => (Ok(shim.fetch_or(v, order)), Ok(std.fetch_or(v, order))),
+            Op::FetchXor(v) => (Ok(shim.fetch_xor(v, order)), Ok(std.fetch_xor(v, order))),
+        };
+        assert_eq!(actual, expected, "after {:?}", op);
+    }
+});
@@ -0,0 +1,26 @@
+/* C interface of atomic-shim, available with the `ffi` feature. */
+#ifndef ATOMIC_SHIM_H
+#define ATOMIC_SHIM_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque 64-bit counter, allocated by atomic_shim_u64_new. */
+typedef struct atomic_shim_u64 atomic_shim_u64;
+
+atomic_shim_u64 *atomic_shim_u64_new(uint64_t value);
+uint64_t atomic_shim_u64_load(const atomic_shim_u64 *atomic);
+void atomic_shim_u64_store(const atomic_shim_u64 *atomic, uint64_t value);
+uint64_t atomic_shim_u64_fetch_add(const atomic_shim_u64 *atomic, uint64_t value);
+bool atomic_shim_u64_cas(const atomic_shim_u64 *atomic, uint64_t current, uint64_t new_value);
+void atomic_shim_u64_free(atomic_shim_u64 *atomic);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ATOMIC_SHIM_H */
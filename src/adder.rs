@@ -0,0 +1,134 @@
+//! Sums spread over several cells, for counters incremented by many threads at once.
+//!
+//! On the shim, every increment of an atomic takes its lock, so many threads incrementing the same counter
+//! take turns on it. `StripedU64` gives each thread one of several atomics, each on its own cache line,
+//! and only adds them together when the sum is read, like Java's `LongAdder`.
+
+use crate::{AtomicU64, Padded};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The most cells a default adder has.
+const MAX_CELLS: usize = 64;
+
+/// The index of the next thread, which spreads the threads over the cells in turn.
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A `u64` sum which threads add to on separate cells, for counters with many writers.
+///
+/// Each thread adds to its own cell, chosen when it first uses an adder, so writers on different cells don't
+/// contend. Reading the sum adds every cell, so it is slower than a load, and isn't a snapshot: the additions
+/// made while it is read may or may not be counted. The operations are `Relaxed`, and the sum wraps around on overflow.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::StripedU64;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let requests = Arc::new(StripedU64::new());
+/// let workers: Vec<_> = (0..32)
+///     .map(|_| {
+///         let requests = requests.clone();
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 requests.inc();
+///             }
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+///
+/// assert_eq!(requests.sum(), 3200);
+/// ```
+pub struct StripedU64 {
+    cells: Box<[Padded<AtomicU64>]>,
+}
+
+impl StripedU64 {
+    /// Creates an adder with a cell per available CPU, up to 64, rounded up to a power of two.
+    pub fn new() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        Self::with_cells(cpus.min(MAX_CELLS).next_power_of_two())
+    }
+
+    /// Creates an adder with `cells` cells, each taking a cache line.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cells` is 0.
+    pub fn with_cells(cells: usize) -> Self {
+        assert!(cells > 0, "a StripedU64 needs at least one cell");
+        Self {
+            cells: (0..cells).map(|_| Padded::new(AtomicU64::new(0))).collect(),
+        }
+    }
+
+    /// Returns the cell of the current thread.
+    fn cell(&self) -> &AtomicU64 {
+        let thread = THREAD.with(|thread| *thread);
+        &self.cells[thread % self.cells.len()]
+    }
+
+    /// Adds one to the sum.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Adds `n` to the sum.
+    pub fn add(&self, n: u64) {
+        self.cell().fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the sum of the cells.
+    pub fn sum(&self) -> u64 {
+        self.cells.iter().fold(0, |sum, cell| {
+            sum.wrapping_add(cell.load(Ordering::Relaxed))
+        })
+    }
+
+    /// Returns the sum of the cells, setting them to 0.
+    ///
+    /// Each cell is swapped on its own, so an addition made meanwhile is either returned or kept for the next sum.
+    pub fn sum_and_reset(&self) -> u64 {
+        self.cells.iter().fold(0, |sum, cell| {
+            sum.wrapping_add(cell.swap(0, Ordering::Relaxed))
+        })
+    }
+
+    /// Returns the number of cells.
+    pub fn cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Consumes the adder, returning the sum.
+    pub fn into_inner(self) -> u64 {
+        self.sum()
+    }
+}
+
+impl Default for StripedU64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StripedU64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.sum(), f)
+    }
+}
+
+impl fmt::Display for StripedU64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.sum(), f)
+    }
+}
@@ -0,0 +1,148 @@
+//! An `Arc` swapped atomically, for configurations reloaded while threads read them.
+//!
+//! Loading an `Arc` from an atomic pointer races with a writer swapping the pointer and dropping the last
+//! reference before the reader incremented the count. Crates such as `arc-swap` solve it with atomics wider
+//! than the targets of the shim have, so the cell counts its readers instead: each reader registers on the
+//! counter of the current epoch, and a writer flips the epoch after swapping the pointer, then waits for the
+//! readers of the previous epoch, which may have loaded the old pointer, before dropping it. Readers never wait
+//! for each other nor for writers, and every atomic is from the [`atomic`](crate::atomic) facade, so they are
+//! shimmed where the target lacks them.
+
+use crate::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use crate::Backoff;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// An `Arc<T>` which threads load and replace atomically, like `arc-swap`'s `ArcSwap`.
+///
+/// Loading clones the `Arc`, without blocking. Storing waits for the loads which may have read the previous
+/// `Arc`, which only take a few atomic operations, before dropping it. Every operation is sequentially consistent.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::ArcCell;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// struct Config {
+///     verbose: bool,
+/// }
+///
+/// let config = Arc::new(ArcCell::new(Arc::new(Config { verbose: false })));
+/// let worker = {
+///     let config = config.clone();
+///     thread::spawn(move || {
+///         // each request sees either configuration, never a dropped one
+///         let _verbose = config.load().verbose;
+///     })
+/// };
+///
+/// config.store(Arc::new(Config { verbose: true }));
+/// worker.join().unwrap();
+/// assert!(config.load().verbose);
+/// ```
+pub struct ArcCell<T> {
+    ptr: AtomicPtr<T>,
+    /// The parity of the epoch, flipped by every write.
+    epoch: AtomicUsize,
+    /// The number of readers which registered on each parity of the epoch.
+    readers: [AtomicUsize; 2],
+    /// Serializes the writers, so each waits for the readers of a single epoch.
+    writer: Mutex<()>,
+}
+
+// The cell holds an `Arc<T>`, and hands out clones of it to other threads, so it has the bounds of `Arc<T>`.
+unsafe impl<T: Send + Sync> Send for ArcCell<T> {}
+unsafe impl<T: Send + Sync> Sync for ArcCell<T> {}
+
+impl<T> ArcCell<T> {
+    /// Creates a cell holding `value`.
+    pub fn new(value: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Arc::into_raw(value) as *mut T),
+            epoch: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns a clone of the `Arc` held.
+    pub fn load(&self) -> Arc<T> {
+        loop {
+            let epoch = self.epoch.load(Ordering::SeqCst) & 1;
+            self.readers[epoch].fetch_add(1, Ordering::SeqCst);
+            // A writer which flipped the epoch after the reader read it may not wait for it.
+            if self.epoch.load(Ordering::SeqCst) & 1 != epoch {
+                self.readers[epoch].fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            let ptr = self.ptr.load(Ordering::SeqCst);
+            // The writer replacing the pointer waits for this reader before dropping the `Arc`.
+            let value = unsafe {
+                Arc::increment_strong_count(ptr);
+                Arc::from_raw(ptr)
+            };
+            self.readers[epoch].fetch_sub(1, Ordering::SeqCst);
+            return value;
+        }
+    }
+
+    /// Replaces the `Arc` held with `value`.
+    pub fn store(&self, value: Arc<T>) {
+        drop(self.swap(value));
+    }
+
+    /// Replaces the `Arc` held with `value`, returning the previous one.
+    ///
+    /// Waits for the loads which may still be cloning the previous `Arc`.
+    pub fn swap(&self, value: Arc<T>) -> Arc<T> {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let prev = self
+            .ptr
+            .swap(Arc::into_raw(value) as *mut T, Ordering::SeqCst);
+        // The readers registering from now on load the new pointer, and those before may have loaded `prev`.
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst) & 1;
+        let backoff = Backoff::new();
+        while self.readers[epoch].load(Ordering::SeqCst) != 0 {
+            backoff.snooze();
+        }
+        unsafe { Arc::from_raw(prev) }
+    }
+
+    /// Consumes the cell, returning the `Arc` held.
+    pub fn into_inner(mut self) -> Arc<T> {
+        // The null pointer left behind tells `drop` the `Arc` was taken.
+        let ptr = mem::replace(self.ptr.get_mut(), ptr::null_mut());
+        unsafe { Arc::from_raw(ptr) }
+    }
+}
+
+impl<T> Drop for ArcCell<T> {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        if !ptr.is_null() {
+            drop(unsafe { Arc::from_raw(ptr) });
+        }
+    }
+}
+
+impl<T: Default> Default for ArcCell<T> {
+    fn default() -> Self {
+        Self::new(Arc::default())
+    }
+}
+
+impl<T> From<Arc<T>> for ArcCell<T> {
+    fn from(value: Arc<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArcCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ArcCell").field(&self.load()).finish()
+    }
+}
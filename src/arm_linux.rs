@@ -0,0 +1,213 @@
+//! Runtime dispatch of the 64-bit shims on ARM Linux.
+//!
+//! Targets such as `armv5te` can't assume 64-bit atomics, but the same binary often runs on ARMv6K or newer
+//! CPUs, which have `LDREXD`/`STREXD`. The CPU is detected once, through `getauxval(AT_HWCAP)`: `HWCAP_TLS`
+//! is reported from ARMv6K on, the same revision which introduced `LDREXD`. On those CPUs, the operations use the
+//! kernel's `__kuser_cmpxchg64` helper, which the kernel implements with `LDREXD`/`STREXD`, and the lock is
+//! only used on older CPUs, or kernels without the helper (before Linux 3.1). The loads use `LDREXD` directly,
+//! as the helper would write the value back, racing with the stores and faulting on read-only mappings.
+
+use crate::shim::{acquire, diagnostics, unpoisoned, Bits64, Slot};
+use crossbeam_utils::sync::ShardedLock;
+use std::arch::asm;
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::{c_int, c_ulong};
+use std::ptr;
+use std::sync::atomic::{fence, AtomicU8, Ordering};
+
+/// Address of the version of the kernel user helpers.
+const KUSER_HELPER_VERSION: usize = 0xffff_0ffc;
+
+/// Address of `__kuser_cmpxchg64`, available from version 5 of the helpers.
+const KUSER_CMPXCHG64: usize = 0xffff_0f60;
+
+const AT_HWCAP: c_ulong = 16;
+const HWCAP_TLS: c_ulong = 1 << 15;
+
+const UNKNOWN: u8 = 0;
+const LOCK_FREE: u8 = 1;
+const LOCKED: u8 = 2;
+
+extern "C" {
+    fn getauxval(kind: c_ulong) -> c_ulong;
+}
+
+/// Whether the CPU and the kernel provide lock-free 64-bit atomics, detected on the first call.
+fn has_cmpxchg64() -> bool {
+    static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+    match STATE.load(Ordering::Relaxed) {
+        LOCK_FREE => true,
+        LOCKED => false,
+        _ => {
+            let lock_free = detect();
+            STATE.store(
+                if lock_free { LOCK_FREE } else { LOCKED },
+                Ordering::Relaxed,
+            );
+            lock_free
+        }
+    }
+}
+
+fn detect() -> bool {
+    let hwcap = unsafe { getauxval(AT_HWCAP) };
+    hwcap & HWCAP_TLS != 0
+        && unsafe { ptr::read_volatile(KUSER_HELPER_VERSION as *const c_int) } >= 5
+}
+
+/// Stores `new` at `ptr` if it holds `old`, returning whether it was stored.
+///
+/// # Safety
+///
+/// `ptr` must be valid and aligned to 8 bytes, and [`has_cmpxchg64`] must have returned `true`.
+unsafe fn cmpxchg64(ptr: *mut u64, old: u64, new: u64) -> bool {
+    let helper: unsafe extern "C" fn(*const u64, *const u64, *mut u64) -> c_int =
+        mem::transmute(KUSER_CMPXCHG64);
+    helper(&old, &new, ptr) == 0
+}
+
+/// Loads the value at `ptr` with a single `LDREXD`, which is atomic for aligned doublewords, without writing to it.
+///
+/// The instruction is encoded by hand, as the assembler rejects it for the ARM revisions of the target,
+/// with the address in `r2`, and the words at `ptr` and `ptr + 4` loaded into `r0` and `r1`.
+/// No store-exclusive follows, so the exclusive monitor it opens is simply left to the next `LDREXD` or `LDREX`.
+/// It is fenced on both sides, like the helper, so the loads stay sequentially consistent.
+///
+/// # Safety
+///
+/// The same as [`cmpxchg64`].
+unsafe fn load64(ptr: *mut u64) -> u64 {
+    let (first, second): (u32, u32);
+    fence(Ordering::SeqCst);
+    // ldrexd r0, r1, [r2]
+    #[cfg(not(target_feature = "thumb-mode"))]
+    asm!(
+        ".inst 0xe1b20f9f",
+        in("r2") ptr,
+        out("r0") first,
+        out("r1") second,
+        options(nostack, preserves_flags, readonly),
+    );
+    #[cfg(target_feature = "thumb-mode")]
+    asm!(
+        ".inst.w 0xe8d2017f",
+        in("r2") ptr,
+        out("r0") first,
+        out("r1") second,
+        options(nostack, preserves_flags, readonly),
+    );
+    fence(Ordering::SeqCst);
+    if cfg!(target_endian = "little") {
+        u64::from(second) << 32 | u64::from(first)
+    } else {
+        u64::from(first) << 32 | u64::from(second)
+    }
+}
+
+/// Storage of a 64-bit shim, accessed with `__kuser_cmpxchg64` when the CPU supports it, and under the lock otherwise.
+///
+/// The choice is made once per process, so every access to a value uses the same mechanism.
+#[repr(C, align(8))]
+pub(crate) struct Dispatch<T> {
+    value: UnsafeCell<u64>,
+    lock: ShardedLock<()>,
+    int: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Sync for Dispatch<T> {}
+
+impl<T: Bits64> Default for Dispatch<T> {
+    fn default() -> Self {
+        Slot::new(T::from_bits(0))
+    }
+}
+
+impl<T: Bits64> Slot<T> for Dispatch<T> {
+    /// The value, and the lock used on older CPUs, which doesn't hold a value.
+    const FOOTPRINT: usize = 8 + 2 * mem::size_of::<usize>();
+    // The kernel helper is only found at runtime.
+    const IS_LOCK_FREE: bool = false;
+
+    fn new(value: T) -> Self {
+        Dispatch {
+            value: UnsafeCell::new(value.to_bits()),
+            lock: ShardedLock::new(()),
+            int: PhantomData,
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        has_cmpxchg64()
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        if self.lock.is_poisoned() {
+            diagnostics::poisoned(self)
+        }
+        unsafe { &mut *(self.value.get() as *mut T) }
+    }
+
+    fn into_inner(self) -> T {
+        if self.lock.is_poisoned() {
+            diagnostics::poisoned_into_inner(&self)
+        }
+        T::from_bits(self.value.into_inner())
+    }
+
+    fn load(&self, _: Ordering) -> T {
+        if has_cmpxchg64() {
+            T::from_bits(unsafe { load64(self.value.get()) })
+        } else {
+            let _lock = unpoisoned(acquire(|| self.lock.try_read(), || self.lock.read()), self);
+            T::from_bits(unsafe { *self.value.get() })
+        }
+    }
+
+    fn update<F: Fn(T) -> T>(&self, _: Ordering, f: F) -> T {
+        if has_cmpxchg64() {
+            let ptr = self.value.get();
+            loop {
+                let prev = unsafe { load64(ptr) };
+                let next = f(T::from_bits(prev)).to_bits();
+                if unsafe { cmpxchg64(ptr, prev, next) } {
+                    return T::from_bits(prev);
+                }
+            }
+        } else {
+            let lock = unpoisoned(
+                acquire(|| self.lock.try_write(), || self.lock.write()),
+                self,
+            );
+            let _witness = diagnostics::Witness::new(self);
+            let section = diagnostics::Section::start();
+            let prev = unsafe { *self.value.get() };
+            unsafe { *self.value.get() = f(T::from_bits(prev)).to_bits() };
+            let slow = section.end();
+            drop(lock);
+            slow.report(self);
+            T::from_bits(prev)
+        }
+    }
+
+    fn snapshot(&self) -> T {
+        if has_cmpxchg64() {
+            T::from_bits(unsafe { load64(self.value.get()) })
+        } else {
+            let _lock = self.lock.read();
+            T::from_bits(unsafe { *self.value.get() })
+        }
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.lock.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {
+        if self.lock.is_poisoned() {
+            diagnostics::forget(self)
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! A fixed number of `u64` counters sharing a few locks, for histogram buckets and per-CPU tables.
+//!
+//! A shimmed atomic per counter brings a lock per counter, and an [`AtomicGroup`](crate::AtomicGroup) a single
+//! lock for all of them, which every thread counting then waits for. The counters of an array are spread over a
+//! few stripes of locks, so threads counting in different stripes don't wait for each other, and taking every
+//! stripe, in order, reads or resets all the counters at once.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The number of locks of an array, the counter at an index being guarded by the lock of that index modulo it.
+const STRIPES: usize = 8;
+
+/// An array of `N` counters, updated one at a time, and read or reset together.
+///
+/// Every operation takes a lock, so they are sequentially consistent, and the counters don't need the shim:
+/// the array works the same on every target, like an [`AtomicGroup`](crate::AtomicGroup), with less waiting
+/// between threads counting at different indexes.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64Array;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let buckets = Arc::new(AtomicU64Array::<4>::new());
+/// let recorders: Vec<_> = (0..4)
+///     .map(|bucket| {
+///         let buckets = buckets.clone();
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 buckets.fetch_add(bucket, 1);
+///             }
+///         })
+///     })
+///     .collect();
+/// for recorder in recorders {
+///     recorder.join().unwrap();
+/// }
+///
+/// assert_eq!(buckets.reset(), [100; 4]);
+/// assert_eq!(buckets.snapshot(), [0; 4]);
+/// ```
+pub struct AtomicU64Array<const N: usize> {
+    values: [UnsafeCell<u64>; N],
+    stripes: [Mutex<()>; STRIPES],
+}
+
+unsafe impl<const N: usize> Sync for AtomicU64Array<N> {}
+
+impl<const N: usize> AtomicU64Array<N> {
+    /// Creates an array of counters starting at 0.
+    pub fn new() -> Self {
+        Self::from_values([0; N])
+    }
+
+    /// Creates an array of counters starting at `values`.
+    pub fn from_values(values: [u64; N]) -> Self {
+        Self {
+            values: values.map(UnsafeCell::new),
+            stripes: Default::default(),
+        }
+    }
+
+    /// Returns the number of counters.
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    /// Returns whether the array has no counters.
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Runs `f` on the counter at `index`, under the lock of its stripe.
+    ///
+    /// No closure given by a caller runs under a lock, so a poisoned lock can't have left a counter half-updated.
+    fn with<R>(&self, index: usize, f: impl FnOnce(&mut u64) -> R) -> R {
+        let value = &self.values[index];
+        let _guard = self.stripes[index % STRIPES]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        f(unsafe { &mut *value.get() })
+    }
+
+    /// Takes the locks of every stripe, in order, so all the counters can be used together.
+    fn lock_all(&self) -> Vec<MutexGuard<'_, ()>> {
+        self.stripes
+            .iter()
+            .map(|stripe| stripe.lock().unwrap_or_else(PoisonError::into_inner))
+            .collect()
+    }
+
+    /// Returns the value of the counter at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds, as all the methods taking an index.
+    pub fn load(&self, index: usize) -> u64 {
+        self.with(index, |value| *value)
+    }
+
+    /// Sets the value of the counter at `index`.
+    pub fn store(&self, index: usize, value: u64) {
+        self.with(index, |prev| *prev = value)
+    }
+
+    /// Sets the value of the counter at `index`, returning the previous value.
+    pub fn swap(&self, index: usize, value: u64) -> u64 {
+        self.with(index, |prev| std::mem::replace(prev, value))
+    }
+
+    /// Adds to the counter at `index`, wrapping around on overflow, and returns the previous value.
+    pub fn fetch_add(&self, index: usize, value: u64) -> u64 {
+        self.with(index, |prev| {
+            let old = *prev;
+            *prev = old.wrapping_add(value);
+            old
+        })
+    }
+
+    /// Subtracts from the counter at `index`, wrapping around on overflow, and returns the previous value.
+    pub fn fetch_sub(&self, index: usize, value: u64) -> u64 {
+        self.with(index, |prev| {
+            let old = *prev;
+            *prev = old.wrapping_sub(value);
+            old
+        })
+    }
+
+    /// Sets the counter at `index` to `new` if it is `current`, returning the previous value,
+    /// as `Ok` if it was written, and as `Err` if it wasn't.
+    pub fn compare_exchange(&self, index: usize, current: u64, new: u64) -> Result<u64, u64> {
+        self.with(index, |prev| {
+            if *prev == current {
+                *prev = new;
+                Ok(current)
+            } else {
+                Err(*prev)
+            }
+        })
+    }
+
+    /// Returns the values of all the counters, read together.
+    pub fn snapshot(&self) -> [u64; N] {
+        let _guards = self.lock_all();
+        std::array::from_fn(|index| unsafe { *self.values[index].get() })
+    }
+
+    /// Sets all the counters to 0 together, returning their previous values.
+    pub fn reset(&self) -> [u64; N] {
+        let _guards = self.lock_all();
+        std::array::from_fn(|index| unsafe { std::mem::take(&mut *self.values[index].get()) })
+    }
+
+    /// Consumes the array, returning the values of its counters.
+    pub fn into_inner(self) -> [u64; N] {
+        self.values.map(UnsafeCell::into_inner)
+    }
+}
+
+impl<const N: usize> Default for AtomicU64Array<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> From<[u64; N]> for AtomicU64Array<N> {
+    fn from(values: [u64; N]) -> Self {
+        Self::from_values(values)
+    }
+}
+
+impl<const N: usize> fmt::Debug for AtomicU64Array<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.snapshot(), f)
+    }
+}
@@ -0,0 +1,44 @@
+//! Drop-in replacement for `std::sync::atomic`.
+//!
+//! Re-exports every type and function from `std::sync::atomic`, replacing the atomic types the
+//! target lacks with their shim, so a codebase can switch with a single import:
+//!
+//! ```
+//! use atomic_shim::atomic::{self, AtomicBool, AtomicU64, Ordering};
+//!
+//! let ready = AtomicBool::new(false);
+//! let counter = AtomicU64::new(0);
+//!
+//! counter.fetch_add(1, Ordering::Relaxed);
+//! atomic::fence(Ordering::Release);
+//! ready.store(true, Ordering::Relaxed);
+//! ```
+
+pub use std::sync::atomic::{compiler_fence, fence, Ordering};
+
+pub use crate::{AtomicI64, AtomicU64};
+
+macro_rules! reexport {
+    ($($fallback:ident => $atomic:ident),* $(,)?) => {
+        $(
+            #[cfg(not($fallback))]
+            pub use std::sync::atomic::$atomic;
+
+            #[cfg($fallback)]
+            pub use crate::shim::$atomic;
+        )*
+    };
+}
+
+reexport! {
+    atomic_shim_fallback_bool => AtomicBool,
+    atomic_shim_fallback_u8 => AtomicU8,
+    atomic_shim_fallback_i8 => AtomicI8,
+    atomic_shim_fallback_u16 => AtomicU16,
+    atomic_shim_fallback_i16 => AtomicI16,
+    atomic_shim_fallback_u32 => AtomicU32,
+    atomic_shim_fallback_i32 => AtomicI32,
+    atomic_shim_fallback_usize => AtomicUsize,
+    atomic_shim_fallback_isize => AtomicIsize,
+    atomic_shim_fallback_ptr => AtomicPtr,
+}
@@ -0,0 +1,210 @@
+//! An audit of the orderings used on each shimmed atomic, behind the `ordering-audit` feature.
+//!
+//! `std` can't tell whether the orderings of a program make sense together. On x86, a `Release` store which no
+//! `Acquire` load ever reads still looks like it works, until the code runs on ARM or POWER. With the feature,
+//! every shimmed integer records whether it was written with `Release` and read with `Acquire`, and when it is
+//! dropped with only one half of the pair, a [`Finding`] points at where it was created.
+//!
+//! The audit only sees the shimmed atomics, so tests enable it along with `mutex`, which shims the 64-bit types.
+//! Statics are never dropped, so they are never audited.
+#![cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+
+#[cfg(atomic_shim_fallback)]
+use crate::shim::Slot;
+use std::fmt;
+#[cfg(atomic_shim_fallback)]
+use std::mem::{self, ManuallyDrop};
+use std::panic::Location;
+#[cfg(atomic_shim_fallback)]
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// An ordering pattern which is likely a mistake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Suspicion {
+    /// The atomic was written with `Release`, but never read with `Acquire`, so the writes publish nothing.
+    ReleaseWithoutAcquire,
+    /// The atomic was read with `Acquire`, but never written with `Release`, so the reads synchronize with nothing.
+    AcquireWithoutRelease,
+}
+
+/// A shimmed atomic which used a suspicious ordering pattern, returned by [`take_findings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Finding {
+    suspicion: Suspicion,
+    location: &'static Location<'static>,
+}
+
+impl Finding {
+    /// The suspicious pattern.
+    pub fn suspicion(&self) -> Suspicion {
+        self.suspicion
+    }
+
+    /// Where the atomic was created.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pattern = match self.suspicion {
+            Suspicion::ReleaseWithoutAcquire => "written with Release, but never read with Acquire",
+            Suspicion::AcquireWithoutRelease => "read with Acquire, but never written with Release",
+        };
+        write!(f, "the atomic created at {} was {}", self.location, pattern)
+    }
+}
+
+static FINDINGS: Mutex<Vec<Finding>> = Mutex::new(Vec::new());
+
+/// Returns the findings of the atomics dropped since the last call, and clears them.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::audit::{self, Suspicion};
+/// use atomic_shim::AtomicU64;
+/// use std::sync::atomic::Ordering;
+///
+/// let ready = AtomicU64::new(0);
+/// ready.store(1, Ordering::Release);
+/// assert_eq!(ready.load(Ordering::Relaxed), 1);
+/// drop(ready);
+///
+/// if !AtomicU64::IS_LOCK_FREE {
+///     let findings = audit::take_findings();
+///     assert_eq!(findings[0].suspicion(), Suspicion::ReleaseWithoutAcquire);
+/// }
+/// ```
+pub fn take_findings() -> Vec<Finding> {
+    std::mem::take(&mut *FINDINGS.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+const RELEASED: usize = 1;
+const ACQUIRED: usize = 2;
+
+/// A storage, with the orderings used on it.
+pub(crate) struct Audited<S> {
+    slot: S,
+    orderings: AtomicUsize,
+    location: &'static Location<'static>,
+}
+
+impl<S> Audited<S> {
+    /// Records the halves of the pair an operation provides, given whether it reads and writes the value.
+    fn saw(&self, order: Ordering, reads: bool, writes: bool) {
+        let mut seen = 0;
+        if writes
+            && matches!(
+                order,
+                Ordering::Release | Ordering::AcqRel | Ordering::SeqCst
+            )
+        {
+            seen |= RELEASED;
+        }
+        if reads
+            && matches!(
+                order,
+                Ordering::Acquire | Ordering::AcqRel | Ordering::SeqCst
+            )
+        {
+            seen |= ACQUIRED;
+        }
+        if seen != 0 && self.orderings.load(Ordering::Relaxed) & seen != seen {
+            self.orderings.fetch_or(seen, Ordering::Relaxed);
+        }
+    }
+
+    fn audit(&self) {
+        let suspicion = match self.orderings.load(Ordering::Relaxed) {
+            RELEASED => Suspicion::ReleaseWithoutAcquire,
+            ACQUIRED => Suspicion::AcquireWithoutRelease,
+            _ => return,
+        };
+        FINDINGS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Finding {
+                suspicion,
+                location: self.location,
+            });
+    }
+}
+
+impl<S> Drop for Audited<S> {
+    fn drop(&mut self) {
+        self.audit();
+    }
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T, S: Slot<T>> Slot<T> for Audited<S> {
+    const FOOTPRINT: usize = S::FOOTPRINT + 2 * mem::size_of::<usize>();
+    const IS_LOCK_FREE: bool = S::IS_LOCK_FREE;
+
+    #[track_caller]
+    fn new(value: T) -> Self {
+        Audited {
+            slot: S::new(value),
+            orderings: AtomicUsize::new(0),
+            location: Location::caller(),
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        self.slot.is_lock_free()
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        self.slot.get_mut()
+    }
+
+    fn into_inner(self) -> T {
+        self.audit();
+        let this = ManuallyDrop::new(self);
+        unsafe { ptr::read(&this.slot) }.into_inner()
+    }
+
+    fn load(&self, order: Ordering) -> T {
+        self.saw(order, true, false);
+        self.slot.load(order)
+    }
+
+    fn update<F: Fn(T) -> T>(&self, order: Ordering, f: F) -> T {
+        self.saw(order, true, true);
+        self.slot.update(order, f)
+    }
+
+    fn apply<F: Fn(T) -> T>(&self, op: &'static str, order: Ordering, f: F) -> T {
+        self.saw(order, op != "store", true);
+        self.slot.apply(op, order, f)
+    }
+
+    fn compare_and_swap(&self, order: Ordering, current: T, new: T) -> T
+    where
+        T: Copy + PartialEq,
+    {
+        self.saw(order, true, true);
+        self.slot.compare_and_swap(order, current, new)
+    }
+
+    fn snapshot(&self) -> T {
+        self.slot.snapshot()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.slot.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {
+        self.slot.forget_poisoning()
+    }
+
+    #[cfg(feature = "history")]
+    fn history(&self) -> Vec<crate::history::Operation<T>> {
+        self.slot.history()
+    }
+}
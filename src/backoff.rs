@@ -0,0 +1,134 @@
+//! Waiting between the attempts of a compare-exchange loop, spinning, then yielding, then sleeping.
+//!
+//! Retrying a failed compare-exchange right away makes the threads fail each other again, and on the shim,
+//! take turns on the atomic's lock. The backoff spins exponentially longer first, as the other thread usually
+//! finishes within a few instructions, then yields to the threads which may hold the lock on a single core,
+//! such as the MIPS and ARM boards the shim runs on, and then sleeps for a growing while. The sleeps stay short,
+//! up to 64 microseconds, so a loop waiting for a change doesn't notice it a millisecond late. It never parks the
+//! thread, which would consume the `unpark` meant for the caller's own `park`.
+
+use std::cell::Cell;
+use std::fmt;
+use std::hint;
+use std::thread;
+use std::time::Duration;
+
+/// Number of steps spinning, each twice as long as the previous one.
+const SPIN_LIMIT: u32 = 6;
+
+/// Number of steps before sleeping, the ones after spinning yielding the thread.
+const YIELD_LIMIT: u32 = 10;
+
+/// Number of steps sleeping twice as long as the previous one, up to 64 microseconds.
+const SLEEP_LIMIT: u32 = 6;
+
+/// The progression of waits of a compare-exchange loop: spinning, then yielding, then sleeping.
+///
+/// The same backoff is used by the crate's own loops, such as
+/// [`fetch_update_with_backoff`](crate::Atomic64Ext::fetch_update_with_backoff).
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{AtomicU64, Backoff};
+/// use std::sync::atomic::Ordering;
+///
+/// fn fetch_double(atomic: &AtomicU64) -> u64 {
+///     let backoff = Backoff::new();
+///     let mut current = atomic.load(Ordering::Relaxed);
+///     loop {
+///         match atomic.compare_exchange_weak(current, current * 2, Ordering::AcqRel, Ordering::Relaxed) {
+///             Ok(previous) => return previous,
+///             Err(actual) => {
+///                 current = actual;
+///                 backoff.snooze();
+///             }
+///         }
+///     }
+/// }
+///
+/// let atomic = AtomicU64::new(21);
+/// assert_eq!(fetch_double(&atomic), 21);
+/// assert_eq!(atomic.load(Ordering::Relaxed), 42);
+/// ```
+#[derive(Default)]
+pub struct Backoff {
+    step: Cell<u32>,
+}
+
+impl Backoff {
+    /// Creates a backoff at its first step.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the progression again, such as after the loop made progress.
+    pub fn reset(&self) {
+        self.step.set(0);
+    }
+
+    /// Spins, twice as long as the previous call, up to 64 iterations.
+    ///
+    /// Suits loops which retry after another thread made progress, such as a failed compare-exchange.
+    pub fn spin(&self) {
+        let step = self.step.get().min(SPIN_LIMIT);
+        for _ in 0..1u32 << step {
+            hint::spin_loop();
+        }
+        if self.step.get() <= SPIN_LIMIT {
+            self.step.set(self.step.get() + 1);
+        }
+    }
+
+    /// Spins, then yields the thread, then sleeps, a little longer at every call, up to 64 microseconds.
+    ///
+    /// Suits loops waiting for another thread, which may not be running.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atomic_shim::Backoff;
+    /// use std::thread;
+    ///
+    /// // an unpark meant for the thread's own `park`
+    /// thread::current().unpark();
+    ///
+    /// let backoff = Backoff::new();
+    /// while !backoff.is_completed() {
+    ///     backoff.snooze();
+    /// }
+    /// backoff.snooze();
+    ///
+    /// // the backoff slept without consuming it, so this returns right away
+    /// thread::park();
+    /// ```
+    pub fn snooze(&self) {
+        let step = self.step.get();
+        if step <= SPIN_LIMIT {
+            for _ in 0..1u32 << step {
+                hint::spin_loop();
+            }
+        } else if step <= YIELD_LIMIT {
+            thread::yield_now();
+        } else {
+            let micros = 1u64 << (step - YIELD_LIMIT).min(SLEEP_LIMIT);
+            thread::sleep(Duration::from_micros(micros));
+        }
+        self.step.set(step.saturating_add(1));
+    }
+
+    /// Returns whether the backoff moved on to sleeping, when blocking on a notification,
+    /// such as with [`AtomicU64::wait`](crate::AtomicU64::wait), would be cheaper.
+    pub fn is_completed(&self) -> bool {
+        self.step.get() > YIELD_LIMIT
+    }
+}
+
+impl fmt::Debug for Backoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backoff")
+            .field("step", &self.step.get())
+            .field("is_completed", &self.is_completed())
+            .finish()
+    }
+}
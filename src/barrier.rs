@@ -0,0 +1,110 @@
+//! A barrier for a fixed number of threads, reusable round after round, in a single 64-bit atomic.
+//!
+//! The threads arriving count themselves in the low half of the atomic, and the last one clears the count and
+//! bumps the round in the high half with a single store, so the barrier is ready for the next round before the
+//! threads of this one leave. The others block with [`wait`](crate::AtomicU64::wait) until the round changes,
+//! and the last one wakes them with `notify_all`, so they sleep instead of spinning, on the shim too.
+
+use crate::AtomicU64;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// The bits of the atomic counting the threads arrived, the others counting the rounds.
+const COUNT_MASK: u64 = u32::MAX as u64;
+
+/// Blocks threads until a fixed number of them reached it, like `std::sync::Barrier`.
+///
+/// Every write a thread made before reaching the barrier is visible to all the threads once they leave it.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::Barrier;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let barrier = Arc::new(Barrier::new(4));
+/// let loaded = Arc::new(AtomicUsize::new(0));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let (barrier, loaded) = (barrier.clone(), loaded.clone());
+///         thread::spawn(move || {
+///             loaded.fetch_add(1, Ordering::Relaxed);
+///             // no worker starts before every one of them loaded its part
+///             let leader = barrier.wait().is_leader();
+///             assert_eq!(loaded.load(Ordering::Relaxed), 4);
+///             leader
+///         })
+///     })
+///     .collect();
+///
+/// let leaders: Vec<bool> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+/// assert_eq!(leaders.iter().filter(|leader| **leader).count(), 1);
+/// ```
+pub struct Barrier {
+    /// The round in the high half, and the threads arrived in it in the low half.
+    state: AtomicU64,
+    threads: u32,
+}
+
+impl Barrier {
+    /// Creates a barrier blocking `threads` threads, which lets every thread through if it is 0 or 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` doesn't fit in 32 bits.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            state: AtomicU64::new(0),
+            threads: u32::try_from(threads).expect("a Barrier blocks up to 2^32 - 1 threads"),
+        }
+    }
+
+    /// Blocks until all the threads reached the barrier, then lets them through together.
+    ///
+    /// The last thread to reach the barrier doesn't block, and is its leader for the round.
+    pub fn wait(&self) -> BarrierWaitResult {
+        let prev = self.state.fetch_add(1, Ordering::AcqRel);
+        let round = prev >> 32;
+        if (prev & COUNT_MASK) + 1 >= u64::from(self.threads) {
+            self.state
+                .store(round.wrapping_add(1) << 32, Ordering::Release);
+            self.state.notify_all();
+            return BarrierWaitResult { leader: true };
+        }
+        loop {
+            let state = self.state.load(Ordering::Acquire);
+            if state >> 32 != round {
+                return BarrierWaitResult { leader: false };
+            }
+            self.state.wait(state, Ordering::Acquire);
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Barrier")
+            .field("threads", &self.threads)
+            .field(
+                "arrived",
+                &(self.state.load(Ordering::Relaxed) & COUNT_MASK),
+            )
+            .finish()
+    }
+}
+
+/// What [`Barrier::wait`] returns, telling whether the thread is the leader of its round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Returns whether the thread was the last one to reach the barrier, a single one per round.
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+}
@@ -0,0 +1,68 @@
+//! Increments applied in batches, for pipelines flushing many of them at once.
+//!
+//! On the shim, every operation takes the atomic's lock, so adding the deltas of a tick one by one
+//! takes it as many times. The batches sum the deltas first, and take the lock of each atomic once.
+
+use crate::{AtomicI64, AtomicU64};
+use std::sync::atomic::Ordering;
+
+macro_rules! fetch_add_batch {
+    ($atomic:ident, $int:ident) => {
+        impl $atomic {
+            /// Adds every delta to the current value, returning the previous value.
+            ///
+            /// The sum wraps around on overflow, like `fetch_add`, and is added with a single operation,
+            /// with `Ordering::SeqCst`, so the shim takes its lock once.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let requests = ", stringify!($atomic), "::new(1);")]
+            /// assert_eq!(requests.fetch_add_batch(&[2, 3, 4]), 1);
+            /// assert_eq!(requests.load(Ordering::SeqCst), 10);
+            /// ```
+            pub fn fetch_add_batch(&self, deltas: &[$int]) -> $int {
+                let sum = deltas
+                    .iter()
+                    .fold(0, |sum: $int, delta| sum.wrapping_add(*delta));
+                self.fetch_add(sum, Ordering::SeqCst)
+            }
+        }
+    };
+}
+
+fetch_add_batch!(AtomicU64, u64);
+fetch_add_batch!(AtomicI64, i64);
+
+/// Adds each delta to its atomic, taking the lock of every atomic once on the shim.
+///
+/// The deltas of an atomic appearing more than once are summed, wrapping around on overflow,
+/// and added with a single `fetch_add`, with `Ordering::SeqCst`. The atomics are updated one after
+/// the other, so other threads may see some of them updated before the others.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64;
+/// use std::sync::atomic::Ordering;
+///
+/// let (hits, misses) = (AtomicU64::new(0), AtomicU64::new(0));
+/// atomic_shim::fetch_add_batch(&[(&hits, 1), (&misses, 1), (&hits, 2)]);
+/// assert_eq!(hits.load(Ordering::SeqCst), 3);
+/// assert_eq!(misses.load(Ordering::SeqCst), 1);
+/// ```
+pub fn fetch_add_batch(pairs: &[(&AtomicU64, u64)]) {
+    let mut pairs = pairs.to_vec();
+    pairs.sort_unstable_by_key(|(atomic, _)| *atomic as *const AtomicU64);
+
+    let mut pairs = pairs.into_iter().peekable();
+    while let Some((atomic, mut sum)) = pairs.next() {
+        while let Some((_, delta)) = pairs.next_if(|(next, _)| std::ptr::eq(*next, atomic)) {
+            sum = sum.wrapping_add(delta);
+        }
+        atomic.fetch_add(sum, Ordering::SeqCst);
+    }
+}
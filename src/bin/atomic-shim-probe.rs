@@ -0,0 +1,111 @@
+//! Prints which implementation `atomic-shim` selected for the current build, for platform bring-up.
+//!
+//! Build it for the target with `cargo run --features probe --bin atomic-shim-probe`, adding the same
+//! features as the application, as they can force the shim.
+
+use std::mem;
+
+/// Returns the runtime answer of the shim, as the `std` atomics don't have `is_lock_free`.
+macro_rules! lock_free {
+    ($fallback:ident, $value:expr) => {{
+        #[cfg($fallback)]
+        let lock_free = $value.is_lock_free();
+        #[cfg(not($fallback))]
+        let lock_free = true;
+        lock_free
+    }};
+}
+
+/// Name of the backend of a shimmed type, matching the one reported by the build script.
+fn backend(width: u32, lock_free: bool) -> &'static str {
+    if cfg!(all(feature = "weak-memory", not(loom))) {
+        "weak-memory"
+    } else if width == 64
+        && cfg!(all(
+            windows,
+            target_arch = "x86",
+            feature = "windows-intrinsics"
+        ))
+    {
+        "interlocked"
+    } else if width == 64 && lock_free && cfg!(all(target_arch = "arm", target_os = "linux")) {
+        "kuser-cmpxchg64"
+    } else if width == 64
+        && cfg!(all(
+            not(any(loom, feature = "replay")),
+            not(all(target_arch = "arm", target_os = "linux")),
+            target_has_atomic = "32",
+            not(all(target_family = "wasm", not(target_feature = "atomics")))
+        ))
+    {
+        "seqlock"
+    } else {
+        "sharded-lock"
+    }
+}
+
+fn row(name: &str, shimmed: bool, width: u32, size: usize, align: usize, lock_free: bool) {
+    let (path, backend) = if shimmed {
+        ("shim", backend(width, lock_free))
+    } else {
+        ("native", "std")
+    };
+    println!(
+        "{:<12} {:<7} {:<16} {:>4} {:>5} {:>9}",
+        name, path, backend, size, align, lock_free
+    );
+}
+
+macro_rules! probe {
+    ($($name:ident, $width:expr, $fallback:ident => $value:expr;)*) => {
+        $(
+            row(
+                stringify!($name),
+                cfg!($fallback),
+                $width,
+                mem::size_of::<atomic_shim::atomic::$name>(),
+                mem::align_of::<atomic_shim::atomic::$name>(),
+                lock_free!($fallback, $value),
+            );
+        )*
+    };
+}
+
+fn main() {
+    println!("target:  {}", env!("ATOMIC_SHIM_TARGET"));
+    println!(
+        "shim:    {}",
+        option_env!("ATOMIC_SHIM_REASON").unwrap_or("inactive")
+    );
+    println!();
+    println!(
+        "{:<12} {:<7} {:<16} {:>4} {:>5} {:>9}",
+        "type", "path", "backend", "size", "align", "lock-free"
+    );
+
+    probe! {
+        AtomicBool, 8, atomic_shim_fallback_bool => atomic_shim::atomic::AtomicBool::new(false);
+        AtomicU8, 8, atomic_shim_fallback_u8 => atomic_shim::atomic::AtomicU8::new(0);
+        AtomicI8, 8, atomic_shim_fallback_i8 => atomic_shim::atomic::AtomicI8::new(0);
+        AtomicU16, 16, atomic_shim_fallback_u16 => atomic_shim::atomic::AtomicU16::new(0);
+        AtomicI16, 16, atomic_shim_fallback_i16 => atomic_shim::atomic::AtomicI16::new(0);
+        AtomicU32, 32, atomic_shim_fallback_u32 => atomic_shim::atomic::AtomicU32::new(0);
+        AtomicI32, 32, atomic_shim_fallback_i32 => atomic_shim::atomic::AtomicI32::new(0);
+        AtomicU64, 64, atomic_shim_fallback_u64 => atomic_shim::atomic::AtomicU64::new(0);
+        AtomicI64, 64, atomic_shim_fallback_i64 => atomic_shim::atomic::AtomicI64::new(0);
+        AtomicUsize, usize::BITS, atomic_shim_fallback_usize => atomic_shim::atomic::AtomicUsize::new(0);
+        AtomicIsize, isize::BITS, atomic_shim_fallback_isize => atomic_shim::atomic::AtomicIsize::new(0);
+    }
+
+    row(
+        "AtomicPtr",
+        cfg!(atomic_shim_fallback_ptr),
+        usize::BITS,
+        mem::size_of::<atomic_shim::atomic::AtomicPtr<u8>>(),
+        mem::align_of::<atomic_shim::atomic::AtomicPtr<u8>>(),
+        lock_free!(
+            atomic_shim_fallback_ptr,
+            atomic_shim::atomic::AtomicPtr::<u8>::new(std::ptr::null_mut())
+        ),
+    );
+}
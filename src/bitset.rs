@@ -0,0 +1,185 @@
+//! A fixed number of bits set and cleared atomically, for tracking which slots of a pool are taken.
+//!
+//! The bits are packed in 64-bit atomics, the shim where the target lacks them, so setting one is a single `fetch_or`,
+//! and finding a clear slot checks 64 of them with each load.
+
+use crate::AtomicU64;
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// A set of bits, numbered from 0 to the capacity, each set and cleared atomically.
+///
+/// # Panics
+///
+/// The methods taking the index of a bit panic if it is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicBitSet;
+/// use std::sync::atomic::Ordering;
+///
+/// let slots = AtomicBitSet::new(100);
+/// assert!(!slots.test_and_set(70, Ordering::Acquire));
+/// assert!(slots.test_and_set(70, Ordering::Acquire));
+///
+/// // take the first free slots
+/// assert_eq!(slots.set_first_clear(Ordering::Acquire), Some(0));
+/// assert_eq!(slots.set_first_clear(Ordering::Acquire), Some(1));
+/// assert_eq!(slots.iter(Ordering::Acquire).collect::<Vec<_>>(), [0, 1, 70]);
+///
+/// slots.clear(0, Ordering::Release);
+/// assert_eq!(slots.count_ones(Ordering::Acquire), 2);
+/// ```
+pub struct AtomicBitSet {
+    words: Box<[AtomicU64]>,
+    capacity: usize,
+}
+
+impl AtomicBitSet {
+    /// Creates a set of `capacity` bits, all clear.
+    pub fn new(capacity: usize) -> Self {
+        let words = (0..capacity.div_ceil(64))
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        Self { words, capacity }
+    }
+
+    /// Returns the number of bits.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the word holding the bit at `index`, and the mask of the bit in it.
+    fn locate(&self, index: usize) -> (&AtomicU64, u64) {
+        assert!(
+            index < self.capacity,
+            "bit {} is out of bounds of a set of {} bits",
+            index,
+            self.capacity
+        );
+        (&self.words[index / 64], 1 << (index % 64))
+    }
+
+    /// Returns the mask of the bits of the word at `word` which are within the capacity.
+    fn mask(&self, word: usize) -> u64 {
+        match self.capacity - word * 64 {
+            bits if bits >= 64 => u64::MAX,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// Returns whether the bit at `index` is set.
+    ///
+    /// Like a `load`, `order` can't be `Release` or `AcqRel`.
+    pub fn test(&self, index: usize, order: Ordering) -> bool {
+        let (word, bit) = self.locate(index);
+        word.load(order) & bit != 0
+    }
+
+    /// Sets the bit at `index`.
+    pub fn set(&self, index: usize, order: Ordering) {
+        self.test_and_set(index, order);
+    }
+
+    /// Clears the bit at `index`.
+    pub fn clear(&self, index: usize, order: Ordering) {
+        self.test_and_clear(index, order);
+    }
+
+    /// Sets the bit at `index`, returning whether it was already set.
+    pub fn test_and_set(&self, index: usize, order: Ordering) -> bool {
+        let (word, bit) = self.locate(index);
+        word.fetch_or(bit, order) & bit != 0
+    }
+
+    /// Clears the bit at `index`, returning whether it was set.
+    pub fn test_and_clear(&self, index: usize, order: Ordering) -> bool {
+        let (word, bit) = self.locate(index);
+        word.fetch_and(!bit, order) & bit != 0
+    }
+
+    /// Sets the first clear bit, returning its index, or `None` if every bit is set.
+    ///
+    /// Threads racing for the same bit set different ones, so each gets a slot of its own.
+    pub fn set_first_clear(&self, order: Ordering) -> Option<usize> {
+        let load = match order {
+            Ordering::Release => Ordering::Relaxed,
+            Ordering::AcqRel => Ordering::Acquire,
+            order => order,
+        };
+        for (i, word) in self.words.iter().enumerate() {
+            let mask = self.mask(i);
+            let mut current = word.load(load);
+            while current & mask != mask {
+                let bit = (!current & mask).trailing_zeros();
+                let prev = word.fetch_or(1 << bit, order);
+                if prev & (1 << bit) == 0 {
+                    return Some(i * 64 + bit as usize);
+                }
+                current = prev;
+            }
+        }
+        None
+    }
+
+    /// Returns the number of set bits.
+    ///
+    /// Each word is loaded separately, so the count isn't a snapshot while other threads change the bits.
+    pub fn count_ones(&self, order: Ordering) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.load(order).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns an iterator over the indexes of the set bits, in increasing order.
+    ///
+    /// The iterator loads each word of 64 bits when it reaches it, so it sees the changes other threads make
+    /// to the bits it hasn't reached yet.
+    pub fn iter(&self, order: Ordering) -> BitSetIter<'_> {
+        BitSetIter {
+            set: self,
+            order,
+            word: 0,
+            bits: 0,
+        }
+    }
+}
+
+impl fmt::Debug for AtomicBitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter(Ordering::SeqCst)).finish()
+    }
+}
+
+/// An iterator over the indexes of the set bits of an [`AtomicBitSet`], returned by its `iter` method.
+pub struct BitSetIter<'a> {
+    set: &'a AtomicBitSet,
+    order: Ordering,
+    /// The index of the next word to load.
+    word: usize,
+    /// The set bits of the last word loaded, which the iterator didn't return yet.
+    bits: u64,
+}
+
+impl Iterator for BitSetIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            let word = self.set.words.get(self.word)?;
+            self.bits = word.load(self.order);
+            self.word += 1;
+        }
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some((self.word - 1) * 64 + bit)
+    }
+}
+
+impl fmt::Debug for BitSetIter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitSetIter").finish_non_exhaustive()
+    }
+}
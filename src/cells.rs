@@ -0,0 +1,135 @@
+//! Counter and gauge handles on the crate's atomics, for metrics exporters on targets without 64-bit atomics.
+//!
+//! The `metrics` facade implements its handles for `std`'s `AtomicU64`, which MIPS and PowerPC don't have, so
+//! exporters for them each wrap the shim the same way. The cells follow the semantics of the facade: a counter's
+//! `absolute` only moves it forward, and a gauge is an `f64`, stored as its bits. With `features = ["metrics"]`,
+//! they implement its `CounterFn` and `GaugeFn`, so they can back a `metrics::Counter` or `metrics::Gauge`.
+
+use crate::{Atomic64Ext, AtomicU64};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// A monotonic counter, with the operations of a `metrics` counter handle.
+///
+/// The operations are `Relaxed`, like a [`Counter`](crate::Counter)'s, and the counter wraps around on overflow.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::CounterCell;
+///
+/// let packets = CounterCell::new();
+/// packets.increment(3);
+///
+/// // synchronizing with the interface's own counter, which an older read can't move back
+/// packets.absolute(40);
+/// packets.absolute(35);
+/// assert_eq!(packets.get(), 40);
+/// ```
+#[derive(Default)]
+pub struct CounterCell {
+    value: AtomicU64,
+}
+
+impl CounterCell {
+    /// Creates a counter at 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` to the counter.
+    pub fn increment(&self, value: u64) {
+        self.value.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Sets the counter to `value`, unless it is already higher.
+    pub fn absolute(&self, value: u64) {
+        let _ =
+            self.value
+                .fetch_update_with_backoff(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                    if value > current {
+                        Some(value)
+                    } else {
+                        None
+                    }
+                });
+    }
+
+    /// Returns the value of the counter.
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for CounterCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CounterCell").field(&self.get()).finish()
+    }
+}
+
+/// A gauge holding an `f64`, with the operations of a `metrics` gauge handle.
+///
+/// The value is stored as its bits, so the increments and decrements are compare-exchange loops.
+/// The operations are `Relaxed`, like a [`Counter`](crate::Counter)'s.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::GaugeCell;
+///
+/// let connections = GaugeCell::new();
+/// connections.increment(2.0);
+/// connections.decrement(3.0);
+/// assert_eq!(connections.get(), -1.0);
+///
+/// connections.set(0.5);
+/// assert_eq!(connections.get(), 0.5);
+/// ```
+pub struct GaugeCell {
+    bits: AtomicU64,
+}
+
+impl GaugeCell {
+    /// Creates a gauge at 0.
+    pub fn new() -> Self {
+        Self {
+            bits: AtomicU64::new(0.0f64.to_bits()),
+        }
+    }
+
+    /// Adds `value` to the gauge, which may be negative.
+    pub fn increment(&self, value: f64) {
+        let _ = self
+            .bits
+            .fetch_update_with_backoff(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+    }
+
+    /// Subtracts `value` from the gauge.
+    pub fn decrement(&self, value: f64) {
+        self.increment(-value);
+    }
+
+    /// Sets the gauge to `value`.
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the value of the gauge.
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for GaugeCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for GaugeCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("GaugeCell").field(&self.get()).finish()
+    }
+}
@@ -0,0 +1,91 @@
+//! Counters which only need to count, with `Relaxed` operations.
+//!
+//! A counter read by a metrics exporter publishes no other data, so its increments need no ordering, yet code
+//! passes `Ordering::SeqCst` everywhere to be safe. `Counter` picks `Relaxed` once, which costs nothing on the
+//! shim, and avoids the fences `SeqCst` emits on ARM and POWER with the native atomics.
+
+use crate::AtomicU64;
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// A `u64` counter, incremented and read with `Ordering::Relaxed`.
+///
+/// The counter wraps around on overflow, like `fetch_add`. It doesn't order other memory operations,
+/// so use an [`AtomicU64`] with `Acquire` and `Release` to publish data with it.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::Counter;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let requests = Arc::new(Counter::new(0));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let requests = requests.clone();
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 requests.inc();
+///             }
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+///
+/// requests.add(10);
+/// assert_eq!(requests.get(), 410);
+/// ```
+#[derive(Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    /// Creates a counter starting at `value`.
+    pub fn new(value: u64) -> Self {
+        Self {
+            value: AtomicU64::new(value),
+        }
+    }
+
+    /// Adds one to the counter.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Adds `n` to the counter.
+    pub fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the value of the counter.
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Consumes the counter, returning its value.
+    pub fn into_inner(self) -> u64 {
+        self.value.into_inner()
+    }
+}
+
+impl From<u64> for Counter {
+    fn from(value: u64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.get(), f)
+    }
+}
+
+impl fmt::Display for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.get(), f)
+    }
+}
@@ -0,0 +1,143 @@
+//! A `cxx` bridge exposing `AtomicU64` to C++, with the API of `std::atomic<uint64_t>`,
+//! so C++ and Rust code sharing counters use the same implementation, and the same lock on the shim.
+//!
+//! The C++ header is generated from this file with the `cxxbridge` command:
+//! `cxxbridge src/cpp.rs --header > atomic_shim.rs.h`. The type is declared as `atomic_shim::AtomicU64`,
+//! created with `atomic_shim::new_atomic_u64`, and every operation uses the `seq_cst` memory order.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::cpp::new_atomic_u64;
+//!
+//! let counter = new_atomic_u64(1);
+//! assert_eq!(counter.fetch_add(2), 1);
+//!
+//! let mut expected = 3;
+//! assert!(counter.compare_exchange_strong(&mut expected, 5));
+//! assert!(!counter.compare_exchange_strong(&mut expected, 7));
+//! assert_eq!(expected, 5);
+//! ```
+
+use std::sync::atomic::Ordering;
+
+// cxx requires a newer Rust than the crate, so its generated code may use newer APIs.
+#[allow(clippy::incompatible_msrv)]
+#[cxx::bridge(namespace = "atomic_shim")]
+mod bridge {
+    extern "Rust" {
+        type AtomicU64;
+
+        fn new_atomic_u64(value: u64) -> Box<AtomicU64>;
+        fn is_lock_free(self: &AtomicU64) -> bool;
+        fn load(self: &AtomicU64) -> u64;
+        fn store(self: &AtomicU64, value: u64);
+        fn exchange(self: &AtomicU64, value: u64) -> u64;
+        fn compare_exchange_weak(self: &AtomicU64, expected: &mut u64, desired: u64) -> bool;
+        fn compare_exchange_strong(self: &AtomicU64, expected: &mut u64, desired: u64) -> bool;
+        fn fetch_add(self: &AtomicU64, value: u64) -> u64;
+        fn fetch_sub(self: &AtomicU64, value: u64) -> u64;
+        fn fetch_and(self: &AtomicU64, value: u64) -> u64;
+        fn fetch_or(self: &AtomicU64, value: u64) -> u64;
+        fn fetch_xor(self: &AtomicU64, value: u64) -> u64;
+    }
+}
+
+/// The `crate::AtomicU64` as seen from C++, with the methods of `std::atomic<uint64_t>`.
+#[derive(Debug, Default)]
+pub struct AtomicU64(crate::AtomicU64);
+
+/// Creates a new `AtomicU64`, boxed so C++ can own it as a `rust::Box`.
+pub fn new_atomic_u64(value: u64) -> Box<AtomicU64> {
+    Box::new(AtomicU64(crate::AtomicU64::new(value)))
+}
+
+impl AtomicU64 {
+    /// Returns whether the operations on this atomic are lock-free.
+    pub fn is_lock_free(&self) -> bool {
+        self.0.is_lock_free()
+    }
+
+    /// Loads the value.
+    pub fn load(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Stores the value.
+    pub fn store(&self, value: u64) {
+        self.0.store(value, Ordering::SeqCst)
+    }
+
+    /// Stores the value, returning the previous value.
+    pub fn exchange(&self, value: u64) -> u64 {
+        self.0.swap(value, Ordering::SeqCst)
+    }
+
+    /// Stores `desired` if the value is `expected`, and is allowed to spuriously fail.
+    ///
+    /// On failure, `expected` is updated with the current value, like on C++.
+    pub fn compare_exchange_weak(&self, expected: &mut u64, desired: u64) -> bool {
+        let result =
+            self.0
+                .compare_exchange_weak(*expected, desired, Ordering::SeqCst, Ordering::SeqCst);
+        update_expected(result, expected)
+    }
+
+    /// Stores `desired` if the value is `expected`.
+    ///
+    /// On failure, `expected` is updated with the current value, like on C++.
+    pub fn compare_exchange_strong(&self, expected: &mut u64, desired: u64) -> bool {
+        let result =
+            self.0
+                .compare_exchange(*expected, desired, Ordering::SeqCst, Ordering::SeqCst);
+        update_expected(result, expected)
+    }
+
+    /// Adds to the value, returning the previous value.
+    pub fn fetch_add(&self, value: u64) -> u64 {
+        self.0.fetch_add(value, Ordering::SeqCst)
+    }
+
+    /// Subtracts from the value, returning the previous value.
+    pub fn fetch_sub(&self, value: u64) -> u64 {
+        self.0.fetch_sub(value, Ordering::SeqCst)
+    }
+
+    /// Bitwise "and" with the value, returning the previous value.
+    pub fn fetch_and(&self, value: u64) -> u64 {
+        self.0.fetch_and(value, Ordering::SeqCst)
+    }
+
+    /// Bitwise "or" with the value, returning the previous value.
+    pub fn fetch_or(&self, value: u64) -> u64 {
+        self.0.fetch_or(value, Ordering::SeqCst)
+    }
+
+    /// Bitwise "xor" with the value, returning the previous value.
+    pub fn fetch_xor(&self, value: u64) -> u64 {
+        self.0.fetch_xor(value, Ordering::SeqCst)
+    }
+}
+
+/// Converts the result of a compare-exchange to the C++ convention.
+fn update_expected(result: Result<u64, u64>, expected: &mut u64) -> bool {
+    match result {
+        Ok(_) => true,
+        Err(actual) => {
+            *expected = actual;
+            false
+        }
+    }
+}
+
+impl From<crate::AtomicU64> for AtomicU64 {
+    fn from(atomic: crate::AtomicU64) -> Self {
+        AtomicU64(atomic)
+    }
+}
+
+impl From<AtomicU64> for crate::AtomicU64 {
+    fn from(atomic: AtomicU64) -> Self {
+        atomic.0
+    }
+}
@@ -0,0 +1,251 @@
+//! Diagnostics of the shim's locks, behind the `diagnostics` feature.
+//!
+//! A thread preempted while holding the lock of an atomic, such as by a higher priority task on a real-time
+//! kernel, stalls every other thread using the atomic. [`set_watchdog`] registers a callback, called when a
+//! write holds the lock longer than a threshold, with the atomic's address and the thread which held it.
+//!
+//! A thread panicking while holding the lock of an atomic poisons it, and every later operation on the atomic
+//! panics. Those panics report the thread which poisoned the lock, when, and its backtrace when `RUST_BACKTRACE`
+//! enables them, so the thread which corrupted the value can be found after the fact.
+//!
+//! The native atomics, and the lock-free backends of the shim, have no lock to watch.
+#![cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A write which held the lock of an atomic longer than the watchdog's threshold.
+#[derive(Clone, Debug)]
+pub struct SlowSection {
+    address: usize,
+    duration: Duration,
+    thread: Option<String>,
+}
+
+impl SlowSection {
+    /// The address of the atomic, to compare with `&atomic as *const _ as usize`.
+    ///
+    /// With the `registry` feature, it is the address of the atomic's storage, which is on the heap.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// How long the lock was held.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The name of the thread which held the lock, if it has one.
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+}
+
+type Callback = Arc<dyn Fn(&SlowSection) + Send + Sync>;
+
+/// The threshold, in microseconds, read by every write, so it doesn't share a lock between the threads.
+static THRESHOLD: AtomicU32 = AtomicU32::new(DISABLED);
+
+const DISABLED: u32 = u32::MAX;
+
+static CALLBACK: RwLock<Option<Callback>> = RwLock::new(None);
+
+/// Calls `callback` whenever a write holds the lock of a shimmed atomic for `threshold` or longer,
+/// replacing the previous watchdog.
+///
+/// The threshold is rounded down to microseconds. The callback runs on the thread which held the lock,
+/// after releasing it, so it may use the atomic.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::diagnostics::{self, SlowSection};
+/// use atomic_shim::AtomicU64;
+/// use std::sync::atomic::Ordering;
+/// use std::sync::mpsc;
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let sender = Mutex::new(sender);
+/// diagnostics::set_watchdog(Duration::from_millis(0), move |slow: &SlowSection| {
+///     sender.lock().unwrap().send(slow.duration()).unwrap();
+/// });
+///
+/// let counter = AtomicU64::new(0);
+/// counter.fetch_add(1, Ordering::SeqCst);
+/// diagnostics::clear_watchdog();
+///
+/// if !counter.is_lock_free() {
+///     assert!(receiver.try_recv().is_ok());
+/// }
+/// ```
+pub fn set_watchdog<F>(threshold: Duration, callback: F)
+where
+    F: Fn(&SlowSection) + Send + Sync + 'static,
+{
+    *CALLBACK.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(callback));
+    let micros = threshold.as_micros().min(u128::from(DISABLED - 1)) as u32;
+    THRESHOLD.store(micros, Ordering::Release);
+}
+
+/// Removes the watchdog, so the writes aren't timed anymore.
+pub fn clear_watchdog() {
+    THRESHOLD.store(DISABLED, Ordering::Release);
+    *CALLBACK.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Times a write of the shim, from taking the lock until releasing it.
+pub(crate) struct Section {
+    start: Option<(Instant, u32)>,
+}
+
+impl Section {
+    pub(crate) fn start() -> Self {
+        let threshold = THRESHOLD.load(Ordering::Acquire);
+        Section {
+            start: (threshold != DISABLED).then(|| (Instant::now(), threshold)),
+        }
+    }
+
+    /// Called just before releasing the lock.
+    pub(crate) fn end(self) -> Slow {
+        Slow {
+            duration: self.start.and_then(|(start, threshold)| {
+                let duration = start.elapsed();
+                (duration >= Duration::from_micros(u64::from(threshold))).then_some(duration)
+            }),
+        }
+    }
+}
+
+/// A write which may have been too slow, reported after releasing the lock.
+pub(crate) struct Slow {
+    duration: Option<Duration>,
+}
+
+impl Slow {
+    pub(crate) fn report<T>(self, atomic: &T) {
+        let duration = match self.duration {
+            Some(duration) => duration,
+            None => return,
+        };
+        let callback = CALLBACK
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(callback) = callback {
+            callback(&SlowSection {
+                address: atomic as *const T as usize,
+                duration,
+                thread: thread::current().name().map(String::from),
+            });
+        }
+    }
+}
+
+/// The panic which poisoned the lock of an atomic.
+struct Poisoning {
+    thread: Option<String>,
+    time: SystemTime,
+    backtrace: Backtrace,
+}
+
+impl fmt::Display for Poisoning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let since_epoch = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        write!(
+            f,
+            "poisoned by thread '{}' at {}.{:03}s since the Unix epoch",
+            self.thread.as_deref().unwrap_or("<unnamed>"),
+            since_epoch.as_secs(),
+            since_epoch.subsec_millis()
+        )?;
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, ", while using it from:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// The panics which poisoned the lock of an atomic, by the address of its storage. A lock stays poisoned, so an
+/// entry is only removed when the atomic is dropped, and another atomic can't find it at the same address.
+static POISONINGS: Mutex<Option<HashMap<usize, Poisoning>>> = Mutex::new(None);
+
+/// Records the panic unwinding through a write of the shim, which poisons the lock of the atomic.
+pub(crate) struct Witness {
+    address: usize,
+}
+
+impl Witness {
+    /// Called just after taking the lock.
+    pub(crate) fn new<T>(atomic: &T) -> Self {
+        Witness {
+            address: address(atomic),
+        }
+    }
+}
+
+impl Drop for Witness {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            return;
+        }
+        let poisoning = Poisoning {
+            thread: thread::current().name().map(String::from),
+            time: SystemTime::now(),
+            backtrace: Backtrace::capture(),
+        };
+        POISONINGS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert_with(HashMap::new)
+            .insert(self.address, poisoning);
+    }
+}
+
+/// Panics because the lock of `atomic` is poisoned, reporting the panic which poisoned it.
+pub(crate) fn poisoned<T>(atomic: &T) -> ! {
+    let poisonings = POISONINGS.lock().unwrap_or_else(PoisonError::into_inner);
+    let poisoning = poisonings
+        .as_ref()
+        .and_then(|poisonings| poisonings.get(&address(atomic)));
+    let message = describe(poisoning);
+    drop(poisonings);
+    panic!("{}", message)
+}
+
+/// Like [`poisoned`], for an atomic consumed by `into_inner`, so it forgets the panic which poisoned it.
+pub(crate) fn poisoned_into_inner<T>(atomic: &T) -> ! {
+    let poisoning = take(atomic);
+    panic!("{}", describe(poisoning.as_ref()))
+}
+
+/// Forgets the panic which poisoned the lock of `atomic`, as it is dropped.
+pub(crate) fn forget<T>(atomic: &T) {
+    take(atomic);
+}
+
+fn take<T>(atomic: &T) -> Option<Poisoning> {
+    POISONINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+        .and_then(|poisonings| poisonings.remove(&address(atomic)))
+}
+
+fn address<T>(atomic: &T) -> usize {
+    atomic as *const T as usize
+}
+
+fn describe(poisoning: Option<&Poisoning>) -> String {
+    match poisoning {
+        Some(poisoning) => format!("the atomic's lock is {}", poisoning),
+        None => String::from("the atomic's lock is poisoned"),
+    }
+}
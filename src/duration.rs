@@ -0,0 +1,88 @@
+//! A `Duration` stored in a 64-bit atomic, for accumulating the time spent in an operation.
+//!
+//! Metrics summing the time spent in requests otherwise convert every `Duration` to nanoseconds, and back when
+//! reading it. The atomic stores the nanoseconds itself, which last 584 years, and is the shim where the target
+//! lacks 64-bit atomics.
+
+use crate::AtomicU64;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// Returns the nanoseconds of `duration`, saturating at 584 years.
+pub(crate) fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// A `Duration`, loaded, stored and added to atomically, with nanosecond precision.
+///
+/// Durations longer than 584 years saturate when stored, and the additions wrap around past them, like `fetch_add`.
+/// The operations are `Relaxed`, like a [`Counter`](crate::Counter)'s.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicDuration;
+/// use std::time::Duration;
+///
+/// let busy = AtomicDuration::default();
+/// busy.fetch_add(Duration::from_millis(250));
+/// busy.fetch_add(Duration::from_micros(1500));
+/// assert_eq!(busy.load(), Duration::from_micros(251_500));
+/// ```
+#[derive(Default)]
+pub struct AtomicDuration {
+    nanos: AtomicU64,
+}
+
+impl AtomicDuration {
+    /// Creates an atomic holding `duration`.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            nanos: AtomicU64::new(nanos(duration)),
+        }
+    }
+
+    /// Returns the duration.
+    pub fn load(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+
+    /// Stores `duration`.
+    pub fn store(&self, duration: Duration) {
+        self.nanos.store(nanos(duration), Ordering::Relaxed);
+    }
+
+    /// Stores `duration`, returning the previous duration.
+    pub fn swap(&self, duration: Duration) -> Duration {
+        Duration::from_nanos(self.nanos.swap(nanos(duration), Ordering::Relaxed))
+    }
+
+    /// Adds `duration`, returning the previous duration.
+    pub fn fetch_add(&self, duration: Duration) -> Duration {
+        Duration::from_nanos(self.nanos.fetch_add(nanos(duration), Ordering::Relaxed))
+    }
+
+    /// Subtracts `duration`, returning the previous duration.
+    pub fn fetch_sub(&self, duration: Duration) -> Duration {
+        Duration::from_nanos(self.nanos.fetch_sub(nanos(duration), Ordering::Relaxed))
+    }
+
+    /// Consumes the atomic, returning the duration.
+    pub fn into_inner(self) -> Duration {
+        Duration::from_nanos(self.nanos.into_inner())
+    }
+}
+
+impl From<Duration> for AtomicDuration {
+    fn from(duration: Duration) -> Self {
+        Self::new(duration)
+    }
+}
+
+impl fmt::Debug for AtomicDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(), f)
+    }
+}
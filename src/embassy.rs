@@ -0,0 +1,205 @@
+//! 64-bit atomics guarded by an `embassy-sync` raw mutex, instead of `std` locks.
+//!
+//! On chips without 64-bit atomics, the shim's `ShardedLock` needs `std`, and can deadlock when an
+//! interrupt handler touches a value held by the code it interrupted. These types take the
+//! `RawMutex` as a parameter instead, so they cooperate with embassy's critical sections:
+//! use `CriticalSectionRawMutex` for values shared with interrupts, or `NoopRawMutex` for
+//! values only shared between tasks of the same executor.
+//!
+//! Like the shim, the operations ignore the `Ordering` argument, as every access is serialized by the mutex.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::embassy::AtomicU64;
+//! use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+//! use std::sync::atomic::Ordering;
+//!
+//! static TICKS: AtomicU64<CriticalSectionRawMutex> = AtomicU64::new(0);
+//!
+//! TICKS.fetch_add(1, Ordering::Relaxed);
+//! assert_eq!(TICKS.load(Ordering::Relaxed), 1);
+//! ```
+
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+macro_rules! embassy_atomic {
+    ($atomic:ident, $int:ident) => {
+        /// An integer type which can be shared between tasks and interrupts, guarded by the raw mutex `M`.
+        pub struct $atomic<M: RawMutex> {
+            value: Mutex<M, Cell<$int>>,
+        }
+
+        impl<M: RawMutex> $atomic<M> {
+            /// Whether the operations are lock-free, which is never the case, as they run under the mutex.
+            pub const IS_LOCK_FREE: bool = false;
+
+            /// Creates a new atomic integer.
+            ///
+            /// It is a `const fn`, so the atomic can be used in a `static`.
+            pub const fn new(v: $int) -> Self {
+                Self {
+                    value: Mutex::new(Cell::new(v)),
+                }
+            }
+
+            /// Returns whether the operations are lock-free, which is never the case.
+            pub fn is_lock_free(&self) -> bool {
+                Self::IS_LOCK_FREE
+            }
+
+            /// Returns a mutable reference to the underlying integer.
+            ///
+            /// This is safe because the mutable reference guarantees that no other threads are concurrently accessing the atomic data.
+            pub fn get_mut(&mut self) -> &mut $int {
+                self.value.get_mut().get_mut()
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> $int {
+                self.value.into_inner().into_inner()
+            }
+
+            /// Runs `f` on the value while holding the mutex, storing the value it returns,
+            /// and returns the previous value.
+            fn update(&self, f: impl FnOnce($int) -> $int) -> $int {
+                self.value.lock(|cell| {
+                    let prev = cell.get();
+                    cell.set(f(prev));
+                    prev
+                })
+            }
+
+            /// Loads a value from the atomic integer.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn load(&self, _: Ordering) -> $int {
+                self.value.lock(Cell::get)
+            }
+
+            /// Stores a value into the atomic integer.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn store(&self, value: $int, _: Ordering) {
+                self.value.lock(|cell| cell.set(value))
+            }
+
+            /// Stores a value into the atomic integer, returning the previous value.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn swap(&self, value: $int, _: Ordering) -> $int {
+                self.value.lock(|cell| cell.replace(value))
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the `current` value.
+            ///
+            /// The return value is a result indicating whether the new value was written and containing the previous value.
+            /// On success this value is guaranteed to be equal to `current`.
+            ///
+            /// It ignores the Ordering arguments, as the access happens under the mutex.
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                _: Ordering,
+                _: Ordering,
+            ) -> Result<$int, $int> {
+                self.value.lock(|cell| {
+                    let prev = cell.get();
+                    if prev == current {
+                        cell.set(new);
+                        Ok(prev)
+                    } else {
+                        Err(prev)
+                    }
+                })
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the `current` value.
+            ///
+            /// It never fails spuriously, as the access happens under the mutex.
+            ///
+            /// It ignores the Ordering arguments, as the access happens under the mutex.
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.compare_exchange(current, new, success, failure)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn fetch_add(&self, val: $int, _: Ordering) -> $int {
+                self.update(|prev| prev.wrapping_add(val))
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn fetch_sub(&self, val: $int, _: Ordering) -> $int {
+                self.update(|prev| prev.wrapping_sub(val))
+            }
+
+            /// Bitwise "and" with the current value, returning the previous value.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn fetch_and(&self, val: $int, _: Ordering) -> $int {
+                self.update(|prev| prev & val)
+            }
+
+            /// Bitwise "nand" with the current value, returning the previous value.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn fetch_nand(&self, val: $int, _: Ordering) -> $int {
+                self.update(|prev| !(prev & val))
+            }
+
+            /// Bitwise "or" with the current value, returning the previous value.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn fetch_or(&self, val: $int, _: Ordering) -> $int {
+                self.update(|prev| prev | val)
+            }
+
+            /// Bitwise "xor" with the current value, returning the previous value.
+            ///
+            /// It ignores the Ordering argument, as the access happens under the mutex.
+            pub fn fetch_xor(&self, val: $int, _: Ordering) -> $int {
+                self.update(|prev| prev ^ val)
+            }
+        }
+
+        impl<M: RawMutex> Default for $atomic<M> {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+
+        impl<M: RawMutex> From<$int> for $atomic<M> {
+            fn from(value: $int) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl<M: RawMutex> fmt::Debug for $atomic<M> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+    };
+}
+
+embassy_atomic!(AtomicU64, u64);
+embassy_atomic!(AtomicI64, i64);
@@ -0,0 +1,151 @@
+//! Tracking of the readers of a read-mostly structure, so a writer knows when the readers of an old version left.
+//!
+//! Readers pin the current epoch, counting themselves among the readers of its parity, and the writer moves to
+//! the next epoch, then waits until no reader of the previous one is left, like the grace period of RCU. It takes
+//! two counters and the epoch, all the crate's atomics, where `crossbeam-epoch` needs atomics the targets of the
+//! shim may not have. The writer blocks with [`wait`](crate::AtomicU64::wait) until the last reader leaves.
+
+use crate::AtomicU64;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, PoisonError};
+
+/// The epoch of a read-mostly structure, pinned by its readers, and advanced by its writers to wait for them.
+///
+/// A writer unpublishes the old version of the data, such as by swapping a pointer, then calls
+/// [`wait_for_quiescence`](EpochCounter::wait_for_quiescence), after which no reader still holds the old version,
+/// and it can be freed. Every operation is sequentially consistent.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::atomic::AtomicPtr;
+/// use atomic_shim::EpochCounter;
+/// use std::sync::atomic::Ordering;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let epochs = Arc::new(EpochCounter::new());
+/// let routes = Arc::new(AtomicPtr::new(Box::into_raw(Box::new(vec![1, 2]))));
+/// let reader = {
+///     let (epochs, routes) = (epochs.clone(), routes.clone());
+///     thread::spawn(move || {
+///         for _ in 0..100 {
+///             let pinned = epochs.pin();
+///             // the writer doesn't free the routes while they are pinned
+///             let current = unsafe { &*routes.load(Ordering::SeqCst) };
+///             assert!(!current.is_empty());
+///             pinned.unpin();
+///         }
+///     })
+/// };
+///
+/// let old = routes.swap(Box::into_raw(Box::new(vec![3])), Ordering::SeqCst);
+/// epochs.wait_for_quiescence();
+/// drop(unsafe { Box::from_raw(old) });
+///
+/// reader.join().unwrap();
+/// drop(unsafe { Box::from_raw(routes.load(Ordering::SeqCst)) });
+/// ```
+pub struct EpochCounter {
+    epoch: AtomicU64,
+    /// The readers pinning the epochs of each parity.
+    readers: [AtomicU64; 2],
+    /// Taken by writers, so that each of them waits for the readers of a single parity.
+    writer: Mutex<()>,
+}
+
+impl EpochCounter {
+    /// Creates a counter at epoch 0, without readers.
+    pub fn new() -> Self {
+        Self {
+            epoch: AtomicU64::new(0),
+            readers: [AtomicU64::new(0), AtomicU64::new(0)],
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns the current epoch.
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    /// Pins the current epoch, until the returned guard is unpinned or dropped.
+    pub fn pin(&self) -> EpochGuard<'_> {
+        loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let parity = (epoch & 1) as usize;
+            self.readers[parity].fetch_add(1, Ordering::SeqCst);
+            // A writer which advanced the epoch meanwhile may not have seen this reader.
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                return EpochGuard {
+                    counter: self,
+                    parity,
+                };
+            }
+            self.leave(parity);
+        }
+    }
+
+    /// Uncounts a reader of `parity`, waking the writer waiting for the last one.
+    fn leave(&self, parity: usize) {
+        if self.readers[parity].fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.readers[parity].notify_all();
+        }
+    }
+
+    /// Advances the epoch, then blocks until every reader pinning the previous one unpinned it.
+    ///
+    /// The readers pinning the new epoch don't delay it, so it returns while readers come and go. It must not
+    /// be called while the thread pins the epoch, as it would wait for itself.
+    pub fn wait_for_quiescence(&self) {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+        let readers = &self.readers[(epoch & 1) as usize];
+        loop {
+            let count = readers.load(Ordering::SeqCst);
+            if count == 0 {
+                return;
+            }
+            readers.wait(count, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Default for EpochCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for EpochCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EpochCounter")
+            .field("epoch", &self.epoch())
+            .finish()
+    }
+}
+
+/// An epoch pinned by a reader of an [`EpochCounter`], unpinned when dropped.
+#[must_use = "the epoch is unpinned as soon as the guard is dropped"]
+pub struct EpochGuard<'a> {
+    counter: &'a EpochCounter,
+    parity: usize,
+}
+
+impl EpochGuard<'_> {
+    /// Unpins the epoch, like dropping the guard.
+    pub fn unpin(self) {}
+}
+
+impl Drop for EpochGuard<'_> {
+    fn drop(&mut self) {
+        self.counter.leave(self.parity);
+    }
+}
+
+impl fmt::Debug for EpochGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EpochGuard").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,115 @@
+//! Events of the shim, emitted through `tracing`, `log` or `metrics`, with the features of the same names.
+//!
+//! A lock still busy after spinning blocks the thread within a `blocked` span, a thread failing many
+//! compare-exchanges in a row reports a storm, and a poisoned lock is reported before the operation panics.
+//! The `log` feature, lighter, warns on poisoned locks and, at most every few seconds, on the blocked threads.
+//! The `metrics` feature counts the busy locks, the blocked threads, the failed compare-exchanges and the poisoned locks.
+
+use std::cell::Cell;
+#[cfg(feature = "log")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LockResult;
+#[cfg(feature = "log")]
+use std::sync::Mutex;
+#[cfg(feature = "log")]
+use std::time::{Duration, Instant};
+
+/// How many compare-exchanges in a row a thread fails before reporting a storm.
+const STORM: u32 = 64;
+
+thread_local! {
+    /// The compare-exchanges this thread failed since its last success or storm.
+    static FAILURES: Cell<u32> = const { Cell::new(0) };
+}
+
+/// The least time between two warnings of blocked threads, so a contended atomic doesn't flood the log.
+#[cfg(feature = "log")]
+const WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The threads which blocked on a lock since the last warning.
+#[cfg(feature = "log")]
+static BLOCKED: AtomicUsize = AtomicUsize::new(0);
+
+/// When the last warning of blocked threads was logged.
+#[cfg(feature = "log")]
+static LAST_WARNING: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Reports a lock found busy, before spinning on it.
+pub(crate) fn contended() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("atomic_shim_contended_total").increment(1);
+}
+
+/// Blocks on a lock which was still busy after `spins` attempts.
+#[cfg_attr(
+    not(any(feature = "tracing", feature = "log")),
+    allow(unused_variables)
+)]
+pub(crate) fn blocking<G>(spins: u32, lock: impl FnOnce() -> LockResult<G>) -> LockResult<G> {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("atomic_shim_blocked_total").increment(1);
+    #[cfg(feature = "log")]
+    warn_blocked(spins);
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!("blocked", spins);
+    #[cfg(feature = "tracing")]
+    let _entered = span.enter();
+    #[cfg(feature = "tracing")]
+    tracing::debug!("the lock of a shimmed atomic is contended, blocking on it");
+    lock()
+}
+
+/// Warns about the threads which blocked on a lock, unless it was done recently.
+///
+/// A thread already logging the warning isn't waited for.
+#[cfg(feature = "log")]
+fn warn_blocked(spins: u32) {
+    BLOCKED.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut last) = LAST_WARNING.try_lock() {
+        let now = Instant::now();
+        let due = match *last {
+            Some(last) => now.duration_since(last) >= WARNING_INTERVAL,
+            None => true,
+        };
+        if due {
+            *last = Some(now);
+            log::warn!(
+                "{} operations on shimmed atomics blocked on a lock still busy after {} attempts, since the last warning",
+                BLOCKED.swap(0, Ordering::Relaxed),
+                spins
+            );
+        }
+    }
+}
+
+/// Reports that the lock of an atomic is poisoned.
+pub(crate) fn poisoned() {
+    #[cfg(feature = "tracing")]
+    tracing::error!("the lock of a shimmed atomic is poisoned");
+    #[cfg(feature = "log")]
+    log::warn!("the lock of a shimmed atomic is poisoned");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("atomic_shim_poisoned_total").increment(1);
+}
+
+/// Counts the compare-exchanges of the current thread, reporting the storms of failures.
+pub(crate) fn compare_exchanged(succeeded: bool) {
+    #[cfg(feature = "metrics")]
+    if !succeeded {
+        metrics::counter!("atomic_shim_cas_failures_total").increment(1);
+    }
+    let _ = FAILURES.try_with(|failures| {
+        if succeeded {
+            failures.set(0);
+        } else if failures.get() + 1 == STORM {
+            failures.set(0);
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                failures = STORM,
+                "compare-exchanges on a shimmed atomic keep failing, the atomic is contended"
+            );
+        } else {
+            failures.set(failures.get() + 1);
+        }
+    });
+}
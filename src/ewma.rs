@@ -0,0 +1,131 @@
+//! An exponentially-weighted moving average of a rate of events, for load shedding and metrics.
+//!
+//! Events are counted in an atomic, and every interval, the count is folded into the average, like the meters
+//! of Dropwizard Metrics. The average is an `f64` stored as its bits, and the thread moving the time of the last
+//! fold forward with a compare-exchange is the one folding the count, so threads don't need a ticker thread,
+//! and the folds use the same atomics as the shim where the target lacks 64-bit ones.
+
+use crate::duration::nanos;
+use crate::{Atomic64Ext, AtomicU64};
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+/// The average rate of events per second, weighted towards the recent ones, over a window.
+///
+/// The count is folded into the average every `interval`, by the next thread updating or reading it. The weight
+/// of an event decays by a factor of e every window, like in the load averages of Unix. Until the first fold, the
+/// rate is 0.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::Ewma;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// // the requests per second over about a minute, folded every 50 milliseconds
+/// let requests = Ewma::with_interval(Duration::from_secs(60), Duration::from_millis(50));
+/// requests.update(10);
+///
+/// thread::sleep(Duration::from_millis(50));
+/// assert!(requests.rate() > 0.0);
+/// ```
+pub struct Ewma {
+    /// The events since the last fold.
+    uncounted: AtomicU64,
+    /// The bits of the average rate, an `f64`, NaN before the first fold.
+    rate: AtomicU64,
+    /// The nanoseconds since `start` of the last fold.
+    last_fold: AtomicU64,
+    /// The weight of each fold.
+    alpha: f64,
+    /// The nanoseconds between two folds.
+    interval: u64,
+    start: Instant,
+}
+
+impl Ewma {
+    /// Creates an average over `window`, folded every 5 seconds, like Dropwizard's meters.
+    pub fn new(window: Duration) -> Self {
+        Self::with_interval(window, Duration::from_secs(5))
+    }
+
+    /// Creates an average over `window`, folded every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn with_interval(window: Duration, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "the interval of an Ewma can't be zero");
+        Self {
+            uncounted: AtomicU64::new(0),
+            rate: AtomicU64::new(f64::NAN.to_bits()),
+            last_fold: AtomicU64::new(0),
+            alpha: 1.0 - (-interval.as_secs_f64() / window.as_secs_f64()).exp(),
+            interval: nanos(interval),
+            start: Instant::now(),
+        }
+    }
+
+    /// Records `n` events.
+    pub fn update(&self, n: u64) {
+        self.fold_if_due();
+        self.uncounted.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the average rate, in events per second.
+    pub fn rate(&self) -> f64 {
+        self.fold_if_due();
+        let rate = f64::from_bits(self.rate.load(Ordering::SeqCst));
+        if rate.is_nan() {
+            0.0
+        } else {
+            rate
+        }
+    }
+
+    /// Folds the count into the average if an interval passed since the last fold.
+    fn fold_if_due(&self) {
+        let now = nanos(self.start.elapsed());
+        let last = self.last_fold.load(Ordering::SeqCst);
+        let folds = now.saturating_sub(last) / self.interval;
+        if folds == 0 {
+            return;
+        }
+        // Only the thread moving the last fold forward folds, once per interval passed.
+        let next = last + folds * self.interval;
+        if self
+            .last_fold
+            .compare_exchange(last, next, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            self.fold(folds);
+        }
+    }
+
+    /// Folds the count into the average, then `folds - 1` intervals without events.
+    fn fold(&self, folds: u64) {
+        let count = self.uncounted.swap(0, Ordering::Relaxed);
+        let instant = count as f64 * 1e9 / self.interval as f64;
+        let decay = (1.0 - self.alpha).powi(i32::try_from(folds - 1).unwrap_or(i32::MAX));
+        let _ = self
+            .rate
+            .fetch_update_with_backoff(Ordering::SeqCst, Ordering::SeqCst, |bits| {
+                let rate = f64::from_bits(bits);
+                let rate = if rate.is_nan() {
+                    instant
+                } else {
+                    rate + self.alpha * (instant - rate)
+                };
+                Some((rate * decay).to_bits())
+            });
+    }
+}
+
+impl fmt::Debug for Ewma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ewma").field("rate", &self.rate()).finish()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{AtomicI64, AtomicU64, Backoff};
+use std::sync::atomic::Ordering;
+
+/// Helpers available on both `atomic_shim` and `std::sync::atomic` 64-bit integers,
+/// so downstream code calls the same methods regardless of which implementation is active.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{Atomic64Ext, AtomicU64};
+/// use std::sync::atomic::Ordering;
+///
+/// let counter = AtomicU64::new(1);
+/// counter.set(5);
+/// assert_eq!(counter.get(), 5);
+///
+/// let doubled = counter.fetch_update_with_backoff(Ordering::SeqCst, Ordering::SeqCst, |v| Some(v * 2));
+/// assert_eq!(doubled, Ok(5));
+/// assert_eq!(counter.get(), 10);
+/// ```
+pub trait Atomic64Ext {
+    /// The integer type stored by the atomic.
+    type Int;
+
+    /// Loads the value with `Ordering::SeqCst`.
+    fn get(&self) -> Self::Int;
+
+    /// Stores the value with `Ordering::SeqCst`.
+    fn set(&self, value: Self::Int);
+
+    /// Fetches the value, and applies a function to it that returns an optional new value.
+    ///
+    /// Behaves like `std`'s `fetch_update`, but waits with a [`Backoff`] between failed attempts,
+    /// spinning exponentially longer, then yielding and sleeping, to reduce contention.
+    ///
+    /// Returns `Ok(previous_value)` if the function returned `Some(_)`, else `Err(previous_value)`.
+    fn fetch_update_with_backoff<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: F,
+    ) -> Result<Self::Int, Self::Int>
+    where
+        F: FnMut(Self::Int) -> Option<Self::Int>;
+}
+
+macro_rules! atomic_64_ext {
+    ($atomic:ty, $int:ident) => {
+        impl Atomic64Ext for $atomic {
+            type Int = $int;
+
+            fn get(&self) -> $int {
+                self.load(Ordering::SeqCst)
+            }
+
+            fn set(&self, value: $int) {
+                self.store(value, Ordering::SeqCst)
+            }
+
+            fn fetch_update_with_backoff<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                let mut prev = self.load(fetch_order);
+                let backoff = Backoff::new();
+                while let Some(next) = f(prev) {
+                    match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
+                        Ok(value) => return Ok(value),
+                        Err(actual) => {
+                            prev = actual;
+                            backoff.snooze();
+                        }
+                    }
+                }
+                Err(prev)
+            }
+        }
+    };
+}
+
+atomic_64_ext!(AtomicU64, u64);
+atomic_64_ext!(AtomicI64, i64);
+
+#[cfg(target_has_atomic = "64")]
+atomic_64_ext!(std::sync::atomic::AtomicU64, u64);
+
+#[cfg(target_has_atomic = "64")]
+atomic_64_ext!(std::sync::atomic::AtomicI64, i64);
@@ -0,0 +1,92 @@
+//! `extern "C"` functions to share a 64-bit counter with C code, through the same implementation as the Rust side.
+//!
+//! The counter is allocated by Rust, and handed to C as an opaque pointer, which must be released with
+//! [`atomic_shim_u64_free`]. Every operation uses `Ordering::SeqCst`.
+//! A matching header is available at `include/atomic_shim.h`.
+//!
+//! The symbols are exported by any `staticlib` or `cdylib` which depends on this crate with the `ffi` feature,
+//! such as the one linking the Rust side of the firmware.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::ffi::*;
+//!
+//! unsafe {
+//!     let counter = atomic_shim_u64_new(1);
+//!     assert_eq!(atomic_shim_u64_fetch_add(counter, 2), 1);
+//!     assert!(atomic_shim_u64_cas(counter, 3, 5));
+//!     assert_eq!(atomic_shim_u64_load(counter), 5);
+//!     atomic_shim_u64_free(counter);
+//! }
+//! ```
+
+use crate::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Allocates a new counter holding `value`.
+///
+/// The returned pointer must be released with [`atomic_shim_u64_free`].
+#[no_mangle]
+pub extern "C" fn atomic_shim_u64_new(value: u64) -> *mut AtomicU64 {
+    Box::into_raw(Box::new(AtomicU64::new(value)))
+}
+
+/// Loads the value of the counter.
+///
+/// # Safety
+///
+/// `atomic` must be a pointer returned by [`atomic_shim_u64_new`], which was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn atomic_shim_u64_load(atomic: *const AtomicU64) -> u64 {
+    (*atomic).load(Ordering::SeqCst)
+}
+
+/// Stores `value` into the counter.
+///
+/// # Safety
+///
+/// `atomic` must be a pointer returned by [`atomic_shim_u64_new`], which was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn atomic_shim_u64_store(atomic: *const AtomicU64, value: u64) {
+    (*atomic).store(value, Ordering::SeqCst)
+}
+
+/// Adds `value` to the counter, returning the previous value.
+///
+/// # Safety
+///
+/// `atomic` must be a pointer returned by [`atomic_shim_u64_new`], which was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn atomic_shim_u64_fetch_add(atomic: *const AtomicU64, value: u64) -> u64 {
+    (*atomic).fetch_add(value, Ordering::SeqCst)
+}
+
+/// Stores `new` into the counter if it holds `current`, returning whether it was stored.
+///
+/// # Safety
+///
+/// `atomic` must be a pointer returned by [`atomic_shim_u64_new`], which was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn atomic_shim_u64_cas(
+    atomic: *const AtomicU64,
+    current: u64,
+    new: u64,
+) -> bool {
+    (*atomic)
+        .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+}
+
+/// Releases a counter allocated by [`atomic_shim_u64_new`]. Null pointers are ignored.
+///
+/// # Safety
+///
+/// `atomic` must be null, or a pointer returned by [`atomic_shim_u64_new`], which was not freed yet.
+/// It must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn atomic_shim_u64_free(atomic: *mut AtomicU64) {
+    if !atomic.is_null() {
+        drop(Box::from_raw(atomic));
+    }
+}
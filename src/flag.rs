@@ -0,0 +1,62 @@
+//! A boolean flag with the operations of C++'s `std::atomic_flag`, for handshakes between threads.
+//!
+//! The flag is an [`AtomicBool`](crate::atomic::AtomicBool) of the [`atomic`](crate::atomic) facade, so it is
+//! lock-free where the target has atomic booleans, and shimmed where it doesn't.
+
+use crate::atomic::{AtomicBool, Ordering};
+use std::fmt;
+
+/// A flag which is either set or clear, like C++'s `std::atomic_flag`.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicFlag;
+/// use std::sync::atomic::Ordering;
+///
+/// let busy = AtomicFlag::new();
+/// assert!(!busy.test_and_set(Ordering::Acquire));
+/// assert!(busy.test_and_set(Ordering::Acquire));
+/// assert!(busy.test(Ordering::Relaxed));
+///
+/// busy.clear(Ordering::Release);
+/// assert!(!busy.test(Ordering::Relaxed));
+/// ```
+#[derive(Default)]
+pub struct AtomicFlag {
+    value: AtomicBool,
+}
+
+impl AtomicFlag {
+    /// Creates a clear flag.
+    pub fn new() -> Self {
+        Self {
+            value: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the flag, returning whether it was already set.
+    pub fn test_and_set(&self, order: Ordering) -> bool {
+        self.value.swap(true, order)
+    }
+
+    /// Clears the flag.
+    ///
+    /// Like a `store`, `order` can't be `Acquire` or `AcqRel`.
+    pub fn clear(&self, order: Ordering) {
+        self.value.store(false, order)
+    }
+
+    /// Returns whether the flag is set.
+    ///
+    /// Like a `load`, `order` can't be `Release` or `AcqRel`.
+    pub fn test(&self, order: Ordering) -> bool {
+        self.value.load(order)
+    }
+}
+
+impl fmt::Debug for AtomicFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.test(Ordering::SeqCst), f)
+    }
+}
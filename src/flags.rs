@@ -0,0 +1,172 @@
+//! Sets of `bitflags` flags changed atomically, over the atomic of the width of their bits.
+//!
+//! Code keeping flags in an atomic converts them to their bits around every `fetch_or` and `fetch_and`, and
+//! back, which is easy to get wrong, such as by forgetting to negate the flags to remove. `AtomicFlags` does the
+//! conversions, and picks the atomic of the [`atomic`](crate::atomic) facade matching the bits, such as an
+//! `AtomicU8` for `u8` flags, so the flags are shimmed only where the target lacks that width.
+
+use crate::atomic::{
+    AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32, AtomicU64,
+    AtomicU8, AtomicUsize, Ordering,
+};
+use crate::AtomicInt;
+use bitflags::{Bits, Flags};
+use std::fmt;
+use std::marker::PhantomData;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The bits of flags which can be stored in an [`AtomicFlags`], implemented by the integers of every width
+/// the [`atomic`](crate::atomic) facade has.
+///
+/// The trait is sealed, and can't be implemented outside of this crate.
+pub trait FlagBits: sealed::Sealed + Bits {
+    /// The atomic storing the bits.
+    type Atomic: AtomicInt<Self>;
+}
+
+macro_rules! flag_bits {
+    ($($int:ident => $atomic:ident),*) => {
+        $(
+            impl sealed::Sealed for $int {}
+
+            impl FlagBits for $int {
+                type Atomic = $atomic;
+            }
+        )*
+    };
+}
+
+flag_bits!(
+    u8 => AtomicU8,
+    i8 => AtomicI8,
+    u16 => AtomicU16,
+    i16 => AtomicI16,
+    u32 => AtomicU32,
+    i32 => AtomicI32,
+    u64 => AtomicU64,
+    i64 => AtomicI64,
+    usize => AtomicUsize,
+    isize => AtomicIsize
+);
+
+/// A set of flags defined with `bitflags`, inserted, removed and toggled atomically.
+///
+/// The methods changing the flags return the flags held before, like the `fetch_*` methods of the atomics.
+/// The bits which aren't flags are kept as they are, like `bitflags`' `from_bits_retain`.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicFlags;
+/// use std::sync::atomic::Ordering;
+///
+/// bitflags::bitflags! {
+///     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///     struct Status: u8 {
+///         const LINK_UP = 1;
+///         const CARRIER = 1 << 1;
+///     }
+/// }
+///
+/// let status = AtomicFlags::new(Status::empty());
+/// status.insert(Status::LINK_UP | Status::CARRIER, Ordering::AcqRel);
+/// status.remove(Status::CARRIER, Ordering::AcqRel);
+/// assert!(status.contains(Status::LINK_UP, Ordering::Acquire));
+/// assert_eq!(status.load(Ordering::Acquire), Status::LINK_UP);
+/// ```
+pub struct AtomicFlags<F: Flags>
+where
+    F::Bits: FlagBits,
+{
+    bits: <F::Bits as FlagBits>::Atomic,
+    _marker: PhantomData<fn() -> F>,
+}
+
+impl<F: Flags> AtomicFlags<F>
+where
+    F::Bits: FlagBits,
+{
+    /// Creates a set holding `flags`.
+    pub fn new(flags: F) -> Self {
+        Self {
+            bits: AtomicInt::new(flags.bits()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the flags.
+    ///
+    /// Like a `load`, `order` can't be `Release` or `AcqRel`.
+    pub fn load(&self, order: Ordering) -> F {
+        F::from_bits_retain(self.bits.load(order))
+    }
+
+    /// Replaces the flags with `flags`.
+    ///
+    /// Like a `store`, `order` can't be `Acquire` or `AcqRel`.
+    pub fn store(&self, flags: F, order: Ordering) {
+        self.bits.store(flags.bits(), order)
+    }
+
+    /// Replaces the flags with `flags`, returning the previous flags.
+    pub fn swap(&self, flags: F, order: Ordering) -> F {
+        F::from_bits_retain(self.bits.swap(flags.bits(), order))
+    }
+
+    /// Sets `flags`, returning the previous flags.
+    pub fn insert(&self, flags: F, order: Ordering) -> F {
+        F::from_bits_retain(self.bits.fetch_or(flags.bits(), order))
+    }
+
+    /// Clears `flags`, returning the previous flags.
+    pub fn remove(&self, flags: F, order: Ordering) -> F {
+        F::from_bits_retain(self.bits.fetch_and(!flags.bits(), order))
+    }
+
+    /// Flips `flags`, returning the previous flags.
+    pub fn toggle(&self, flags: F, order: Ordering) -> F {
+        F::from_bits_retain(self.bits.fetch_xor(flags.bits(), order))
+    }
+
+    /// Returns whether every one of `flags` is set.
+    ///
+    /// Like a `load`, `order` can't be `Release` or `AcqRel`.
+    pub fn contains(&self, flags: F, order: Ordering) -> bool {
+        self.load(order).contains(flags)
+    }
+
+    /// Consumes the set, returning the flags.
+    pub fn into_inner(self) -> F {
+        F::from_bits_retain(self.bits.into_inner())
+    }
+}
+
+impl<F: Flags> Default for AtomicFlags<F>
+where
+    F::Bits: FlagBits,
+{
+    fn default() -> Self {
+        Self::new(F::empty())
+    }
+}
+
+impl<F: Flags> From<F> for AtomicFlags<F>
+where
+    F::Bits: FlagBits,
+{
+    fn from(flags: F) -> Self {
+        Self::new(flags)
+    }
+}
+
+impl<F: Flags + fmt::Debug> fmt::Debug for AtomicFlags<F>
+where
+    F::Bits: FlagBits,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(Ordering::SeqCst), f)
+    }
+}
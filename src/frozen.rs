@@ -0,0 +1,91 @@
+//! Values which are only written during startup, and read without any locking afterwards.
+//!
+//! Once an atomic won't be written anymore, `freeze` consumes it, and the frozen value is read as a plain
+//! integer, which needs no lock on the shim. Sharing it between threads, such as in an `Arc` or a `static`
+//! initialized once, publishes it like any other immutable value.
+
+use crate::{AtomicI64, AtomicU64};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+macro_rules! frozen {
+    ($frozen:ident, $atomic:ident, $int:ident) => {
+        #[doc = concat!("The value of an [`", stringify!($atomic), "`] which can't be written anymore, read without locking.")]
+        ///
+        #[doc = concat!("Created by [`", stringify!($atomic), "::freeze`].")]
+        #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $frozen {
+            value: $int,
+        }
+
+        impl $frozen {
+            /// Returns the value.
+            pub const fn get(&self) -> $int {
+                self.value
+            }
+
+            /// Returns the value, like the atomic's `load`, so the reads don't change when freezing it.
+            ///
+            /// The ordering is ignored, as the value can't change.
+            pub const fn load(&self, _: Ordering) -> $int {
+                self.value
+            }
+        }
+
+        impl $atomic {
+            /// Consumes the atomic, returning its value, which is then read without locking.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            /// use std::sync::Arc;
+            /// use std::thread;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let limit = ", stringify!($atomic), "::new(0);")]
+            /// limit.store(100, Ordering::SeqCst);
+            ///
+            /// let limit = Arc::new(limit.freeze());
+            /// let reader = {
+            ///     let limit = limit.clone();
+            ///     thread::spawn(move || limit.get())
+            /// };
+            /// assert_eq!(reader.join().unwrap(), 100);
+            /// assert_eq!(limit.load(Ordering::Relaxed), 100);
+            /// ```
+            pub fn freeze(self) -> $frozen {
+                $frozen {
+                    value: self.into_inner(),
+                }
+            }
+        }
+
+        impl From<$atomic> for $frozen {
+            fn from(atomic: $atomic) -> Self {
+                atomic.freeze()
+            }
+        }
+
+        impl From<$frozen> for $atomic {
+            fn from(frozen: $frozen) -> Self {
+                $atomic::new(frozen.value)
+            }
+        }
+
+        impl fmt::Debug for $frozen {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.value, f)
+            }
+        }
+
+        impl fmt::Display for $frozen {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.value, f)
+            }
+        }
+    };
+}
+
+frozen!(FrozenU64, AtomicU64, u64);
+frozen!(FrozenI64, AtomicI64, i64);
@@ -0,0 +1,161 @@
+//! Integers sharing a single lock, so they can be read together consistently.
+//!
+//! Independent atomics are read one after the other, so the buckets of a histogram scraped while being
+//! incremented may add up to a count which never existed. The members of a group are updated under the
+//! group's lock, one at a time or together, and a snapshot reads all of them under it too.
+
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A fixed number of `u64` counters behind a single lock, with consistent snapshots of all of them.
+///
+/// Every operation takes the lock, so they are sequentially consistent, and the members don't need
+/// the shim: the group works the same on every target. The indexes of the members start at 0.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicGroup;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let buckets = Arc::new(AtomicGroup::from_values(vec![10, 0, 0]));
+/// let mover = {
+///     let buckets = buckets.clone();
+///     thread::spawn(move || {
+///         for i in 0..100 {
+///             buckets.update(|buckets| {
+///                 if buckets[i % 3] > 0 {
+///                     buckets[i % 3] -= 1;
+///                     buckets[(i + 1) % 3] += 1;
+///                 }
+///             });
+///         }
+///     })
+/// };
+///
+/// for _ in 0..100 {
+///     // the observations move between the buckets, but never go missing from a snapshot
+///     assert_eq!(buckets.snapshot().iter().sum::<u64>(), 10);
+/// }
+/// mover.join().unwrap();
+/// ```
+pub struct AtomicGroup {
+    values: Mutex<Box<[u64]>>,
+}
+
+impl AtomicGroup {
+    /// Creates a group of `len` members, starting at 0.
+    pub fn new(len: usize) -> Self {
+        Self::from_values(vec![0; len])
+    }
+
+    /// Creates a group with a member per value.
+    pub fn from_values(values: impl Into<Box<[u64]>>) -> Self {
+        Self {
+            values: Mutex::new(values.into()),
+        }
+    }
+
+    /// A poisoned lock only means a closure given to `update` panicked, leaving the updates it made, so the group stays usable.
+    fn lock(&self) -> MutexGuard<'_, Box<[u64]>> {
+        self.values.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the number of members.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns whether the group has no members.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value of the member at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds, as all the methods taking an index.
+    pub fn load(&self, index: usize) -> u64 {
+        self.lock()[index]
+    }
+
+    /// Sets the value of the member at `index`.
+    pub fn store(&self, index: usize, value: u64) {
+        self.lock()[index] = value;
+    }
+
+    /// Sets the value of the member at `index`, returning the previous value.
+    pub fn swap(&self, index: usize, value: u64) -> u64 {
+        std::mem::replace(&mut self.lock()[index], value)
+    }
+
+    /// Adds to the member at `index`, wrapping around on overflow, and returns the previous value.
+    pub fn fetch_add(&self, index: usize, value: u64) -> u64 {
+        let mut values = self.lock();
+        let prev = values[index];
+        values[index] = prev.wrapping_add(value);
+        prev
+    }
+
+    /// Subtracts from the member at `index`, wrapping around on overflow, and returns the previous value.
+    pub fn fetch_sub(&self, index: usize, value: u64) -> u64 {
+        let mut values = self.lock();
+        let prev = values[index];
+        values[index] = prev.wrapping_sub(value);
+        prev
+    }
+
+    /// Sets the member at `index` to `new` if it is `current`, returning the previous value,
+    /// as `Ok` if it was written, and as `Err` if it wasn't.
+    pub fn compare_exchange(&self, index: usize, current: u64, new: u64) -> Result<u64, u64> {
+        let mut values = self.lock();
+        let prev = values[index];
+        if prev == current {
+            values[index] = new;
+            Ok(prev)
+        } else {
+            Err(prev)
+        }
+    }
+
+    /// Updates several members at once, with `f` receiving all of them, and returns its result.
+    ///
+    /// No snapshot sees the members between the updates `f` makes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atomic_shim::AtomicGroup;
+    ///
+    /// let buckets = AtomicGroup::from_values(vec![1, 0]);
+    /// buckets.update(|buckets| {
+    ///     buckets[0] -= 1;
+    ///     buckets[1] += 1;
+    /// });
+    /// assert_eq!(buckets.snapshot(), [0, 1]);
+    /// ```
+    pub fn update<R>(&self, f: impl FnOnce(&mut [u64]) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    /// Returns the values of all the members, read together.
+    pub fn snapshot(&self) -> Vec<u64> {
+        self.lock().to_vec()
+    }
+
+    /// Consumes the group, returning the values of its members.
+    pub fn into_inner(self) -> Vec<u64> {
+        self.values
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .into_vec()
+    }
+}
+
+impl fmt::Debug for AtomicGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.snapshot(), f)
+    }
+}
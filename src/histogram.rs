@@ -0,0 +1,124 @@
+//! A histogram of `u64` observations over fixed buckets, such as request latencies for a metrics exporter.
+//!
+//! The counts of the buckets and the sum of the observations are members of an [`AtomicGroup`], so an observation
+//! updates all of them under the group's lock, and a scrape reads a snapshot whose counts add up to its total.
+
+use crate::AtomicGroup;
+use std::fmt;
+
+/// A histogram counting observations in buckets whose upper bounds are given when creating it.
+///
+/// A value falls in the first bucket whose bound it doesn't exceed, like Prometheus' `le` buckets, or in a last
+/// bucket for the values above every bound. The counts and the sum wrap around on overflow.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicHistogram;
+///
+/// // latencies in milliseconds
+/// let latencies = AtomicHistogram::new(vec![10, 100, 1000]);
+/// for latency in [3, 10, 42, 5000] {
+///     latencies.record(latency);
+/// }
+///
+/// let snapshot = latencies.snapshot();
+/// assert_eq!(snapshot.counts(), [2, 1, 0, 1]);
+/// assert_eq!(snapshot.count(), 4);
+/// assert_eq!(snapshot.sum(), 5055);
+/// ```
+pub struct AtomicHistogram {
+    bounds: Box<[u64]>,
+    /// The count of each bucket, then the sum of the observations.
+    values: AtomicGroup,
+}
+
+impl AtomicHistogram {
+    /// Creates a histogram with a bucket per bound, and one for the values above every bound.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bounds aren't increasing.
+    pub fn new(bounds: impl Into<Box<[u64]>>) -> Self {
+        let bounds = bounds.into();
+        assert!(
+            bounds.windows(2).all(|pair| pair[0] < pair[1]),
+            "the bounds of an AtomicHistogram must be increasing"
+        );
+        let values = AtomicGroup::new(bounds.len() + 2);
+        Self { bounds, values }
+    }
+
+    /// Returns the upper bounds of the buckets, without the last bucket's.
+    pub fn bounds(&self) -> &[u64] {
+        &self.bounds
+    }
+
+    /// Records an observation of `value`.
+    pub fn record(&self, value: u64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.values.update(|values| {
+            values[bucket] = values[bucket].wrapping_add(1);
+            let sum = values.len() - 1;
+            values[sum] = values[sum].wrapping_add(value);
+        });
+    }
+
+    /// Returns the counts and the sum, read together.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot::new(self.values.snapshot())
+    }
+
+    /// Returns the counts and the sum, setting them to 0, such as for an exporter sending the observations since the last scrape.
+    pub fn snapshot_and_reset(&self) -> HistogramSnapshot {
+        let values = self.values.update(|values| {
+            let snapshot = values.to_vec();
+            values.iter_mut().for_each(|value| *value = 0);
+            snapshot
+        });
+        HistogramSnapshot::new(values)
+    }
+}
+
+impl fmt::Debug for AtomicHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicHistogram")
+            .field("bounds", &self.bounds)
+            .field("snapshot", &self.snapshot())
+            .finish()
+    }
+}
+
+/// The counts and the sum of an [`AtomicHistogram`], read together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    counts: Vec<u64>,
+    sum: u64,
+}
+
+impl HistogramSnapshot {
+    fn new(mut values: Vec<u64>) -> Self {
+        let sum = values.pop().unwrap_or_default();
+        Self {
+            counts: values,
+            sum,
+        }
+    }
+
+    /// Returns the count of each bucket, in the order of the bounds, then the count of the values above every bound.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the number of observations.
+    pub fn count(&self) -> u64 {
+        self.counts
+            .iter()
+            .fold(0, |count, bucket| count.wrapping_add(*bucket))
+    }
+
+    /// Returns the sum of the observations.
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+}
@@ -0,0 +1,158 @@
+//! The recent writes of each shimmed atomic, behind the `history` feature.
+//!
+//! Finding who reset a counter in the field takes knowing the writes which led to its value. With the feature,
+//! every shimmed integer keeps its last writes in a ring, with the thread and the operation which made them,
+//! returned by its `history` method. Each write also takes the lock of the ring, so it is meant for debugging.
+//!
+//! The native atomics record nothing.
+#![cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+
+#[cfg(atomic_shim_fallback)]
+use crate::shim::Slot;
+use std::collections::VecDeque;
+use std::fmt;
+#[cfg(atomic_shim_fallback)]
+use std::mem;
+#[cfg(atomic_shim_fallback)]
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, PoisonError};
+use std::thread::{self, ThreadId};
+
+/// How many writes each atomic keeps.
+pub const CAPACITY: usize = 16;
+
+/// A write to an atomic, returned by its `history` method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operation<T> {
+    thread: ThreadId,
+    op: &'static str,
+    old: T,
+    new: T,
+}
+
+impl<T: Copy> Operation<T> {
+    /// The thread which made the write.
+    pub fn thread(&self) -> ThreadId {
+        self.thread
+    }
+
+    /// The method which made the write, such as `fetch_add` or `compare_exchange`.
+    pub fn op(&self) -> &'static str {
+        self.op
+    }
+
+    /// The value before the write.
+    pub fn old_value(&self) -> T {
+        self.old
+    }
+
+    /// The value after the write, which is the old one after a failed compare-exchange.
+    pub fn new_value(&self) -> T {
+        self.new
+    }
+}
+
+/// Formats the write on a line, such as `ThreadId(2) fetch_sub: 1 -> 0`.
+impl<T: fmt::Debug> fmt::Display for Operation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {}: {:?} -> {:?}",
+            self.thread, self.op, self.old, self.new
+        )
+    }
+}
+
+/// A storage, with the ring of its last writes.
+///
+/// The writes are recorded right after they are made, so concurrent writes may be recorded out of order.
+#[derive(Default)]
+pub(crate) struct History<T, S> {
+    slot: S,
+    ring: Mutex<VecDeque<Operation<T>>>,
+}
+
+impl<T: Copy, S> History<T, S> {
+    fn ring(&self) -> std::sync::MutexGuard<'_, VecDeque<Operation<T>>> {
+        self.ring.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, op: &'static str, old: T, new: T) {
+        let mut ring = self.ring();
+        if ring.len() == CAPACITY {
+            ring.pop_front();
+        }
+        ring.push_back(Operation {
+            thread: thread::current().id(),
+            op,
+            old,
+            new,
+        });
+    }
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T: Copy + PartialEq, S: Slot<T>> Slot<T> for History<T, S> {
+    const FOOTPRINT: usize = S::FOOTPRINT + mem::size_of::<Mutex<VecDeque<Operation<T>>>>();
+    const IS_LOCK_FREE: bool = S::IS_LOCK_FREE;
+
+    fn new(value: T) -> Self {
+        History {
+            slot: S::new(value),
+            ring: Mutex::new(VecDeque::with_capacity(CAPACITY)),
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        self.slot.is_lock_free()
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        self.slot.get_mut()
+    }
+
+    fn into_inner(self) -> T {
+        self.slot.into_inner()
+    }
+
+    fn load(&self, order: Ordering) -> T {
+        self.slot.load(order)
+    }
+
+    fn update<F: Fn(T) -> T>(&self, order: Ordering, f: F) -> T {
+        self.apply("update", order, f)
+    }
+
+    fn apply<F: Fn(T) -> T>(&self, op: &'static str, order: Ordering, f: F) -> T {
+        let prev = self.slot.apply(op, order, &f);
+        self.record(op, prev, f(prev));
+        prev
+    }
+
+    fn compare_and_swap(&self, order: Ordering, current: T, new: T) -> T
+    where
+        T: Copy + PartialEq,
+    {
+        let prev = self.slot.compare_and_swap(order, current, new);
+        let next = if prev == current { new } else { prev };
+        self.record("compare_exchange", prev, next);
+        prev
+    }
+
+    fn snapshot(&self) -> T {
+        self.slot.snapshot()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.slot.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {
+        self.slot.forget_poisoning()
+    }
+
+    /// Returns the recorded writes, from the oldest to the latest.
+    fn history(&self) -> Vec<Operation<T>> {
+        self.ring().iter().copied().collect()
+    }
+}
@@ -0,0 +1,133 @@
+//! Unique identifiers, such as request or sequence numbers, without a mutex around a counter.
+//!
+//! Each call takes the next value of an atomic, so the identifiers are unique, and increase in the order
+//! they are taken. What happens once the counter runs out is chosen when creating the generator.
+
+use crate::{Atomic64Ext, AtomicU64};
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// What an [`IdGen`] does once it has returned `u64::MAX - 1`, its last identifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// Panics, as identifiers can't be reused.
+    #[default]
+    Panic,
+    /// Starts again from 0, for sequence numbers which are compared with wrapping arithmetic.
+    Wrap,
+    /// Returns [`IdsExhausted`] from [`IdGen::try_next_id`].
+    Error,
+}
+
+/// The error returned by [`IdGen::try_next_id`] once every identifier was returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdsExhausted;
+
+impl fmt::Display for IdsExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("every identifier of the generator was returned")
+    }
+}
+
+impl Error for IdsExhausted {}
+
+/// A generator of unique, increasing `u64` identifiers.
+///
+/// The identifiers go up to `u64::MAX - 1`, as `u64::MAX` marks the exhausted generator.
+/// With [`Overflow::Wrap`], they start again from 0 after `u64::MAX`, and are only unique until then.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{IdGen, Overflow};
+///
+/// let requests = IdGen::new();
+/// assert_eq!(requests.next_id(), 0);
+/// assert_eq!(requests.next_id(), 1);
+///
+/// let sequence = IdGen::starting_at(u64::MAX - 1).with_overflow(Overflow::Error);
+/// assert_eq!(sequence.try_next_id(), Ok(u64::MAX - 1));
+/// assert!(sequence.try_next_id().is_err());
+/// ```
+#[derive(Default)]
+pub struct IdGen {
+    next: AtomicU64,
+    overflow: Overflow,
+}
+
+impl IdGen {
+    /// Creates a generator starting at 0, which panics once it runs out.
+    pub fn new() -> Self {
+        Self::starting_at(0)
+    }
+
+    /// Creates a generator starting at `first`, which panics once it runs out.
+    pub fn starting_at(first: u64) -> Self {
+        Self {
+            next: AtomicU64::new(first),
+            overflow: Overflow::Panic,
+        }
+    }
+
+    /// Sets what the generator does once it runs out.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Returns the next identifier.
+    ///
+    /// # Panics
+    ///
+    /// Panics once the generator runs out, unless it wraps around.
+    pub fn next_id(&self) -> u64 {
+        match self.try_next_id() {
+            Ok(id) => id,
+            Err(exhausted) => panic!("{}", exhausted),
+        }
+    }
+
+    /// Returns the next identifier, or an error once the generator runs out.
+    ///
+    /// # Panics
+    ///
+    /// Panics once the generator runs out, if it was created with [`Overflow::Panic`].
+    pub fn try_next_id(&self) -> Result<u64, IdsExhausted> {
+        if self.overflow == Overflow::Wrap {
+            return Ok(self.next.fetch_add(1, Ordering::Relaxed));
+        }
+        match self
+            .next
+            .fetch_update_with_backoff(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                if next == u64::MAX {
+                    None
+                } else {
+                    Some(next + 1)
+                }
+            }) {
+            Ok(id) => Ok(id),
+            Err(_) if self.overflow == Overflow::Panic => panic!("{}", IdsExhausted),
+            Err(_) => Err(IdsExhausted),
+        }
+    }
+
+    /// Returns the identifier the next call will return, without taking it.
+    pub fn peek(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    /// Returns the overflow policy of the generator.
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+}
+
+impl fmt::Debug for IdGen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdGen")
+            .field("next", &self.peek())
+            .field("overflow", &self.overflow)
+            .finish()
+    }
+}
@@ -0,0 +1,31 @@
+//! `arbitrary::Arbitrary`, generating the atomics from an arbitrary integer,
+//! so structs holding them can be derived in fuzz targets and property tests.
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use atomic_shim::AtomicU64;
+//! use std::sync::atomic::Ordering;
+//!
+//! let mut u = Unstructured::new(&[1, 0, 0, 0, 0, 0, 0, 0]);
+//! let counter = AtomicU64::arbitrary(&mut u).unwrap();
+//! assert_eq!(counter.load(Ordering::SeqCst), 1);
+//! ```
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+macro_rules! arbitrary_atomic {
+    ($atomic:ident, $int:ident) => {
+        impl<'a> Arbitrary<'a> for crate::$atomic {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                $int::arbitrary(u).map(Self::new)
+            }
+
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                $int::size_hint(depth)
+            }
+        }
+    };
+}
+
+arbitrary_atomic!(AtomicU64, u64);
+arbitrary_atomic!(AtomicI64, i64);
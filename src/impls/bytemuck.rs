@@ -0,0 +1,49 @@
+//! `bytemuck::Zeroable` for the native implementation, and zeroed slices on both implementations.
+//!
+//! The native types are `#[repr(transparent)]` wrappers of the `std` atomics, for which all-zero bytes are
+//! a valid value of `0`. The shim holds a lock, which can't be created from zeroed memory, so it is not `Zeroable`.
+//!
+//! None of the types can be `Pod`: `Pod` values may be cast from shared `&[u8]` slices,
+//! and mutating them through the shared reference of an atomic would break the immutability of those bytes.
+
+#[cfg(not(atomic_shim_fallback_u64))]
+unsafe impl bytemuck::Zeroable for crate::AtomicU64 {}
+
+#[cfg(not(atomic_shim_fallback_i64))]
+unsafe impl bytemuck::Zeroable for crate::AtomicI64 {}
+
+macro_rules! zeroed_slice {
+    ($atomic:ident, $fallback:ident) => {
+        impl crate::$atomic {
+            /// Allocates a slice of `len` atomics, all set to zero.
+            ///
+            /// On the native implementation it is a single zeroed allocation, through `bytemuck`,
+            /// and on the shim every element is created individually.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let counters = ", stringify!($atomic), "::zeroed_slice(1024);")]
+            /// counters[7].fetch_add(1, Ordering::Relaxed);
+            /// assert_eq!(counters[7].load(Ordering::Relaxed), 1);
+            /// assert_eq!(counters[8].load(Ordering::Relaxed), 0);
+            /// ```
+            pub fn zeroed_slice(len: usize) -> Box<[Self]> {
+                #[cfg(not($fallback))]
+                {
+                    bytemuck::zeroed_slice_box(len)
+                }
+                #[cfg($fallback)]
+                {
+                    (0..len).map(|_| Self::new(0)).collect()
+                }
+            }
+        }
+    };
+}
+
+zeroed_slice!(AtomicU64, atomic_shim_fallback_u64);
+zeroed_slice!(AtomicI64, atomic_shim_fallback_i64);
@@ -0,0 +1,45 @@
+//! Conversions between the 64-bit atomics and `crossbeam_utils::atomic::AtomicCell`,
+//! so code migrating from one to the other doesn't need glue modules.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::AtomicU64;
+//! use crossbeam_utils::atomic::AtomicCell;
+//!
+//! let atomic = AtomicU64::from(AtomicCell::new(5));
+//! let cell = atomic.to_atomic_cell();
+//! assert_eq!(cell.load(), 5);
+//!
+//! let cell: AtomicCell<u64> = atomic.into();
+//! assert_eq!(cell.into_inner(), 5);
+//! ```
+
+use crossbeam_utils::atomic::AtomicCell;
+use std::sync::atomic::Ordering;
+
+macro_rules! atomic_cell {
+    ($atomic:ident, $int:ident) => {
+        impl crate::$atomic {
+            /// Creates a new `AtomicCell` holding a snapshot of the value, loaded with `Ordering::SeqCst`.
+            pub fn to_atomic_cell(&self) -> AtomicCell<$int> {
+                AtomicCell::new(self.load(Ordering::SeqCst))
+            }
+        }
+
+        impl From<AtomicCell<$int>> for crate::$atomic {
+            fn from(cell: AtomicCell<$int>) -> Self {
+                Self::new(cell.into_inner())
+            }
+        }
+
+        impl From<crate::$atomic> for AtomicCell<$int> {
+            fn from(atomic: crate::$atomic) -> Self {
+                AtomicCell::new(atomic.into_inner())
+            }
+        }
+    };
+}
+
+atomic_cell!(AtomicU64, u64);
+atomic_cell!(AtomicI64, i64);
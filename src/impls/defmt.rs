@@ -0,0 +1,17 @@
+//! `defmt::Format`, printing the current value, so RTT logs can include the atomics without explicit loads.
+//!
+//! The value is loaded with `Ordering::Relaxed`, as formatting only needs a snapshot.
+
+use std::sync::atomic::Ordering;
+
+impl defmt::Format for crate::AtomicU64 {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{=u64}", self.load(Ordering::Relaxed))
+    }
+}
+
+impl defmt::Format for crate::AtomicI64 {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{=i64}", self.load(Ordering::Relaxed))
+    }
+}
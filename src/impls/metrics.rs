@@ -0,0 +1,44 @@
+//! `metrics::CounterFn` and `metrics::GaugeFn` for the cells, so they can back the facade's handles,
+//! such as in a recorder for targets without 64-bit atomics.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::{CounterCell, GaugeCell};
+//! use std::sync::Arc;
+//!
+//! let requests = Arc::new(CounterCell::new());
+//! metrics::Counter::from_arc(requests.clone()).increment(1);
+//! assert_eq!(requests.get(), 1);
+//!
+//! let queued = Arc::new(GaugeCell::new());
+//! metrics::Gauge::from_arc(queued.clone()).set(4.0);
+//! assert_eq!(queued.get(), 4.0);
+//! ```
+
+use crate::{CounterCell, GaugeCell};
+use metrics::{CounterFn, GaugeFn};
+
+impl CounterFn for CounterCell {
+    fn increment(&self, value: u64) {
+        self.increment(value)
+    }
+
+    fn absolute(&self, value: u64) {
+        self.absolute(value)
+    }
+}
+
+impl GaugeFn for GaugeCell {
+    fn increment(&self, value: f64) {
+        self.increment(value)
+    }
+
+    fn decrement(&self, value: f64) {
+        self.decrement(value)
+    }
+
+    fn set(&self, value: f64) {
+        self.set(value)
+    }
+}
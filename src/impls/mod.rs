@@ -0,0 +1,18 @@
+//! Implementations of third-party traits, each behind the feature of the same name.
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "bytemuck")]
+mod bytemuck;
+#[cfg(feature = "crossbeam-utils")]
+mod crossbeam_utils;
+#[cfg(feature = "defmt")]
+mod defmt;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "radium")]
+mod radium;
+#[cfg(feature = "rkyv")]
+mod rkyv;
+#[cfg(all(feature = "zerocopy", not(atomic_shim_fallback_u64)))]
+mod zerocopy;
@@ -0,0 +1,152 @@
+//! `radium::Radium` for the crate types, so crates generic over "maybe atomic" storage,
+//! such as `bitvec`, work on targets served by the shim.
+//!
+//! `radium` already implements its trait for the `std` atomics, so only the crate's own types,
+//! and the shims of the narrower widths, are covered here.
+
+use radium::Radium;
+use std::sync::atomic::{self, Ordering};
+
+macro_rules! radium_common {
+    ($int:ty) => {
+        type Item = $int;
+
+        fn new(value: $int) -> Self {
+            Self::new(value)
+        }
+
+        fn fence(order: Ordering) {
+            atomic::fence(order)
+        }
+
+        fn get_mut(&mut self) -> &mut $int {
+            self.get_mut()
+        }
+
+        fn into_inner(self) -> $int {
+            self.into_inner()
+        }
+
+        fn load(&self, order: Ordering) -> $int {
+            self.load(order)
+        }
+
+        fn store(&self, value: $int, order: Ordering) {
+            self.store(value, order)
+        }
+
+        fn swap(&self, value: $int, order: Ordering) -> $int {
+            self.swap(value, order)
+        }
+
+        fn compare_and_swap(&self, current: $int, new: $int, order: Ordering) -> $int {
+            match self.compare_exchange(current, new, order, Ordering::SeqCst) {
+                Ok(prev) | Err(prev) => prev,
+            }
+        }
+
+        fn compare_exchange(
+            &self,
+            current: $int,
+            new: $int,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<$int, $int> {
+            self.compare_exchange(current, new, success, failure)
+        }
+
+        fn compare_exchange_weak(
+            &self,
+            current: $int,
+            new: $int,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<$int, $int> {
+            self.compare_exchange_weak(current, new, success, failure)
+        }
+
+        fn fetch_and(&self, value: $int, order: Ordering) -> $int {
+            self.fetch_and(value, order)
+        }
+
+        fn fetch_nand(&self, value: $int, order: Ordering) -> $int {
+            self.fetch_nand(value, order)
+        }
+
+        fn fetch_or(&self, value: $int, order: Ordering) -> $int {
+            self.fetch_or(value, order)
+        }
+
+        fn fetch_xor(&self, value: $int, order: Ordering) -> $int {
+            self.fetch_xor(value, order)
+        }
+
+        fn fetch_update<F>(
+            &self,
+            set_order: Ordering,
+            fetch_order: Ordering,
+            mut f: F,
+        ) -> Result<$int, $int>
+        where
+            F: FnMut($int) -> Option<$int>,
+        {
+            let mut prev = self.load(fetch_order);
+            while let Some(next) = f(prev) {
+                match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
+                    Ok(value) => return Ok(value),
+                    Err(actual) => prev = actual,
+                }
+            }
+            Err(prev)
+        }
+    };
+}
+
+macro_rules! radium_int {
+    ($atomic:ty, $int:ty) => {
+        impl Radium for $atomic {
+            radium_common!($int);
+
+            fn fetch_add(&self, value: $int, order: Ordering) -> $int {
+                self.fetch_add(value, order)
+            }
+
+            fn fetch_sub(&self, value: $int, order: Ordering) -> $int {
+                self.fetch_sub(value, order)
+            }
+        }
+    };
+}
+
+radium_int!(crate::AtomicU64, u64);
+radium_int!(crate::AtomicI64, i64);
+
+#[cfg(atomic_shim_fallback_u8)]
+radium_int!(crate::shim::AtomicU8, u8);
+#[cfg(atomic_shim_fallback_i8)]
+radium_int!(crate::shim::AtomicI8, i8);
+#[cfg(atomic_shim_fallback_u16)]
+radium_int!(crate::shim::AtomicU16, u16);
+#[cfg(atomic_shim_fallback_i16)]
+radium_int!(crate::shim::AtomicI16, i16);
+#[cfg(atomic_shim_fallback_u32)]
+radium_int!(crate::shim::AtomicU32, u32);
+#[cfg(atomic_shim_fallback_i32)]
+radium_int!(crate::shim::AtomicI32, i32);
+#[cfg(atomic_shim_fallback_usize)]
+radium_int!(crate::shim::AtomicUsize, usize);
+#[cfg(atomic_shim_fallback_isize)]
+radium_int!(crate::shim::AtomicIsize, isize);
+
+#[cfg(atomic_shim_fallback_bool)]
+impl Radium for crate::shim::AtomicBool {
+    radium_common!(bool);
+
+    fn fetch_add(&self, _: bool, _: Ordering) -> bool {
+        unreachable!("radium statically forbids arithmetic on bool")
+    }
+
+    fn fetch_sub(&self, _: bool, _: Ordering) -> bool {
+        unreachable!("radium statically forbids arithmetic on bool")
+    }
+}
@@ -0,0 +1,59 @@
+//! `rkyv` archival, storing a snapshot of the value as the archived integer.
+//!
+//! Unlike `rkyv::with::AtomicLoad`, which needs to be declared on every field, these impls let structs
+//! holding the crate types derive `Archive` directly, on both implementations.
+//! The value is loaded with `Ordering::SeqCst` when resolving, and a new atomic is created when deserializing.
+//!
+//! ```
+//! use atomic_shim::AtomicU64;
+//! use rkyv::rancor::Error;
+//! use std::sync::atomic::Ordering;
+//!
+//! #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+//! struct Stats {
+//!     name: String,
+//!     hits: AtomicU64,
+//! }
+//!
+//! let stats = Stats { name: "cache".to_string(), hits: AtomicU64::new(42) };
+//! let bytes = rkyv::to_bytes::<Error>(&stats).unwrap();
+//!
+//! let archived = rkyv::access::<ArchivedStats, Error>(&bytes).unwrap();
+//! assert_eq!(archived.hits, 42);
+//!
+//! let restored = rkyv::deserialize::<Stats, Error>(archived).unwrap();
+//! assert_eq!(restored.hits.load(Ordering::SeqCst), 42);
+//! ```
+
+use rkyv::primitive::{ArchivedI64, ArchivedU64};
+use rkyv::rancor::Fallible;
+use rkyv::{Archive, Deserialize, Place, Serialize};
+use std::sync::atomic::Ordering;
+
+macro_rules! rkyv_atomic {
+    ($atomic:ident, $archived:ident) => {
+        impl Archive for crate::$atomic {
+            type Archived = $archived;
+            type Resolver = ();
+
+            fn resolve(&self, _: Self::Resolver, out: Place<Self::Archived>) {
+                out.write($archived::from_native(self.load(Ordering::SeqCst)));
+            }
+        }
+
+        impl<S: Fallible + ?Sized> Serialize<S> for crate::$atomic {
+            fn serialize(&self, _: &mut S) -> Result<Self::Resolver, S::Error> {
+                Ok(())
+            }
+        }
+
+        impl<D: Fallible + ?Sized> Deserialize<crate::$atomic, D> for $archived {
+            fn deserialize(&self, _: &mut D) -> Result<crate::$atomic, D::Error> {
+                Ok(crate::$atomic::new(self.to_native()))
+            }
+        }
+    };
+}
+
+rkyv_atomic!(AtomicU64, ArchivedU64);
+rkyv_atomic!(AtomicI64, ArchivedI64);
@@ -0,0 +1,38 @@
+//! `zerocopy` traits for the native implementation, derived on the types themselves.
+//!
+//! The native types are `#[repr(transparent)]` wrappers of the `std` atomics, which `zerocopy` already
+//! supports, so they are `FromZeros`, `FromBytes`, `IntoBytes`, `KnownLayout` and `TryFromBytes`,
+//! the `zerocopy` 0.8 names of `FromZeroes` and `AsBytes`.
+//! They can't be `Immutable`, as atomics mutate through shared references, so structs holding them are
+//! parsed from `&mut [u8]`, such as a mapped shared memory segment, and shared afterwards.
+//! The shim holds a lock, which has no stable layout, so it doesn't implement any of them.
+//!
+//! ```
+//! use atomic_shim::AtomicU64;
+//! use std::sync::atomic::Ordering;
+//! use zerocopy::{FromBytes, IntoBytes, KnownLayout};
+//!
+//! #[derive(FromBytes, IntoBytes, KnownLayout)]
+//! #[repr(C)]
+//! struct Telemetry {
+//!     packets: AtomicU64,
+//!     bytes: AtomicU64,
+//! }
+//!
+//! let mut segment = [0u64; 2];
+//! let telemetry = Telemetry::mut_from_bytes(segment.as_mut_bytes()).unwrap();
+//! telemetry.packets.fetch_add(1, Ordering::Relaxed);
+//! telemetry.bytes.fetch_add(1500, Ordering::Relaxed);
+//! assert_eq!(segment, [1, 1500]);
+//! ```
+
+use zerocopy::{FromBytes, FromZeros, IntoBytes, KnownLayout, TryFromBytes};
+
+/// Checks the native types implement the traits, as the derives are easy to lose in the macro.
+fn assert_traits<T: FromZeros + FromBytes + IntoBytes + KnownLayout + TryFromBytes>() {}
+
+#[cfg(not(atomic_shim_fallback_u64))]
+const _: fn() = assert_traits::<crate::AtomicU64>;
+
+#[cfg(not(atomic_shim_fallback_i64))]
+const _: fn() = assert_traits::<crate::AtomicI64>;
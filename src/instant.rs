@@ -0,0 +1,117 @@
+//! An `Instant` stored in a 64-bit atomic, for "last seen" timestamps shared between threads.
+//!
+//! An `Instant` is opaque, and larger than 64 bits, so the atomic stores the nanoseconds elapsed since an epoch
+//! taken the first time the process uses one, which last 584 years. Where the target lacks 64-bit atomics, it is
+//! the shim, so timestamps don't need a `Mutex<Instant>` there.
+
+use crate::duration::nanos;
+use crate::{Atomic64Ext, AtomicU64};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The instant the atomics count from, taken when the process first uses one.
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+fn epoch() -> Instant {
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// A point in time, loaded and stored atomically, such as when a peer was last heard from.
+///
+/// It stores nanoseconds since the process first used an `AtomicInstant`, and instants before that
+/// are stored as that epoch. The operations are `Relaxed`, like a [`Counter`](crate::Counter)'s.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicInstant;
+/// use std::time::{Duration, Instant};
+///
+/// let last_seen = AtomicInstant::now();
+/// last_seen.set_now();
+/// assert!(last_seen.elapsed() < Duration::from_secs(60));
+///
+/// // a late report of an older packet doesn't move the timestamp back
+/// let earlier = last_seen.load() - Duration::from_millis(1);
+/// assert!(!last_seen.store_if_later(earlier));
+/// ```
+pub struct AtomicInstant {
+    nanos: AtomicU64,
+}
+
+impl AtomicInstant {
+    /// Creates an atomic holding `instant`.
+    pub fn new(instant: Instant) -> Self {
+        Self {
+            nanos: AtomicU64::new(Self::encode(instant)),
+        }
+    }
+
+    /// Creates an atomic holding the current time.
+    pub fn now() -> Self {
+        Self::new(Instant::now())
+    }
+
+    fn encode(instant: Instant) -> u64 {
+        nanos(instant.saturating_duration_since(epoch()))
+    }
+
+    fn decode(nanos: u64) -> Instant {
+        epoch() + Duration::from_nanos(nanos)
+    }
+
+    /// Returns the instant.
+    pub fn load(&self) -> Instant {
+        Self::decode(self.nanos.load(Ordering::Relaxed))
+    }
+
+    /// Stores `instant`.
+    pub fn store(&self, instant: Instant) {
+        self.nanos.store(Self::encode(instant), Ordering::Relaxed);
+    }
+
+    /// Stores the current time.
+    pub fn set_now(&self) {
+        self.store(Instant::now());
+    }
+
+    /// Stores `instant` if it is later than the one held, returning whether it was stored.
+    ///
+    /// Threads reporting events out of order, such as from different sockets, keep the latest of them.
+    pub fn store_if_later(&self, instant: Instant) -> bool {
+        let new = Self::encode(instant);
+        self.nanos
+            .fetch_update_with_backoff(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                if new > current {
+                    Some(new)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Returns the time elapsed since the instant, or zero if it is in the future.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().saturating_duration_since(self.load())
+    }
+
+    /// Consumes the atomic, returning the instant.
+    pub fn into_inner(self) -> Instant {
+        Self::decode(self.nanos.into_inner())
+    }
+}
+
+impl From<Instant> for AtomicInstant {
+    fn from(instant: Instant) -> Self {
+        Self::new(instant)
+    }
+}
+
+impl fmt::Debug for AtomicInstant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(), f)
+    }
+}
@@ -0,0 +1,95 @@
+//! A one-shot event, set once, releasing every thread waiting for it.
+//!
+//! Waiting for another thread by spinning on the load of an atomic keeps a core busy for as long as it waits.
+//! A latch blocks its waiters with [`wait`](crate::AtomicU64::wait) on its atomic instead, until it is set and
+//! they are notified, on the native atomics and the shim alike.
+
+use crate::AtomicU64;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// An event which starts unset, is set once, and stays set, releasing the threads waiting for it.
+///
+/// Setting the latch releases the writes made before it to the threads which see it set.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::Latch;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let ready = Arc::new(Latch::new());
+/// let config = Arc::new(AtomicU64::new(0));
+/// let loader = {
+///     let (ready, config) = (ready.clone(), config.clone());
+///     thread::spawn(move || {
+///         config.store(42, Ordering::Relaxed);
+///         ready.set();
+///     })
+/// };
+///
+/// ready.wait();
+/// assert_eq!(config.load(Ordering::Relaxed), 42);
+/// loader.join().unwrap();
+/// ```
+pub struct Latch {
+    /// 0 until the latch is set, then 1.
+    state: AtomicU64,
+}
+
+impl Latch {
+    /// Creates a latch which isn't set.
+    pub fn new() -> Self {
+        Self {
+            state: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the latch, and wakes the threads waiting for it.
+    ///
+    /// Returns whether this call set it, only the first one doing so.
+    pub fn set(&self) -> bool {
+        let first = self.state.swap(1, Ordering::AcqRel) == 0;
+        if first {
+            self.state.notify_all();
+        }
+        first
+    }
+
+    /// Returns whether the latch is set.
+    pub fn is_set(&self) -> bool {
+        self.state.load(Ordering::Acquire) != 0
+    }
+
+    /// Blocks until the latch is set, returning immediately if it already is.
+    pub fn wait(&self) {
+        self.state.wait(0, Ordering::Acquire);
+    }
+
+    /// Blocks until the latch is set, or until `timeout` elapsed, returning whether it is set.
+    ///
+    /// Unlike [`wait`](Latch::wait), the latch is polled between the waits of a [`Backoff`](crate::Backoff),
+    /// as the wait of an atomic has no timeout.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.state
+            .wait_until_timeout(|state| state != 0, timeout)
+            .is_some()
+    }
+}
+
+impl Default for Latch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Latch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Latch")
+            .field("set", &self.is_set())
+            .finish()
+    }
+}
@@ -8,11 +8,117 @@
 //! The crate does target detection and on supported architectures it will use `std::sync` structures.
 //! When it detects it is running on unsupported platforms, it fallbacks to the shim implementation, using `crossbeam` Mutex.
 //!
-//! For testing purposes, and for other reasons, you can replace the default implementation with the Mutex implementation by using the `features = ["mutex"]`
+//! The detection happens on the build script, based on the `max-atomic-width` of the target spec,
+//! so custom JSON targets without 64-bit atomics also get the shim, regardless of their `target_arch`.
+//! On WebAssembly, the native atomics are only used with the `atomics` target feature, as `std` lowers them to plain memory accesses without it.
+//!
+//! For testing purposes, and for other reasons, you can replace the default implementation with the Mutex implementation by using the `features = ["mutex"]`.
+//! To replace only one of the types, use `features = ["force-shim-u64"]` or `features = ["force-shim-i64"]`.
+//! Applications which must never run on the shim can enable `features = ["deny-shim"]`, which makes selecting it a compilation error.
+//!
+//! When the shim is selected, the build script emits a warning with the chosen backend and the reason for it,
+//! visible with `cargo build -vv` when depending on the crate from a registry. Set `ATOMIC_SHIM_NO_WARN` to silence it.
+//! For platform bring-up, `cargo run --features probe --bin atomic-shim-probe` prints the backend, size and lock-freedom of each type.
 //!
 //! # Usage
 //!
-//! Replace any imports of `use std::sync::AtomicU64;` with `use atomic_shim::Atomic64;`
+//! Replace any imports of `use std::sync::atomic::AtomicU64;` with `use atomic_shim::AtomicU64;`
+//!
+//! To replace the whole `std::sync::atomic` module, including `Ordering` and the other widths, use the [`atomic`] facade:
+//! replace `use std::sync::atomic;` with `use atomic_shim::atomic;`
+//! The [`prelude`] brings the atomic types and `Ordering` with a single import: `use atomic_shim::prelude::*;`
+//!
+//! On the native implementation, `AtomicU64` and `AtomicI64` are `#[repr(transparent)]` wrappers of the `std` types, which they deref to.
+//! Both implementations convert from and into the `std` types with `From`/`Into` when the target has them.
+//! Libraries can check `AtomicU64::IS_LOCK_FREE`, `is_lock_free()` or [`NATIVE_ATOMICS`] to choose a different algorithm when running on the shim.
+//!
+//! # Backends
+//!
+//! On the shim, the 64-bit loads don't take the lock: writers bump a version around their writes, and readers retry when one overlapped them.
+//! Before blocking on a busy lock, the shims spin a few times, set by `ATOMIC_SHIM_SPIN_LIMIT` when building, from 0 to 16, 6 by default.
+//! On ARM Linux, the 64-bit shims use the kernel's `__kuser_cmpxchg64` helper instead of the lock when the CPU has `LDREXD`/`STREXD`.
+//! On 32-bit x86 Windows, `features = ["windows-intrinsics"]` backs the 64-bit shims with `InterlockedCompareExchange64` instead of the lock.
+//!
+//! # Features
+//!
+//! Debugging the shim:
+//!
+//! - `diagnostics`: a [watchdog](diagnostics) for writes holding a lock too long, and the origin of poisoned locks.
+//! - `stats`: per-atomic [counts](stats) of the operations and busy locks, returned by [`stats`](AtomicU64::stats).
+//! - `registry`: a [registry] of the live shimmed atomics, listed by [`debug_dump`].
+//! - `history`: the last writes of each shimmed integer, returned by its `history` method.
+//! - `tracing`, `log` and `metrics`: the blocked threads, storms of failed compare-exchanges and poisoned locks, reported through those crates.
+//!
+//! Testing code using the atomics:
+//!
+//! - `--cfg loom`: shims every type over loom's `RwLock`, so [loom](https://docs.rs/loom) can model-check the code using them.
+//! - `replay`: a [scheduler](replay) recording the order of the shim's operations, and replaying it deterministically.
+//! - `weak-memory`: a [simulation of weak memory](weak_memory), where weak loads may return older values.
+//! - `ordering-audit`: [`audit::take_findings`] returns the atomics written with `Release` but never read with `Acquire`, or the opposite.
+//! - `tsan`: annotations of the shim's lock for ThreadSanitizer, with `-Zsanitizer=thread`.
+//!
+//! Sharing the atomics with other code:
+//!
+//! - `ffi` and `cxx`: [C functions](ffi) and a [C++ bridge](cpp) over the 64-bit atomics, so both sides use the same lock on the shim.
+//! - `process-shared`: an [`AtomicU64`](process_shared::AtomicU64) guarded by a mutex shared between processes.
+//! - `libatomic`: [atomics](libatomic) calling the `__atomic_*_8` functions of the toolchain's runtime, like C code.
+//! - `per-cpu`: a [counter](per_cpu) sharded per CPU on Linux.
+//! - `async`: [`wait_async`](AtomicU64::wait_async), awaiting a change of a 64-bit atomic.
+//! - `clone`: `Clone` on the atomics, with a snapshot of the value.
+//!
+//! Passing Miri is part of the crate's contract, for both implementations: `tests/miri.rs` runs on CI with
+//! `cargo +nightly miri test --test miri`, with and without `--features mutex`.
+//!
+//! # Building blocks
+//!
+//! The crate also provides, on both implementations:
+//!
+//! - Traits: [`AtomicInt`] over every integer width, [`AnyAtomicU64`] for `dyn` counters, and the [`Atomic64Ext`] helpers.
+//! - Counters: [`Counter`], [`StripedU64`], [`CounterCell`], [`GaugeCell`], [`AtomicMax`], [`AtomicMin`], [`RateCounter`], [`Ewma`], [`Welford`] and [`TokenBucket`].
+//! - Collections of counters: [`AtomicGroup`], [`AtomicHistogram`], [`AtomicU64Array`], [`AtomicU64Pool`], [`AtomicU64Slice`], [`AtomicBitSet`], [`Padded`] and [`Snapshotter`].
+//! - Values: [`AtomicDuration`], [`AtomicInstant`], [`AtomicU64Pair`], [`AtomicTaggedPtr`], [`ArcCell`], [`SeqLock`], [`WatchCell`], [`MemoCell`], [`OnceU64`] and [`FrozenU64`].
+//! - Synchronization: [`Backoff`], [`Barrier`], [`Latch`], [`Sequencer`], [`EpochCounter`], [`AtomicFlag`], [`IdGen`], and the C++20-style [`wait`](AtomicU64::wait).
+//! - Integers the atomic doesn't own: [`RawAtomicU64`], [`RawAtomicI64`] and [`AtomicField`].
+//!
+//! # Integrations
+//!
+//! Traits from other crates are implemented behind the feature of the same name:
+//!
+//! - `arbitrary`: `arbitrary::Arbitrary`, for fuzz targets and property tests.
+//! - `bitflags`: the [`AtomicFlags`] type, and its [`FlagBits`] trait implemented by the integers of every width.
+//! - `bytemuck`: `bytemuck::Zeroable` on the native implementation, and `zeroed_slice` constructors on both.
+//!   The types can't be `Pod`, as atomics mutate through shared references.
+//! - `crossbeam-utils`: conversions to and from `AtomicCell`, and a `to_atomic_cell` snapshot constructor.
+//!   It is also enabled by the `mutex` feature.
+//! - `defmt`: `defmt::Format`, printing the current value.
+//! - `embassy-sync`: the [`embassy`] module, with 64-bit atomics guarded by an `embassy-sync` `RawMutex`,
+//!   so they cooperate with embassy's critical sections instead of `std` locks.
+//! - `metrics`: `metrics::CounterFn` on `CounterCell`, and `metrics::GaugeFn` on `GaugeCell`, so they can back the facade's handles.
+//! - `radium`: `radium::Radium`, for crates generic over "maybe atomic" storage, such as `bitvec`.
+//! - `rkyv`: `Archive`, `Serialize` and `Deserialize`, archiving a snapshot of the value.
+//! - `zerocopy`: `FromZeros`, `FromBytes`, `IntoBytes`, `KnownLayout` and `TryFromBytes` on the native implementation,
+//!   so atomics can live in structs parsed from shared memory.
+//!
+//! # Memory footprint
+//!
+//! Arrays of counters add up on targets with little memory, so the size of every shim is checked at compile time
+//! against its budget, and a change growing one fails the build. In bytes:
+//!
+//! | Backend | Types | 64-bit targets | 32-bit targets | Heap, per atomic |
+//! |---|---|---|---|---|
+//! | native | every type | as `std` | as `std` | none |
+//! | `seqlock` | `u64`, `i64` | 24 | 24 | none |
+//! | `sharded-lock` | `u64`, `i64` on WebAssembly without `atomics` | | 16 | 8 cache-padded shards |
+//! | `sharded-lock` | narrower integers, `bool`, pointers | 24 | 12 | 8 cache-padded shards |
+//! | ARM Linux dispatch | `u64`, `i64` | | 16 | 8 cache-padded shards |
+//! | `interlocked` | `u64`, `i64` | 8 | 8 | none |
+//!
+//! The `seqlock` sizes are for Linux, where the writers' `Mutex` takes 8 bytes.
+//! The `stats` feature adds four words to every shim, and the `registry` feature moves it to the heap, leaving a pointer.
+//! The `history` feature adds a `Mutex` and a `VecDeque`, with a ring of 16 writes on the heap.
+//! The `ordering-audit` feature adds two words.
+//! The shards of the lock take 256 bytes on ARM and MIPS, and 512 bytes on PowerPC.
+//! `atomic-shim-probe` prints the sizes selected for the current build.
 //!
 //! # Examples
 //!
@@ -32,14 +138,60 @@
 //!     spinlock_clone.store(0, Ordering::SeqCst);
 //! });
 //!
-//! // Wait for the other thread to release the lock
-//! while spinlock.load(Ordering::SeqCst) != 0 {}
+//! // Wait for the other thread to release the lock, backing off instead of spinning
+//! spinlock.wait_until(|locked| locked == 0);
 //!
 //! if let Err(panic) = thread.join() {
 //!     println!("Thread had an error: {:?}", panic);
 //! }
 //!```
 //!
+//! Or, instead of spinning, sleep until the other thread notifies the change:
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::sync::atomic::Ordering;
+//! use std::thread;
+//! use atomic_shim::AtomicU64;
+//!
+//! let lock = Arc::new(AtomicU64::new(1));
+//!
+//! let lock_clone = lock.clone();
+//! let thread = thread::spawn(move|| {
+//!     lock_clone.store(0, Ordering::SeqCst);
+//!     lock_clone.notify_all();
+//! });
+//!
+//! // Sleep while the other thread holds the lock
+//! lock.wait(1, Ordering::SeqCst);
+//!
+//! if let Err(panic) = thread.join() {
+//!     println!("Thread had an error: {:?}", panic);
+//! }
+//! ```
+//!
+//! When the other thread only signals once that it is done, a [`Latch`] makes the store and the notification:
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::thread;
+//! use atomic_shim::Latch;
+//!
+//! let done = Arc::new(Latch::new());
+//!
+//! let done_clone = done.clone();
+//! let thread = thread::spawn(move|| {
+//!     done_clone.set();
+//! });
+//!
+//! // Sleep until the other thread is done
+//! done.wait();
+//!
+//! if let Err(panic) = thread.join() {
+//!     println!("Thread had an error: {:?}", panic);
+//! }
+//! ```
+//!
 //! Keep a global count of live threads:
 //!
 //! ```
@@ -52,11 +204,208 @@
 //! println!("live threads: {}", old_thread_count + 1);
 //! ```
 
-#[cfg(not(any(target_arch = "mips", target_arch = "powerpc", feature = "mutex")))]
-pub use std::sync::atomic::{AtomicI64, AtomicU64};
+#[cfg(all(feature = "deny-shim", atomic_shim_fallback))]
+compile_error!(
+    "atomic-shim selected the lock-based shim, but the `deny-shim` feature forbids it. \
+     The target lacks native 64-bit atomics, or the `mutex`/`force-shim-*` features are enabled."
+);
+
+#[cfg(not(all(atomic_shim_fallback_u64, atomic_shim_fallback_i64)))]
+mod native;
 
-#[cfg(any(target_arch = "mips", target_arch = "powerpc", feature = "mutex"))]
+#[cfg(atomic_shim_fallback)]
 mod shim;
 
-#[cfg(any(target_arch = "mips", target_arch = "powerpc", feature = "mutex"))]
-pub use shim::{AtomicI64, AtomicU64};
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    target_arch = "arm",
+    target_os = "linux",
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+mod arm_linux;
+
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    windows,
+    target_arch = "x86",
+    feature = "windows-intrinsics",
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+mod windows;
+
+mod adder;
+mod arc_cell;
+mod array;
+pub mod atomic;
+#[cfg(feature = "ordering-audit")]
+pub mod audit;
+mod backoff;
+mod barrier;
+mod batch;
+mod bitset;
+mod cells;
+mod counter;
+#[cfg(feature = "cxx")]
+pub mod cpp;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+mod duration;
+#[cfg(feature = "embassy-sync")]
+pub mod embassy;
+mod epoch;
+#[cfg(all(
+    any(feature = "tracing", feature = "log", feature = "metrics"),
+    atomic_shim_fallback
+))]
+mod events;
+mod ewma;
+mod ext;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod flag;
+#[cfg(feature = "bitflags")]
+mod flags;
+mod frozen;
+mod group;
+mod histogram;
+#[cfg(feature = "history")]
+pub mod history;
+mod ids;
+mod impls;
+mod instant;
+mod latch;
+#[cfg(feature = "libatomic")]
+pub mod libatomic;
+#[cfg(all(
+    feature = "diagnostics",
+    feature = "process-shared",
+    unix,
+    debug_assertions
+))]
+mod lock_order;
+mod memo;
+mod once;
+mod padded;
+mod pair;
+#[cfg(all(feature = "per-cpu", target_os = "linux"))]
+pub mod per_cpu;
+mod pool;
+pub mod prelude;
+#[cfg(all(feature = "process-shared", unix))]
+pub mod process_shared;
+mod rate;
+mod raw;
+#[cfg(feature = "registry")]
+pub mod registry;
+#[cfg(feature = "replay")]
+pub mod replay;
+mod seqlock;
+mod sequencer;
+mod slice;
+mod snapshot;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(any(atomic_shim_fallback_u64, atomic_shim_fallback_i64))]
+mod striped;
+mod tagged;
+mod token_bucket;
+mod traits;
+#[cfg(all(feature = "tsan", atomic_shim_fallback))]
+mod tsan;
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    not(all(target_arch = "arm", target_os = "linux")),
+    not(all(windows, target_arch = "x86", feature = "windows-intrinsics")),
+    target_has_atomic = "32",
+    not(all(target_family = "wasm", not(target_feature = "atomics"))),
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+mod versioned;
+mod wait;
+mod watch;
+mod watermark;
+#[cfg(all(feature = "weak-memory", not(loom)))]
+pub mod weak_memory;
+mod welford;
+
+pub use adder::StripedU64;
+pub use arc_cell::ArcCell;
+pub use array::AtomicU64Array;
+pub use backoff::Backoff;
+pub use barrier::{Barrier, BarrierWaitResult};
+pub use batch::fetch_add_batch;
+pub use bitset::{AtomicBitSet, BitSetIter};
+pub use cells::{CounterCell, GaugeCell};
+pub use counter::Counter;
+pub use duration::AtomicDuration;
+pub use epoch::{EpochCounter, EpochGuard};
+pub use ewma::Ewma;
+pub use ext::Atomic64Ext;
+pub use flag::AtomicFlag;
+#[cfg(feature = "bitflags")]
+pub use flags::{AtomicFlags, FlagBits};
+pub use frozen::{FrozenI64, FrozenU64};
+pub use group::AtomicGroup;
+pub use histogram::{AtomicHistogram, HistogramSnapshot};
+pub use ids::{IdGen, IdsExhausted, Overflow};
+pub use instant::AtomicInstant;
+pub use latch::Latch;
+pub use memo::MemoCell;
+pub use once::OnceU64;
+pub use padded::Padded;
+pub use pair::AtomicU64Pair;
+pub use pool::{AtomicU64Pool, PoolRef};
+pub use rate::RateCounter;
+pub use raw::{AtomicField, FieldInt, RawAtomicI64, RawAtomicU64};
+#[cfg(feature = "registry")]
+pub use registry::debug_dump;
+pub use seqlock::{PlainData, SeqLock};
+pub use sequencer::{Sequencer, SequencerGuard};
+pub use slice::AtomicU64Slice;
+pub use snapshot::Snapshotter;
+pub use tagged::AtomicTaggedPtr;
+pub use token_bucket::TokenBucket;
+pub use traits::{AnyAtomicU64, AtomicInt};
+#[cfg(feature = "async")]
+pub use wait::WaitFuture;
+pub use watch::WatchCell;
+pub use watermark::{AtomicMax, AtomicMin};
+pub use welford::{Welford, WelfordSnapshot};
+
+#[cfg(not(atomic_shim_fallback_u64))]
+pub use native::AtomicU64;
+
+#[cfg(atomic_shim_fallback_u64)]
+pub use shim::AtomicU64;
+
+#[cfg(not(atomic_shim_fallback_i64))]
+pub use native::AtomicI64;
+
+#[cfg(atomic_shim_fallback_i64)]
+pub use shim::AtomicI64;
+
+/// Whether every atomic of the crate is native, so none of them takes a lock in this build.
+///
+/// Libraries can pick a different algorithm when their dependency graph ends up on the shim.
+/// Their build scripts can check the target instead, as `CARGO_CFG_TARGET_HAS_ATOMIC` lists `64` when it has
+/// 64-bit atomics, to emit a cfg of their own. Unlike the constant, it doesn't see the `mutex` and `force-shim-*`
+/// features forcing the shim:
+///
+/// ```no_run
+/// // build.rs of a crate depending on atomic-shim
+/// let widths = std::env::var("CARGO_CFG_TARGET_HAS_ATOMIC").unwrap_or_default();
+/// if widths.split(',').any(|width| width == "64") {
+///     println!("cargo:rustc-cfg=atomic_shim_native");
+/// }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64;
+///
+/// if atomic_shim::NATIVE_ATOMICS {
+///     assert!(AtomicU64::IS_LOCK_FREE);
+/// }
+/// ```
+pub const NATIVE_ATOMICS: bool = cfg!(not(atomic_shim_fallback));
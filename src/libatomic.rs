@@ -0,0 +1,228 @@
+//! 64-bit atomics backed by the C toolchain's atomic runtime, `libatomic` or `compiler-rt`.
+//!
+//! On targets without native 64-bit atomics, C compilers lower `_Atomic uint64_t` and `std::atomic<uint64_t>`
+//! to calls such as `__atomic_load_8` and `__atomic_compare_exchange_8`, which the runtime emulates with its own locks.
+//! These types call the same functions, so a value shared with C code is protected by the same locks on both sides,
+//! which the shim's `ShardedLock` can't provide.
+//!
+//! The memory orderings are forwarded to the runtime. The crate links to `libatomic`, which must be available
+//! to the linker, as it is with GCC toolchains.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::libatomic::AtomicU64;
+//! use std::sync::atomic::Ordering;
+//!
+//! let counter = AtomicU64::new(1);
+//! assert_eq!(counter.fetch_add(2, Ordering::SeqCst), 1);
+//! assert_eq!(counter.compare_exchange(3, 5, Ordering::SeqCst, Ordering::SeqCst), Ok(3));
+//! assert_eq!(counter.load(Ordering::SeqCst), 5);
+//! ```
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::os::raw::{c_int, c_void};
+use std::sync::atomic::Ordering;
+
+#[link(name = "atomic")]
+extern "C" {
+    fn __atomic_load_8(ptr: *const c_void, order: c_int) -> u64;
+    fn __atomic_store_8(ptr: *mut c_void, value: u64, order: c_int);
+    fn __atomic_exchange_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+    fn __atomic_compare_exchange_8(
+        ptr: *mut c_void,
+        expected: *mut c_void,
+        desired: u64,
+        weak: bool,
+        success: c_int,
+        failure: c_int,
+    ) -> bool;
+    fn __atomic_fetch_add_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+    fn __atomic_fetch_sub_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+    fn __atomic_fetch_and_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+    fn __atomic_fetch_nand_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+    fn __atomic_fetch_or_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+    fn __atomic_fetch_xor_8(ptr: *mut c_void, value: u64, order: c_int) -> u64;
+}
+
+/// Converts an `Ordering` to the `__ATOMIC_*` memory order of the C runtime.
+fn memory_order(order: Ordering) -> c_int {
+    match order {
+        Ordering::Relaxed => 0,
+        Ordering::Acquire => 2,
+        Ordering::Release => 3,
+        Ordering::AcqRel => 4,
+        _ => 5,
+    }
+}
+
+macro_rules! libatomic_atomic {
+    ($atomic:ident, $int:ident) => {
+        /// An integer type which can be safely shared between threads, and with C code using the same atomic runtime.
+        #[repr(C, align(8))]
+        pub struct $atomic {
+            value: UnsafeCell<u64>,
+        }
+
+        unsafe impl Send for $atomic {}
+        unsafe impl Sync for $atomic {}
+
+        impl $atomic {
+            /// Creates a new atomic integer.
+            pub const fn new(v: $int) -> Self {
+                Self {
+                    value: UnsafeCell::new(v as u64),
+                }
+            }
+
+            /// Returns a mutable reference to the underlying integer.
+            ///
+            /// This is safe because the mutable reference guarantees that no other threads are concurrently accessing the atomic data.
+            pub fn get_mut(&mut self) -> &mut $int {
+                unsafe { &mut *(self.value.get() as *mut $int) }
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> $int {
+                self.value.into_inner() as $int
+            }
+
+            /// Returns a raw pointer to the underlying integer, to share it with C code.
+            pub fn as_ptr(&self) -> *mut $int {
+                self.value.get() as *mut $int
+            }
+
+            fn ptr(&self) -> *mut c_void {
+                self.value.get().cast()
+            }
+
+            /// Loads a value from the atomic integer.
+            pub fn load(&self, order: Ordering) -> $int {
+                unsafe { __atomic_load_8(self.ptr(), memory_order(order)) as $int }
+            }
+
+            /// Stores a value into the atomic integer.
+            pub fn store(&self, value: $int, order: Ordering) {
+                unsafe { __atomic_store_8(self.ptr(), value as u64, memory_order(order)) }
+            }
+
+            /// Stores a value into the atomic integer, returning the previous value.
+            pub fn swap(&self, value: $int, order: Ordering) -> $int {
+                unsafe {
+                    __atomic_exchange_8(self.ptr(), value as u64, memory_order(order)) as $int
+                }
+            }
+
+            fn compare_exchange_with(
+                &self,
+                current: $int,
+                new: $int,
+                weak: bool,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                let mut expected = current as u64;
+                let exchanged = unsafe {
+                    __atomic_compare_exchange_8(
+                        self.ptr(),
+                        (&mut expected as *mut u64).cast(),
+                        new as u64,
+                        weak,
+                        memory_order(success),
+                        memory_order(failure),
+                    )
+                };
+                if exchanged {
+                    Ok(current)
+                } else {
+                    Err(expected as $int)
+                }
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the `current` value.
+            ///
+            /// The return value is a result indicating whether the new value was written and containing the previous value.
+            /// On success this value is guaranteed to be equal to `current`.
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.compare_exchange_with(current, new, false, success, failure)
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the `current` value.
+            ///
+            /// Unlike `compare_exchange`, this function is allowed to spuriously fail even when the comparison succeeds.
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.compare_exchange_with(current, new, true, success, failure)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                unsafe { __atomic_fetch_add_8(self.ptr(), val as u64, memory_order(order)) as $int }
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                unsafe { __atomic_fetch_sub_8(self.ptr(), val as u64, memory_order(order)) as $int }
+            }
+
+            /// Bitwise "and" with the current value, returning the previous value.
+            pub fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                unsafe { __atomic_fetch_and_8(self.ptr(), val as u64, memory_order(order)) as $int }
+            }
+
+            /// Bitwise "nand" with the current value, returning the previous value.
+            pub fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                unsafe {
+                    __atomic_fetch_nand_8(self.ptr(), val as u64, memory_order(order)) as $int
+                }
+            }
+
+            /// Bitwise "or" with the current value, returning the previous value.
+            pub fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                unsafe { __atomic_fetch_or_8(self.ptr(), val as u64, memory_order(order)) as $int }
+            }
+
+            /// Bitwise "xor" with the current value, returning the previous value.
+            pub fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                unsafe { __atomic_fetch_xor_8(self.ptr(), val as u64, memory_order(order)) as $int }
+            }
+        }
+
+        impl Default for $atomic {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+
+        impl From<$int> for $atomic {
+            fn from(value: $int) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl fmt::Debug for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+    };
+}
+
+libatomic_atomic!(AtomicU64, u64);
+libatomic_atomic!(AtomicI64, i64);
@@ -0,0 +1,87 @@
+//! Detection of lock order inversions between process-shared atomics, with `diagnostics` in debug builds.
+//!
+//! Holding the [guard](crate::process_shared::Guard) of an atomic while locking another is fine, as long as every
+//! thread locks them in the same order. Each thread records the locks it holds, and every pair of locks taken
+//! one inside the other is recorded globally, with where both were taken. Taking a pair in the opposite order
+//! panics, instead of deadlocking the day both threads interleave. Only the threads of the current process are
+//! tracked. The backtraces are captured when `RUST_BACKTRACE` is set.
+
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Where two locks were taken, the first one being held while taking the second.
+struct Pair {
+    outer: Arc<Backtrace>,
+    inner: Arc<Backtrace>,
+}
+
+/// The pairs of locks taken one inside the other, by their addresses.
+static ORDER: Mutex<BTreeMap<(usize, usize), Pair>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    static HELD: RefCell<Vec<(usize, Arc<Backtrace>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records the lock of `address` by the current thread, before taking it.
+///
+/// # Panics
+///
+/// Panics if the thread already holds it, or if another thread took one of the held locks while holding it.
+pub(crate) fn acquire(address: usize) {
+    let backtrace = Arc::new(Backtrace::capture());
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        let mut order = ORDER.lock().unwrap_or_else(PoisonError::into_inner);
+        for (outer, outer_backtrace) in held.iter() {
+            if *outer == address {
+                panic!(
+                    "the process-shared atomic at {:#x} is locked twice by the same thread, which would deadlock\n\
+                     first locked at:\n{}\nlocked again at:\n{}",
+                    address, outer_backtrace, backtrace
+                );
+            }
+            if let Some(inverted) = order.get(&(address, *outer)) {
+                panic!(
+                    "lock order inversion between the process-shared atomics at {:#x} and {:#x}\n\
+                     this thread locked {:#x} at:\n{}\nand is locking {:#x} at:\n{}\n\
+                     another thread locked {:#x} at:\n{}\nand then {:#x} at:\n{}",
+                    outer,
+                    address,
+                    outer,
+                    outer_backtrace,
+                    address,
+                    backtrace,
+                    address,
+                    inverted.outer,
+                    outer,
+                    inverted.inner
+                );
+            }
+            order.entry((*outer, address)).or_insert_with(|| Pair {
+                outer: outer_backtrace.clone(),
+                inner: backtrace.clone(),
+            });
+        }
+        held.push((address, backtrace));
+    });
+}
+
+/// Records the unlock of `address` by the current thread.
+pub(crate) fn release(address: usize) {
+    let _ = HELD.try_with(|held| {
+        let mut held = held.borrow_mut();
+        if let Some(i) = held.iter().rposition(|(held, _)| *held == address) {
+            held.remove(i);
+        }
+    });
+}
+
+/// Forgets the pairs of a destroyed lock, as another one may be initialized at its address.
+pub(crate) fn forget(address: usize) {
+    ORDER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .retain(|(outer, inner), _| *outer != address && *inner != address);
+}
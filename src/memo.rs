@@ -0,0 +1,223 @@
+//! A value computed at most once by the threads needing it, until it is reset, such as the result of a hardware probe.
+//!
+//! The cell's state is an atomic: empty, computing or ready, with a generation bumped by every reset. The first
+//! thread finding it empty moves it to computing with a compare-exchange, and computes the value, while the
+//! others block with [`wait`](crate::AtomicU64::wait) until it is ready, instead of computing it too. The value is
+//! stored under a lock, checked again once taken, and a computation overlapping a reset isn't stored.
+
+use crate::{Atomic64Ext, AtomicU64};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The bits of the state holding the phase, the others holding the generation.
+const PHASE: u64 = 0b11;
+const EMPTY: u64 = 0;
+const COMPUTING: u64 = 1;
+const READY: u64 = 2;
+
+/// A cached value, computed by a single thread at a time when missing, and invalidated by [`reset`](MemoCell::reset).
+///
+/// The value is returned as a clone, as a reset may drop it while it is used, and can be an `Arc` to share it
+/// instead. If the computation panics, the cell stays empty, and the next thread needing the value computes it.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::MemoCell;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let probes = Arc::new(AtomicUsize::new(0));
+/// let link_speed = Arc::new(MemoCell::new());
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let (probes, link_speed) = (probes.clone(), link_speed.clone());
+///         thread::spawn(move || {
+///             link_speed.get_or_init(|| {
+///                 probes.fetch_add(1, Ordering::Relaxed);
+///                 1000
+///             })
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     assert_eq!(worker.join().unwrap(), 1000);
+/// }
+/// assert_eq!(probes.load(Ordering::Relaxed), 1);
+///
+/// // the link renegotiated, so the next worker probes it again
+/// link_speed.reset();
+/// assert_eq!(link_speed.get(), None);
+/// ```
+pub struct MemoCell<T> {
+    /// The generation, and the phase in the low bits.
+    state: AtomicU64,
+    value: Mutex<Option<T>>,
+}
+
+impl<T> MemoCell<T> {
+    /// Creates an empty cell.
+    pub fn new() -> Self {
+        Self {
+            state: AtomicU64::new(EMPTY),
+            value: Mutex::new(None),
+        }
+    }
+
+    /// The computations don't run under the lock, so a poisoned lock only means a clone of the value panicked.
+    fn lock(&self) -> MutexGuard<'_, Option<T>> {
+        self.value.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Drops the value, so the next thread needing it computes it again.
+    ///
+    /// A computation in progress isn't cached once it returns, as it may have started before what the reset
+    /// invalidates, and the threads waiting for it compute the value again.
+    pub fn reset(&self) {
+        let mut value = self.lock();
+        *value = None;
+        // Moves to the next generation, empty, whatever the phase.
+        let _ =
+            self.state
+                .fetch_update_with_backoff(Ordering::AcqRel, Ordering::Relaxed, |state| {
+                    Some((state | PHASE).wrapping_add(1))
+                });
+        drop(value);
+        self.state.notify_all();
+    }
+
+    /// Consumes the cell, returning the value if it was computed.
+    pub fn into_inner(self) -> Option<T> {
+        self.value
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Clone> MemoCell<T> {
+    /// Returns the value if it was computed.
+    pub fn get(&self) -> Option<T> {
+        self.lock().clone()
+    }
+
+    /// Returns the value, computing it with `f` if it is missing, or waiting for the thread computing it.
+    ///
+    /// If `f`, or the clone of the value cached, panics, the cell is emptied, and the next thread needing the value
+    /// computes it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atomic_shim::MemoCell;
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// static CLONED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// struct Probe(u32);
+    ///
+    /// impl Clone for Probe {
+    ///     fn clone(&self) -> Self {
+    ///         // the first clone fails
+    ///         if !CLONED.swap(true, Ordering::Relaxed) {
+    ///             panic!("out of memory");
+    ///         }
+    ///         Probe(self.0)
+    ///     }
+    /// }
+    ///
+    /// let cell = MemoCell::new();
+    /// assert!(panic::catch_unwind(AssertUnwindSafe(|| cell.get_or_init(|| Probe(1)))).is_err());
+    ///
+    /// // the cell isn't left computing, so the next thread doesn't wait forever
+    /// assert_eq!(cell.get_or_init(|| Probe(2)).0, 2);
+    /// ```
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> T {
+        loop {
+            let state = self.state.load(Ordering::Acquire);
+            match state & PHASE {
+                READY => {
+                    if let Some(value) = self.get() {
+                        return value;
+                    }
+                }
+                COMPUTING => self.state.wait(state, Ordering::Acquire),
+                _ => {
+                    if self
+                        .state
+                        .compare_exchange(
+                            state,
+                            state | COMPUTING,
+                            Ordering::Acquire,
+                            Ordering::Relaxed,
+                        )
+                        .is_ok()
+                    {
+                        return self.compute(state | COMPUTING, f);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Computes the value in the generation of `computing`, and stores it unless the cell was reset meanwhile.
+    fn compute(&self, computing: u64, f: impl FnOnce() -> T) -> T {
+        /// Empties the cell if the computation panics, so that another thread computes the value.
+        struct Abandon<'a> {
+            state: &'a AtomicU64,
+            computing: u64,
+        }
+
+        impl Drop for Abandon<'_> {
+            fn drop(&mut self) {
+                let _ = self.state.compare_exchange(
+                    self.computing,
+                    self.computing & !PHASE,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
+                self.state.notify_all();
+            }
+        }
+
+        let abandon = Abandon {
+            state: &self.state,
+            computing,
+        };
+        let value = f();
+
+        // Cloned before the cell is ready, so a panicking clone empties it instead of leaving it ready without a
+        // value.
+        let cached = value.clone();
+        let mut slot = self.lock();
+        let ready = (computing & !PHASE) | READY;
+        if self
+            .state
+            .compare_exchange(computing, ready, Ordering::Release, Ordering::Relaxed)
+            .is_ok()
+        {
+            *slot = Some(cached);
+        }
+        drop(slot);
+        // The value is stored, or a reset moved the cell out of the computation, so there's nothing to abandon.
+        std::mem::forget(abandon);
+        self.state.notify_all();
+        value
+    }
+}
+
+impl<T> Default for MemoCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MemoCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoCell")
+            .field("value", &*self.lock())
+            .finish()
+    }
+}
@@ -0,0 +1,667 @@
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{self, Ordering};
+
+/// Returns the failure ordering used by `compare_and_swap`, as done by `std`.
+fn strongest_failure_ordering(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Release | Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::Acquire | Ordering::AcqRel => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    }
+}
+
+macro_rules! native_atomic {
+    ($atomic:ident, $std:ident, $int:ident) => {
+        /// An integer type which can be safely shared between threads.
+        ///
+        #[doc = concat!("Wraps `std::sync::atomic::", stringify!($std), "`, which is lock-free on this target.")]
+        ///
+        /// The type is `#[repr(transparent)]`, so it has the same size, alignment and ABI as the `std` type,
+        /// can be used on FFI structs or transmuted to and from it, and derefs to it.
+        #[repr(transparent)]
+        #[derive(Default)]
+        #[cfg_attr(
+            feature = "zerocopy",
+            derive(zerocopy::FromBytes, zerocopy::IntoBytes, zerocopy::KnownLayout)
+        )]
+        pub struct $atomic {
+            value: atomic::$std,
+        }
+
+        impl $atomic {
+            /// Whether the operations are lock-free, which is always the case on the native implementation.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            #[doc = concat!("if ", stringify!($atomic), "::IS_LOCK_FREE {")]
+            ///     // use a lock-free algorithm
+            /// }
+            /// ```
+            pub const IS_LOCK_FREE: bool = true;
+
+            /// Creates a new atomic integer.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            #[doc = concat!("let atomic_forty_two = ", stringify!($atomic), "::new(42);")]
+            /// ```
+            #[inline]
+            pub const fn new(v: $int) -> Self {
+                Self {
+                    value: atomic::$std::new(v),
+                }
+            }
+
+            #[doc = concat!("Creates a new atomic integer from a `std::sync::atomic::", stringify!($std), "`.")]
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            /// use std::sync::atomic::Ordering;
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::from_std(std::sync::atomic::", stringify!($std), "::new(5));")]
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 5);
+            /// ```
+            #[inline]
+            pub const fn from_std(value: atomic::$std) -> Self {
+                Self { value }
+            }
+
+            /// Creates a new reference to an atomic integer from a pointer.
+            ///
+            /// # Safety
+            ///
+            #[doc = concat!("The same requirements as `std::sync::atomic::", stringify!($std), "::from_ptr`: `ptr` must be aligned to")]
+            /// 8 bytes, valid for reads and writes for the whole lifetime `'a`, and only accessed atomically meanwhile.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            /// use std::sync::atomic::Ordering;
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            #[doc = concat!("let alias = unsafe { ", stringify!($atomic), "::from_ptr(some_var.as_ptr()) };")]
+            /// alias.store(10, Ordering::Relaxed);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            #[inline]
+            pub unsafe fn from_ptr<'a>(ptr: *mut $int) -> &'a Self {
+                // The type is transparent over the `std` atomic.
+                &*(atomic::$std::from_ptr(ptr) as *const atomic::$std as *const Self)
+            }
+
+            #[doc = concat!("Returns a reference to the wrapped `std::sync::atomic::", stringify!($std), "`.")]
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            /// use std::sync::atomic::Ordering;
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// some_var.as_std().store(10, Ordering::Relaxed);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            #[inline]
+            pub fn as_std(&self) -> &atomic::$std {
+                &self.value
+            }
+
+            #[doc = concat!("Returns the wrapped `std::sync::atomic::", stringify!($std), "`, which is always available on the native implementation.")]
+            ///
+            /// It gives access to `std` methods the crate doesn't wrap yet, while remaining portable to the shim,
+            /// where it returns `None`.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            /// use std::sync::atomic::Ordering;
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// if let Some(native) = some_var.as_native() {
+            ///     native.fetch_max(10, Ordering::Relaxed);
+            ///     assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// }
+            /// ```
+            #[inline]
+            pub fn as_native(&self) -> Option<&atomic::$std> {
+                Some(&self.value)
+            }
+
+            /// Returns a mutable pointer to the underlying integer.
+            ///
+            #[doc = concat!("Like `std::sync::atomic::", stringify!($std), "::as_ptr`, it is only available on the native implementation,")]
+            /// as the shim keeps the integer behind its lock.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(unsafe { some_var.as_ptr().read() }, 5);
+            /// ```
+            #[inline]
+            pub const fn as_ptr(&self) -> *mut $int {
+                self.value.as_ptr()
+            }
+
+            /// Returns whether the operations on this type are lock-free.
+            ///
+            /// Mirrors C++'s `atomic::is_lock_free`, and always returns `true` on the native implementation.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            #[doc = concat!("assert_eq!(some_var.is_lock_free(), ", stringify!($atomic), "::IS_LOCK_FREE);")]
+            /// ```
+            #[inline]
+            pub fn is_lock_free(&self) -> bool {
+                Self::IS_LOCK_FREE
+            }
+
+            /// Returns a mutable reference to the underlying integer.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let mut some_var = ", stringify!($atomic), "::new(10);")]
+            /// assert_eq!(*some_var.get_mut(), 10);
+            /// *some_var.get_mut() = 5;
+            /// assert_eq!(some_var.load(Ordering::SeqCst), 5);
+            /// ```
+            #[inline]
+            pub fn get_mut(&mut self) -> &mut $int {
+                self.value.get_mut()
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.into_inner(), 5);
+            /// ```
+            #[inline]
+            pub fn into_inner(self) -> $int {
+                self.value.into_inner()
+            }
+
+            /// Loads a value from the atomic integer.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 5);
+            /// ```
+            #[inline]
+            pub fn load(&self, order: Ordering) -> $int {
+                self.value.load(order)
+            }
+
+            /// Stores a value into the atomic integer.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// some_var.store(10, Ordering::Relaxed);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            #[inline]
+            pub fn store(&self, value: $int, order: Ordering) {
+                self.value.store(value, order)
+            }
+
+            /// Stores a value into the atomic integer, returning the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.swap(10, Ordering::Relaxed), 5);
+            /// ```
+            #[inline]
+            pub fn swap(&self, value: $int, order: Ordering) -> $int {
+                self.value.swap(value, order)
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the current value.
+            ///
+            /// The return value is always the previous value. If it is equal to current, then the value was updated.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.compare_and_swap(5, 10, Ordering::Relaxed), 5);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// assert_eq!(some_var.compare_and_swap(6, 12, Ordering::Relaxed), 10);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            #[inline]
+            pub fn compare_and_swap(&self, current: $int, new: $int, order: Ordering) -> $int {
+                match self.compare_exchange(current, new, order, strongest_failure_ordering(order)) {
+                    Ok(prev) | Err(prev) => prev,
+                }
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the current value.
+            ///
+            /// The return value is a result indicating whether the new value was written and containing the previous value. On success this value is guaranteed to be equal to current.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.compare_exchange(5, 10, Ordering::Acquire, Ordering::Relaxed), Ok(5));
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// assert_eq!(some_var.compare_exchange(6, 12, Ordering::SeqCst, Ordering::Acquire), Err(10));
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            #[inline]
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.value.compare_exchange(current, new, success, failure)
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the current value.
+            ///
+            /// Unlike `compare_exchange`, this function is allowed to spuriously fail even when the comparison succeeds.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let val = ", stringify!($atomic), "::new(4);")]
+            /// let mut old = val.load(Ordering::Relaxed);
+            /// loop {
+            ///     let new = old * 2;
+            ///     match val.compare_exchange_weak(old, new, Ordering::SeqCst, Ordering::Relaxed) {
+            ///         Ok(_) => break,
+            ///         Err(x) => old = x,
+            ///     }
+            /// }
+            /// ```
+            #[inline]
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.value
+                    .compare_exchange_weak(current, new, success, failure)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0);")]
+            /// assert_eq!(foo.fetch_add(10, Ordering::SeqCst), 0);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 10);
+            /// ```
+            #[inline]
+            pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_add(val, order)
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(20);")]
+            /// assert_eq!(foo.fetch_sub(10, Ordering::SeqCst), 20);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 10);
+            /// ```
+            #[inline]
+            pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_sub(val, order)
+            }
+
+            /// Bitwise "and" with the current value.
+            ///
+            /// Performs a bitwise "and" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0b101101);")]
+            /// assert_eq!(foo.fetch_and(0b110011, Ordering::SeqCst), 0b101101);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 0b100001);
+            /// ```
+            #[inline]
+            pub fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_and(val, order)
+            }
+
+            /// Bitwise "nand" with the current value.
+            ///
+            /// Performs a bitwise "nand" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0x13);")]
+            /// assert_eq!(foo.fetch_nand(0x31, Ordering::SeqCst), 0x13);
+            /// assert_eq!(foo.load(Ordering::SeqCst), !(0x13 & 0x31));
+            /// ```
+            #[inline]
+            pub fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_nand(val, order)
+            }
+
+            /// Bitwise "or" with the current value.
+            ///
+            /// Performs a bitwise "or" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0b101101);")]
+            /// assert_eq!(foo.fetch_or(0b110011, Ordering::SeqCst), 0b101101);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 0b111111);
+            /// ```
+            #[inline]
+            pub fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_or(val, order)
+            }
+
+            /// Bitwise "xor" with the current value.
+            ///
+            /// Performs a bitwise "xor" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0b101101);")]
+            /// assert_eq!(foo.fetch_xor(0b110011, Ordering::SeqCst), 0b101101);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 0b011110);
+            /// ```
+            #[inline]
+            pub fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_xor(val, order)
+            }
+
+            /// Fetches the value, and applies a function to it that returns an optional new value.
+            ///
+            /// Returns `Ok(previous_value)` if the function returned `Some(_)`, else `Err(previous_value)`.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let x = ", stringify!($atomic), "::new(7);")]
+            /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| None), Err(7));
+            /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| Some(x + 1)), Ok(7));
+            /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| Some(x + 1)), Ok(8));
+            /// assert_eq!(x.load(Ordering::SeqCst), 9);
+            /// ```
+            #[inline]
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                self.value.fetch_update(set_order, fetch_order, f)
+            }
+
+            /// Maximum with the current value, returning the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(23);")]
+            /// assert_eq!(foo.fetch_max(42, Ordering::SeqCst), 23);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 42);
+            /// ```
+            #[inline]
+            pub fn fetch_max(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_max(val, order)
+            }
+
+            /// Minimum with the current value, returning the previous value.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(23);")]
+            /// assert_eq!(foo.fetch_min(42, Ordering::SeqCst), 23);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 23);
+            /// assert_eq!(foo.fetch_min(22, Ordering::SeqCst), 23);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 22);
+            /// ```
+            #[inline]
+            pub fn fetch_min(&self, val: $int, order: Ordering) -> $int {
+                self.value.fetch_min(val, order)
+            }
+
+            /// Returns the counts of the operations, which are always zero, as the native atomics count nothing.
+            ///
+            /// It is defined for compatibility with the shim implementation.
+            #[cfg(feature = "stats")]
+            #[inline]
+            pub fn stats(&self) -> crate::stats::Stats {
+                crate::stats::Stats::default()
+            }
+
+            /// Ignores the label, as the native atomics aren't registered.
+            ///
+            /// It is defined for compatibility with the shim implementation.
+            #[cfg(feature = "registry")]
+            #[inline]
+            pub fn set_label(&self, _: impl Into<String>) {}
+
+            /// Returns the last writes, which are always empty, as the native atomics record nothing.
+            ///
+            /// It is defined for compatibility with the shim implementation.
+            #[cfg(feature = "history")]
+            #[inline]
+            pub fn history(&self) -> Vec<crate::history::Operation<$int>> {
+                Vec::new()
+            }
+        }
+
+        const _: () = assert!(mem::size_of::<$atomic>() == mem::size_of::<atomic::$std>());
+        const _: () = assert!(mem::align_of::<$atomic>() == mem::align_of::<atomic::$std>());
+
+        /// Formats the value like `std` atomics.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+        #[doc = concat!("assert_eq!(format!(\"{:?}\", ", stringify!($atomic), "::new(5)), \"5\");")]
+        /// ```
+        impl fmt::Debug for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.value, f)
+            }
+        }
+
+        /// Formats a snapshot of the value, loaded with `Ordering::Relaxed`, as `Display`, `LowerHex`, `UpperHex` and `Binary`.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+        ///
+        #[doc = concat!("let mask = ", stringify!($atomic), "::new(42);")]
+        /// assert_eq!(format!("{} {:x} {:X} {:b}", mask, mask, mask, mask), "42 2a 2A 101010");
+        /// ```
+        impl fmt::Display for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+
+        impl fmt::LowerHex for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::LowerHex::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+
+        impl fmt::UpperHex for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::UpperHex::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+
+        impl fmt::Binary for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Binary::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+
+        /// Creates a new atomic holding a snapshot of the value, loaded with `Ordering::SeqCst`.
+        ///
+        /// Only available with the `clone` feature, as cloning an atomic is usually a mistake.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use std::sync::atomic::Ordering;
+        #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+        ///
+        #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+        /// let snapshot = some_var.clone();
+        /// some_var.store(10, Ordering::SeqCst);
+        /// assert_eq!(snapshot.load(Ordering::SeqCst), 5);
+        /// ```
+        #[cfg(feature = "clone")]
+        impl Clone for $atomic {
+            fn clone(&self) -> Self {
+                Self::new(self.load(Ordering::SeqCst))
+            }
+        }
+
+        impl From<$int> for $atomic {
+            fn from(value: $int) -> Self {
+                $atomic::new(value)
+            }
+        }
+
+        impl From<atomic::$std> for $atomic {
+            fn from(value: atomic::$std) -> Self {
+                $atomic::from_std(value)
+            }
+        }
+
+        impl From<$atomic> for atomic::$std {
+            fn from(value: $atomic) -> Self {
+                value.value
+            }
+        }
+
+        /// Dereferences to the wrapped `std` atomic, so the wrapper can be passed where a reference to it is expected.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+        /// use std::sync::atomic::{self, Ordering};
+        ///
+        #[doc = concat!("fn bump(counter: &atomic::", stringify!($std), ") {")]
+        ///     counter.fetch_add(1, Ordering::Relaxed);
+        /// }
+        ///
+        #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+        /// bump(&some_var);
+        /// assert_eq!(some_var.load(Ordering::Relaxed), 6);
+        /// ```
+        impl Deref for $atomic {
+            type Target = atomic::$std;
+
+            #[inline]
+            fn deref(&self) -> &atomic::$std {
+                &self.value
+            }
+        }
+    };
+}
+
+#[cfg(not(atomic_shim_fallback_u64))]
+native_atomic!(AtomicU64, AtomicU64, u64);
+
+#[cfg(not(atomic_shim_fallback_i64))]
+native_atomic!(AtomicI64, AtomicI64, i64);
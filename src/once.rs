@@ -0,0 +1,153 @@
+//! A `u64` set once, then read by many threads, for caching values which are expensive to compute.
+//!
+//! File sizes or hashes cached in a global are computed by the first thread reading them, while the other
+//! threads wait for the result instead of computing it again. The value is only written before the cell is
+//! marked as initialized, so it is a plain `u64` published by a word-sized atomic, which every target with
+//! threads has, and reading it never takes the shim's lock.
+
+use crate::wait;
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const EMPTY: usize = 0;
+const RUNNING: usize = 1;
+const DONE: usize = 2;
+
+/// A `u64` which is written once, by the first thread initializing it, like a `OnceLock<u64>`.
+///
+/// It can be created in a `static`, as its constructor is `const`.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::OnceU64;
+/// use std::thread;
+///
+/// static SIZE: OnceU64 = OnceU64::new();
+///
+/// let readers: Vec<_> = (0..4)
+///     .map(|_| thread::spawn(|| SIZE.get_or_init(|| 4096)))
+///     .collect();
+/// for reader in readers {
+///     assert_eq!(reader.join().unwrap(), 4096);
+/// }
+///
+/// assert_eq!(SIZE.get(), Some(4096));
+/// assert_eq!(SIZE.set(0), Err(0));
+/// ```
+pub struct OnceU64 {
+    state: AtomicUsize,
+    value: UnsafeCell<u64>,
+}
+
+// The value is only written by the thread which moved the state to `RUNNING`, and only read once it is `DONE`.
+unsafe impl Sync for OnceU64 {}
+
+/// Sets the cell back to empty if the initializer panics, so another thread can try again.
+struct Reset<'a>(&'a OnceU64);
+
+impl Drop for Reset<'_> {
+    fn drop(&mut self) {
+        self.0.state.store(EMPTY, Ordering::Release);
+        wait::notify(self.0.address());
+    }
+}
+
+impl OnceU64 {
+    /// Creates an empty cell.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(EMPTY),
+            value: UnsafeCell::new(0),
+        }
+    }
+
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Returns the value, or `None` if the cell wasn't initialized yet.
+    pub fn get(&self) -> Option<u64> {
+        if self.state.load(Ordering::Acquire) == DONE {
+            Some(unsafe { *self.value.get() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value, initializing it with `f` if the cell is empty.
+    ///
+    /// Only one thread runs `f`, and the others block until it returns. If `f` panics, the panic is propagated,
+    /// and the cell stays empty.
+    pub fn get_or_init<F: FnOnce() -> u64>(&self, f: F) -> u64 {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        loop {
+            match self
+                .state
+                .compare_exchange(EMPTY, RUNNING, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    let reset = Reset(self);
+                    let value = f();
+                    unsafe { *self.value.get() = value };
+                    std::mem::forget(reset);
+                    self.state.store(DONE, Ordering::Release);
+                    wait::notify(self.address());
+                    return value;
+                }
+                Err(DONE) => return unsafe { *self.value.get() },
+                Err(_) => wait::wait_while(self.address(), || {
+                    self.state.load(Ordering::Acquire) == RUNNING
+                }),
+            }
+        }
+    }
+
+    /// Initializes the cell with `value`, or returns it back if the cell was already initialized.
+    ///
+    /// Blocks while another thread initializes the cell.
+    pub fn set(&self, value: u64) -> Result<(), u64> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap_or_default());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Consumes the cell, returning the value, if it was initialized.
+    pub fn into_inner(self) -> Option<u64> {
+        if self.state.into_inner() == DONE {
+            Some(self.value.into_inner())
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for OnceU64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<u64> for OnceU64 {
+    fn from(value: u64) -> Self {
+        Self {
+            state: AtomicUsize::new(DONE),
+            value: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl fmt::Debug for OnceU64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceU64").field(&value).finish(),
+            None => f.write_str("OnceU64(<uninit>)"),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Values aligned to the cache line, so neighbouring hot atomics don't share one.
+//!
+//! In an array of counters, the cores updating different counters keep invalidating each other's copy of the
+//! line holding them. On the shim, the lock words sit next to the values, so they are shared too.
+//! The alignments follow `crossbeam-utils`' `CachePadded`, which accounts for the prefetchers fetching lines in pairs.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// Pads and aligns a value to the length of a cache line.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{AtomicU64, Padded};
+/// use std::sync::atomic::Ordering;
+///
+/// let counters: Vec<Padded<AtomicU64>> = (0..4).map(|_| Padded::new(AtomicU64::new(0))).collect();
+/// counters[1].fetch_add(1, Ordering::Relaxed);
+///
+/// assert_eq!(counters[1].load(Ordering::Relaxed), 1);
+/// assert!(std::mem::align_of::<Padded<AtomicU64>>() >= 16);
+/// ```
+#[cfg_attr(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64",
+        target_arch = "arm64ec"
+    ),
+    repr(align(128))
+)]
+#[cfg_attr(
+    any(
+        target_arch = "arm",
+        target_arch = "mips",
+        target_arch = "mips32r6",
+        target_arch = "mips64",
+        target_arch = "mips64r6",
+        target_arch = "sparc",
+        target_arch = "hexagon"
+    ),
+    repr(align(32))
+)]
+#[cfg_attr(target_arch = "m68k", repr(align(16)))]
+#[cfg_attr(target_arch = "s390x", repr(align(256)))]
+#[cfg_attr(
+    not(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64",
+        target_arch = "arm64ec",
+        target_arch = "arm",
+        target_arch = "mips",
+        target_arch = "mips32r6",
+        target_arch = "mips64",
+        target_arch = "mips64r6",
+        target_arch = "sparc",
+        target_arch = "hexagon",
+        target_arch = "m68k",
+        target_arch = "s390x"
+    )),
+    repr(align(64))
+)]
+#[derive(Default)]
+pub struct Padded<T> {
+    value: T,
+}
+
+impl<T> Padded<T> {
+    /// Pads and aligns `value` to the length of a cache line.
+    pub const fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    /// Returns the padded value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Padded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Padded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> From<T> for Padded<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Formats the value, as the padding is an implementation detail.
+impl<T: fmt::Debug> fmt::Debug for Padded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.value, f)
+    }
+}
@@ -0,0 +1,204 @@
+//! Two `u64`s compared and exchanged together, for algorithms pairing a value with a version.
+//!
+//! Lock-free stacks and queues avoid the ABA problem by bumping a version along with the value they swap, which
+//! takes a compare-exchange of both words at once. On x86-64 CPUs with `cmpxchg16b`, detected at runtime, the
+//! pair uses it, and every other target, as well as Miri, uses a lock stored alongside the pair.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Two `u64`s updated together, with a compare-exchange of both.
+///
+/// Every operation is sequentially consistent. The pair is aligned to 16 bytes, as `cmpxchg16b` requires.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64Pair;
+///
+/// // a value, and the version bumped with each of its changes
+/// let head = AtomicU64Pair::new((7, 0));
+/// assert_eq!(head.compare_exchange((7, 0), (8, 1)), Ok((7, 0)));
+///
+/// // another thread read the head before the change, so its exchange fails even if the value came back to 7
+/// head.store((7, 2));
+/// assert_eq!(head.compare_exchange((7, 0), (9, 1)), Err((7, 2)));
+/// ```
+#[repr(C, align(16))]
+pub struct AtomicU64Pair {
+    value: UnsafeCell<[u64; 2]>,
+    /// Guards the value when `cmpxchg16b` isn't available.
+    lock: Mutex<()>,
+}
+
+// The value is only accessed through `cmpxchg16b`, or under the lock.
+unsafe impl Sync for AtomicU64Pair {}
+
+#[cfg(all(target_arch = "x86_64", not(miri)))]
+mod cmpxchg16b {
+    use std::arch::asm;
+
+    /// Whether the CPU has `cmpxchg16b`, which `std` detects once.
+    pub(super) fn detected() -> bool {
+        cfg!(target_feature = "cmpxchg16b") || std::is_x86_feature_detected!("cmpxchg16b")
+    }
+
+    /// Stores `new` at `dst` if it holds `old`, returning the previous value, and whether it was stored.
+    ///
+    /// # Safety
+    ///
+    /// `dst` must be valid for writes, aligned to 16 bytes, and the CPU must have `cmpxchg16b`.
+    #[target_feature(enable = "cmpxchg16b")]
+    pub(super) unsafe fn compare_exchange(
+        dst: *mut [u64; 2],
+        old: [u64; 2],
+        new: [u64; 2],
+    ) -> ([u64; 2], bool) {
+        let (low, high): (u64, u64);
+        let stored: u8;
+        // `rbx` is reserved by LLVM, so the low half of `new` is swapped into it around the instruction.
+        asm!(
+            "xchg {new_low}, rbx",
+            "lock cmpxchg16b xmmword ptr [{dst}]",
+            "sete {stored}",
+            "mov rbx, {new_low}",
+            dst = in(reg) dst,
+            new_low = inout(reg) new[0] => _,
+            stored = out(reg_byte) stored,
+            in("rcx") new[1],
+            inout("rax") old[0] => low,
+            inout("rdx") old[1] => high,
+            options(nostack),
+        );
+        ([low, high], stored != 0)
+    }
+}
+
+impl AtomicU64Pair {
+    /// Creates a pair holding `value`.
+    pub fn new(value: (u64, u64)) -> Self {
+        Self {
+            value: UnsafeCell::new([value.0, value.1]),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Returns whether the operations use `cmpxchg16b` instead of the lock.
+    pub fn is_lock_free(&self) -> bool {
+        #[cfg(all(target_arch = "x86_64", not(miri)))]
+        {
+            cmpxchg16b::detected()
+        }
+        #[cfg(not(all(target_arch = "x86_64", not(miri))))]
+        {
+            false
+        }
+    }
+
+    /// The lock guards no data, so a poisoned lock is still safe to use.
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Stores the value returned by `f` if it isn't `None`, returning the previous value,
+    /// as `Ok` if it was stored, and as `Err` if it wasn't.
+    ///
+    /// `guess` is the expected value, which `cmpxchg16b` starts from, as a failed exchange returns the current value.
+    fn update(
+        &self,
+        guess: [u64; 2],
+        f: impl Fn([u64; 2]) -> Option<[u64; 2]>,
+    ) -> Result<[u64; 2], [u64; 2]> {
+        #[cfg(all(target_arch = "x86_64", not(miri)))]
+        if cmpxchg16b::detected() {
+            let mut current = guess;
+            loop {
+                // Exchanging the value with itself checks that it didn't change, without changing it.
+                let new = f(current);
+                let (prev, stored) = unsafe {
+                    cmpxchg16b::compare_exchange(self.value.get(), current, new.unwrap_or(current))
+                };
+                match (stored, new) {
+                    (true, Some(_)) => return Ok(prev),
+                    (true, None) => return Err(prev),
+                    (false, _) => current = prev,
+                }
+            }
+        }
+        #[cfg(not(all(target_arch = "x86_64", not(miri))))]
+        let _ = guess;
+        let _guard = self.lock();
+        let value = unsafe { &mut *self.value.get() };
+        let prev = *value;
+        match f(prev) {
+            Some(new) => {
+                *value = new;
+                Ok(prev)
+            }
+            None => Err(prev),
+        }
+    }
+
+    /// Returns the value.
+    pub fn load(&self) -> (u64, u64) {
+        let [first, second] = match self.update([0, 0], |_| None) {
+            Ok(value) | Err(value) => value,
+        };
+        (first, second)
+    }
+
+    /// Stores `value`.
+    pub fn store(&self, value: (u64, u64)) {
+        self.swap(value);
+    }
+
+    /// Stores `value`, returning the previous value.
+    pub fn swap(&self, value: (u64, u64)) -> (u64, u64) {
+        let [first, second] = match self.update([0, 0], |_| Some([value.0, value.1])) {
+            Ok(value) | Err(value) => value,
+        };
+        (first, second)
+    }
+
+    /// Stores `new` if the pair holds `current`, returning the previous value,
+    /// as `Ok` if it was stored, and as `Err` if it wasn't.
+    pub fn compare_exchange(
+        &self,
+        current: (u64, u64),
+        new: (u64, u64),
+    ) -> Result<(u64, u64), (u64, u64)> {
+        let current = [current.0, current.1];
+        let new = [new.0, new.1];
+        self.update(
+            current,
+            |value| if value == current { Some(new) } else { None },
+        )
+        .map(|[first, second]| (first, second))
+        .map_err(|[first, second]| (first, second))
+    }
+
+    /// Consumes the pair, returning its value.
+    pub fn into_inner(self) -> (u64, u64) {
+        let [first, second] = self.value.into_inner();
+        (first, second)
+    }
+}
+
+impl Default for AtomicU64Pair {
+    fn default() -> Self {
+        Self::new((0, 0))
+    }
+}
+
+impl From<(u64, u64)> for AtomicU64Pair {
+    fn from(value: (u64, u64)) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for AtomicU64Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(), f)
+    }
+}
@@ -0,0 +1,111 @@
+//! A counter split in one shard per CPU, for hot counters which are updated much more often than they are read.
+//!
+//! Each update goes to the shard of the CPU the thread runs on, found with `sched_getcpu`, which glibc 2.35
+//! and newer serve from the thread's restartable sequences (`rseq`) area, without a syscall.
+//! Threads on different CPUs never touch the same shard, or the same cache line, so on the shim their
+//! updates don't contend on a lock. Reads fold every shard.
+//!
+//! The shards are the crate's `AtomicU64`, as a thread can migrate to another CPU between reading its CPU number
+//! and updating the shard. A full `rseq` critical section, committing the update only if the thread didn't migrate,
+//! would need inline assembly, which Rust only supports on stable for targets which already have 64-bit atomics.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::per_cpu::PerCpuCounter;
+//!
+//! let requests = PerCpuCounter::new();
+//! requests.add(2);
+//! requests.increment();
+//! assert_eq!(requests.sum(), 3);
+//! ```
+
+use crate::AtomicU64;
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// A shard, aligned to its own cache line so neighbouring CPUs don't share it.
+#[derive(Default)]
+#[repr(align(64))]
+struct Shard(AtomicU64);
+
+/// A counter with one shard per CPU, folded on read.
+///
+/// The updates are `Relaxed`, as the counter is meant for statistics, and the sum is not a snapshot:
+/// updates made while it is computed may or may not be included.
+pub struct PerCpuCounter {
+    shards: Box<[Shard]>,
+}
+
+impl PerCpuCounter {
+    /// Creates a counter with a shard for each CPU configured on the system.
+    pub fn new() -> Self {
+        let cpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) };
+        Self::with_shards(cpus.max(1) as usize)
+    }
+
+    /// Creates a counter with `shards` shards, shared by the CPUs past that number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "a counter needs at least one shard");
+        Self {
+            shards: (0..shards).map(|_| Shard::default()).collect(),
+        }
+    }
+
+    fn shard(&self) -> &AtomicU64 {
+        let cpu = unsafe { libc::sched_getcpu() };
+        let index = if cpu < 0 {
+            0
+        } else {
+            cpu as usize % self.shards.len()
+        };
+        &self.shards[index].0
+    }
+
+    /// Adds to the shard of the current CPU.
+    ///
+    /// This operation wraps around on overflow.
+    pub fn add(&self, val: u64) {
+        self.shard().fetch_add(val, Ordering::Relaxed);
+    }
+
+    /// Adds one to the shard of the current CPU.
+    pub fn increment(&self) {
+        self.add(1)
+    }
+
+    /// Returns the sum of every shard.
+    pub fn sum(&self) -> u64 {
+        self.shards.iter().fold(0, |sum, shard| {
+            sum.wrapping_add(shard.0.load(Ordering::Relaxed))
+        })
+    }
+
+    /// Returns the sum of every shard, setting them to zero.
+    pub fn take(&self) -> u64 {
+        self.shards.iter().fold(0, |sum, shard| {
+            sum.wrapping_add(shard.0.swap(0, Ordering::Relaxed))
+        })
+    }
+
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+}
+
+impl Default for PerCpuCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for PerCpuCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.sum(), f)
+    }
+}
@@ -0,0 +1,178 @@
+//! An arena of atomic counters, for applications needing tens of thousands of them.
+//!
+//! A shimmed [`AtomicU64`](crate::AtomicU64) carries its own lock, so it takes several times the size of its
+//! value. The counters of a pool are bare `u64`s in chunks of contiguous slots, operated on like a
+//! [`RawAtomicU64`], so on the shim they share the global table of locks keyed by address, and the storage of a
+//! counter takes 8 bytes. The slots freed are linked through their own storage, so the free list takes no memory
+//! either.
+
+use crate::RawAtomicU64;
+use std::fmt;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The number of slots of a chunk.
+const CHUNK: usize = 1024;
+
+/// The index of a free slot ending the free list.
+const NONE: u64 = u64::MAX;
+
+/// The storage of a counter, aligned to 8 bytes as [`RawAtomicU64::from_raw`] requires, even on 32-bit targets.
+#[repr(align(8))]
+struct Slot(u64);
+
+struct Slots {
+    /// The chunks, allocated as boxed slices of `CHUNK` slots, and never moved until the pool is dropped.
+    chunks: Vec<*mut Slot>,
+    /// The index of the first free slot, each free slot holding the index of the next one.
+    free: u64,
+}
+
+unsafe impl Send for Slots {}
+
+impl Slots {
+    /// Returns the storage of the slot at `index`.
+    fn slot(&self, index: u64) -> *mut u64 {
+        let index = index as usize;
+        unsafe { ptr::addr_of_mut!((*self.chunks[index / CHUNK].add(index % CHUNK)).0) }
+    }
+
+    /// Allocates a chunk, and links its slots at the front of the free list.
+    fn grow(&mut self) {
+        let first = (self.chunks.len() * CHUNK) as u64;
+        let chunk: Box<[Slot]> = (1..=CHUNK as u64)
+            .map(|next| {
+                Slot(if next == CHUNK as u64 {
+                    self.free
+                } else {
+                    first + next
+                })
+            })
+            .collect();
+        self.chunks.push(Box::into_raw(chunk) as *mut Slot);
+        self.free = first;
+    }
+}
+
+/// An arena handing out atomic counters backed by its chunks of slots.
+///
+/// The counters are handed out as [`PoolRef`]s, which dereference to a [`RawAtomicU64`] and give their slot back
+/// to the pool when dropped. The pool grows by chunks of 1024 slots, and keeps them until it is dropped, reusing
+/// the slots freed. Allocating and freeing take the lock of the pool; the operations on the counters don't.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64Pool;
+/// use std::sync::atomic::Ordering;
+///
+/// let pool = AtomicU64Pool::new();
+/// let sessions: Vec<_> = (0..10_000).map(|_| pool.alloc(0)).collect();
+/// for session in &sessions {
+///     session.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// let total: u64 = sessions.iter().map(|session| session.load(Ordering::Relaxed)).sum();
+/// assert_eq!(total, 10_000);
+/// ```
+pub struct AtomicU64Pool {
+    slots: Mutex<Slots>,
+}
+
+impl AtomicU64Pool {
+    /// Creates an empty pool, which allocates its first chunk with its first counter.
+    pub fn new() -> Self {
+        Self {
+            slots: Mutex::new(Slots {
+                chunks: Vec::new(),
+                free: NONE,
+            }),
+        }
+    }
+
+    /// No code panics while holding the lock, so a poisoned lock can't have left the free list half-updated.
+    fn lock(&self) -> MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Hands out a counter starting at `value`.
+    pub fn alloc(&self, value: u64) -> PoolRef<'_> {
+        let mut slots = self.lock();
+        if slots.free == NONE {
+            slots.grow();
+        }
+        let index = slots.free;
+        let slot = slots.slot(index);
+        // The slot is only used by the pool while it is free, under its lock.
+        unsafe {
+            slots.free = slot.read();
+            slot.write(value);
+        }
+        PoolRef {
+            atomic: unsafe { RawAtomicU64::from_raw(slot) },
+            index,
+            pool: self,
+        }
+    }
+
+    /// Returns the number of slots of the chunks allocated, in use or free.
+    pub fn capacity(&self) -> usize {
+        self.lock().chunks.len() * CHUNK
+    }
+}
+
+impl Default for AtomicU64Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AtomicU64Pool {
+    fn drop(&mut self) {
+        let slots = self.slots.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for &chunk in &slots.chunks {
+            drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(chunk, CHUNK)) });
+        }
+    }
+}
+
+impl fmt::Debug for AtomicU64Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicU64Pool")
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+/// A counter of an [`AtomicU64Pool`], giving its slot back to the pool when dropped.
+///
+/// It dereferences to the [`RawAtomicU64`] operating on its slot.
+pub struct PoolRef<'a> {
+    atomic: RawAtomicU64,
+    index: u64,
+    pool: &'a AtomicU64Pool,
+}
+
+impl Deref for PoolRef<'_> {
+    type Target = RawAtomicU64;
+
+    fn deref(&self) -> &RawAtomicU64 {
+        &self.atomic
+    }
+}
+
+impl Drop for PoolRef<'_> {
+    fn drop(&mut self) {
+        let mut slots = self.pool.lock();
+        // Taking the lock of the pool orders the last operations on the counter before the slot is reused.
+        unsafe { self.atomic.as_ptr().write(slots.free) };
+        slots.free = self.index;
+    }
+}
+
+impl fmt::Debug for PoolRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.atomic, f)
+    }
+}
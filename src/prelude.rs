@@ -0,0 +1,14 @@
+//! Single import for the atomic types and `Ordering`.
+//!
+//! ```
+//! use atomic_shim::prelude::*;
+//!
+//! let counter = AtomicU64::new(0);
+//! counter.fetch_add(1, Ordering::Relaxed);
+//! assert_eq!(counter.load(Ordering::Relaxed), 1);
+//! ```
+
+pub use crate::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16,
+    AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering,
+};
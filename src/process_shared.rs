@@ -0,0 +1,481 @@
+//! A 64-bit atomic which is safe to share between processes, through a shared memory segment.
+//!
+//! The shim's `ShardedLock` only synchronizes threads of the same process, so a shimmed atomic placed in an
+//! `mmap`'d segment is not protected against other processes. This `AtomicU64` is `#[repr(C)]`, and places a
+//! `PTHREAD_PROCESS_SHARED` mutex alongside the value, so every process mapping the segment uses the same lock.
+//!
+//! A pthread mutex can't be moved once initialized, so the atomic is never created by value:
+//! one process initializes it in place with [`AtomicU64::init`], and the others borrow it with
+//! [`AtomicU64::from_ptr`] once it is initialized.
+//!
+//! Like the shim, the operations ignore the `Ordering` argument, as every access is serialized by the mutex.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::process_shared::AtomicU64;
+//! use std::mem;
+//! use std::ptr;
+//! use std::sync::atomic::Ordering;
+//!
+//! unsafe {
+//!     let segment = libc::mmap(
+//!         ptr::null_mut(),
+//!         mem::size_of::<AtomicU64>(),
+//!         libc::PROT_READ | libc::PROT_WRITE,
+//!         libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+//!         -1,
+//!         0,
+//!     );
+//!     assert_ne!(segment, libc::MAP_FAILED);
+//!
+//!     let counter = AtomicU64::init(segment.cast(), 1).unwrap();
+//!     counter.fetch_add(1, Ordering::SeqCst);
+//!     assert_eq!(counter.load(Ordering::SeqCst), 2);
+//!
+//!     AtomicU64::destroy(segment.cast());
+//!     libc::munmap(segment, mem::size_of::<AtomicU64>());
+//! }
+//! ```
+//!
+//! # Robust mode
+//!
+//! A process which dies while holding the mutex would block the others forever.
+//! On Linux and FreeBSD, [`AtomicU64::init_robust`] initializes a robust mutex instead, so the next process to
+//! lock it is told the owner died. [`AtomicU64::lock`] exposes that decision to the caller, with the last value
+//! stored, which is never torn, as every write is a single store under the mutex. The caller either recovers
+//! the value, possibly replacing it, or abandons the atomic, making every later lock fail.
+//! The atomic operations always recover, keeping the last value.
+//!
+//! ```
+//! # #[cfg(target_os = "linux")]
+//! # {
+//! use atomic_shim::process_shared::AtomicU64;
+//! use std::mem::MaybeUninit;
+//!
+//! let mut storage = MaybeUninit::<AtomicU64>::uninit();
+//! let counter = unsafe { AtomicU64::init_robust(storage.as_mut_ptr(), 7) }.unwrap();
+//!
+//! let guard = match counter.lock() {
+//!     Ok(guard) => guard,
+//!     Err(owner_died) => {
+//!         let mut guard = owner_died.recover();
+//!         guard.set(0);
+//!         guard
+//!     }
+//! };
+//! assert_eq!(guard.get(), 7);
+//! # drop(guard);
+//! # unsafe { AtomicU64::destroy(storage.as_mut_ptr()) };
+//! # }
+//! ```
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::sync::atomic::Ordering;
+
+/// Converts the return code of a pthread function into a `Result`.
+fn check(code: libc::c_int) -> io::Result<()> {
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(code))
+    }
+}
+
+/// An integer type which can be safely shared between processes, through shared memory.
+#[repr(C)]
+pub struct AtomicU64 {
+    mutex: UnsafeCell<libc::pthread_mutex_t>,
+    value: UnsafeCell<u64>,
+}
+
+unsafe impl Send for AtomicU64 {}
+unsafe impl Sync for AtomicU64 {}
+
+impl AtomicU64 {
+    /// Whether the operations are lock-free, which is never the case, as they run under the mutex.
+    pub const IS_LOCK_FREE: bool = false;
+
+    /// Initializes an atomic integer in place, with a `PTHREAD_PROCESS_SHARED` mutex.
+    ///
+    /// Returns the error reported by pthread if the mutex can't be initialized.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and aligned for `AtomicU64`, and must not be used by another
+    /// process or thread until this function returns. The atomic must not be moved afterwards.
+    pub unsafe fn init<'a>(ptr: *mut Self, value: u64) -> io::Result<&'a Self> {
+        Self::init_with(ptr, value, false)
+    }
+
+    /// Initializes an atomic integer in place, with a robust `PTHREAD_PROCESS_SHARED` mutex,
+    /// so the death of a process holding it can be recovered by the others with [`AtomicU64::lock`].
+    ///
+    /// Returns the error reported by pthread if the mutex can't be initialized.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`AtomicU64::init`].
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub unsafe fn init_robust<'a>(ptr: *mut Self, value: u64) -> io::Result<&'a Self> {
+        Self::init_with(ptr, value, true)
+    }
+
+    unsafe fn init_with<'a>(ptr: *mut Self, value: u64, robust: bool) -> io::Result<&'a Self> {
+        let mut attr = MaybeUninit::<libc::pthread_mutexattr_t>::uninit();
+        check(libc::pthread_mutexattr_init(attr.as_mut_ptr()))?;
+        let result = check(libc::pthread_mutexattr_setpshared(
+            attr.as_mut_ptr(),
+            libc::PTHREAD_PROCESS_SHARED,
+        ))
+        .and_then(|()| {
+            if robust {
+                set_robust(attr.as_mut_ptr())
+            } else {
+                Ok(())
+            }
+        })
+        .and_then(|()| {
+            check(libc::pthread_mutex_init(
+                UnsafeCell::raw_get(&(*ptr).mutex),
+                attr.as_ptr(),
+            ))
+        });
+        libc::pthread_mutexattr_destroy(attr.as_mut_ptr());
+        result?;
+
+        *UnsafeCell::raw_get(&(*ptr).value) = value;
+        Ok(&*ptr)
+    }
+
+    /// Borrows an atomic integer which was initialized with [`AtomicU64::init`], possibly by another process.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to an initialized atomic, which stays mapped and is not destroyed during `'a`.
+    pub unsafe fn from_ptr<'a>(ptr: *mut Self) -> &'a Self {
+        &*ptr
+    }
+
+    /// Destroys the mutex of an atomic integer initialized with [`AtomicU64::init`].
+    ///
+    /// # Safety
+    ///
+    /// No process may use the atomic during or after this call, until it is initialized again.
+    pub unsafe fn destroy(ptr: *mut Self) {
+        #[cfg(all(feature = "diagnostics", debug_assertions))]
+        crate::lock_order::forget(ptr as usize);
+        libc::pthread_mutex_destroy(UnsafeCell::raw_get(&(*ptr).mutex));
+    }
+
+    /// Returns whether the operations are lock-free, which is never the case.
+    pub fn is_lock_free(&self) -> bool {
+        Self::IS_LOCK_FREE
+    }
+
+    /// Locks the mutex, giving access to the value until the guard is dropped.
+    ///
+    /// Returns [`OwnerDied`] when the mutex is robust, and the process holding it died,
+    /// leaving the decision of recovering the value to the caller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked, such as when a previous owner death was not recovered.
+    ///
+    /// With the `diagnostics` feature, in debug builds, also panics when this thread already holds the mutex,
+    /// or when the atomics are locked in the opposite order of another thread, which would risk a deadlock.
+    pub fn lock(&self) -> Result<Guard<'_>, OwnerDied<'_>> {
+        #[cfg(all(feature = "diagnostics", debug_assertions))]
+        crate::lock_order::acquire(self as *const Self as usize);
+        let code = unsafe { libc::pthread_mutex_lock(self.mutex.get()) };
+        let guard = Guard {
+            atomic: self,
+            _not_send: PhantomData,
+        };
+        match code {
+            0 => Ok(guard),
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            libc::EOWNERDEAD => Err(OwnerDied { guard }),
+            _ => {
+                mem::forget(guard);
+                #[cfg(all(feature = "diagnostics", debug_assertions))]
+                crate::lock_order::release(self as *const Self as usize);
+                panic!(
+                    "failed to lock the process-shared mutex: {}",
+                    io::Error::from_raw_os_error(code)
+                );
+            }
+        }
+    }
+
+    /// Runs `f` on the value while holding the mutex, recovering it if its owner died.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    fn with<T>(&self, f: impl FnOnce(&mut u64) -> T) -> T {
+        let mut guard = self.lock().unwrap_or_else(OwnerDied::recover);
+        f(guard.value_mut())
+    }
+
+    /// Loads a value from the atomic integer.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn load(&self, _: Ordering) -> u64 {
+        self.with(|value| *value)
+    }
+
+    /// Stores a value into the atomic integer.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn store(&self, value: u64, _: Ordering) {
+        self.with(|v| *v = value)
+    }
+
+    /// Stores a value into the atomic integer, returning the previous value.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn swap(&self, value: u64, _: Ordering) -> u64 {
+        self.with(|v| mem::replace(v, value))
+    }
+
+    /// Stores a value into the atomic integer if the current value is the same as the `current` value.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing the previous value.
+    /// On success this value is guaranteed to be equal to `current`.
+    ///
+    /// It ignores the Ordering arguments, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn compare_exchange(
+        &self,
+        current: u64,
+        new: u64,
+        _: Ordering,
+        _: Ordering,
+    ) -> Result<u64, u64> {
+        self.with(|v| {
+            let prev = *v;
+            if prev == current {
+                *v = new;
+                Ok(prev)
+            } else {
+                Err(prev)
+            }
+        })
+    }
+
+    /// Stores a value into the atomic integer if the current value is the same as the `current` value.
+    ///
+    /// It never fails spuriously, as the access happens under the mutex.
+    ///
+    /// It ignores the Ordering arguments, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn compare_exchange_weak(
+        &self,
+        current: u64,
+        new: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64> {
+        self.compare_exchange(current, new, success, failure)
+    }
+
+    /// Runs `f` on the value while holding the mutex, storing the value it returns,
+    /// and returns the previous value.
+    fn update(&self, f: impl FnOnce(u64) -> u64) -> u64 {
+        self.with(|v| {
+            let prev = *v;
+            *v = f(prev);
+            prev
+        })
+    }
+
+    /// Adds to the current value, returning the previous value.
+    ///
+    /// This operation wraps around on overflow.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn fetch_add(&self, val: u64, _: Ordering) -> u64 {
+        self.update(|prev| prev.wrapping_add(val))
+    }
+
+    /// Subtracts from the current value, returning the previous value.
+    ///
+    /// This operation wraps around on overflow.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn fetch_sub(&self, val: u64, _: Ordering) -> u64 {
+        self.update(|prev| prev.wrapping_sub(val))
+    }
+
+    /// Bitwise "and" with the current value, returning the previous value.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn fetch_and(&self, val: u64, _: Ordering) -> u64 {
+        self.update(|prev| prev & val)
+    }
+
+    /// Bitwise "nand" with the current value, returning the previous value.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn fetch_nand(&self, val: u64, _: Ordering) -> u64 {
+        self.update(|prev| !(prev & val))
+    }
+
+    /// Bitwise "or" with the current value, returning the previous value.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn fetch_or(&self, val: u64, _: Ordering) -> u64 {
+        self.update(|prev| prev | val)
+    }
+
+    /// Bitwise "xor" with the current value, returning the previous value.
+    ///
+    /// It ignores the Ordering argument, as the access happens under the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex can't be locked.
+    pub fn fetch_xor(&self, val: u64, _: Ordering) -> u64 {
+        self.update(|prev| prev ^ val)
+    }
+}
+
+/// Access to the value of a process-shared atomic, while holding its mutex.
+///
+/// The mutex is unlocked when the guard is dropped.
+pub struct Guard<'a> {
+    atomic: &'a AtomicU64,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Guard<'_> {
+    /// Returns the value.
+    pub fn get(&self) -> u64 {
+        unsafe { *self.atomic.value.get() }
+    }
+
+    /// Replaces the value.
+    pub fn set(&mut self, value: u64) {
+        *self.value_mut() = value;
+    }
+
+    fn value_mut(&mut self) -> &mut u64 {
+        unsafe { &mut *self.atomic.value.get() }
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::pthread_mutex_unlock(self.atomic.mutex.get());
+        }
+        #[cfg(all(feature = "diagnostics", debug_assertions))]
+        crate::lock_order::release(self.atomic as *const AtomicU64 as usize);
+    }
+}
+
+impl fmt::Debug for Guard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Guard").field(&self.get()).finish()
+    }
+}
+
+/// The error returned by [`AtomicU64::lock`] when the process holding a robust mutex died.
+///
+/// The mutex is held, and the caller decides what to do with the value left by the dead process:
+/// [`OwnerDied::recover`] makes the mutex usable again, while dropping it abandons the atomic,
+/// making every later lock fail.
+pub struct OwnerDied<'a> {
+    guard: Guard<'a>,
+}
+
+impl<'a> OwnerDied<'a> {
+    /// Returns the last value stored before the owner died.
+    pub fn value(&self) -> u64 {
+        self.guard.get()
+    }
+
+    /// Marks the mutex as consistent again, returning the guard to inspect or replace the value.
+    pub fn recover(self) -> Guard<'a> {
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        unsafe {
+            libc::pthread_mutex_consistent(self.guard.atomic.mutex.get());
+        }
+        self.guard
+    }
+}
+
+impl fmt::Debug for OwnerDied<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnerDied").field(&self.value()).finish()
+    }
+}
+
+impl fmt::Display for OwnerDied<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the process holding the process-shared mutex died")
+    }
+}
+
+/// Makes the mutex robust, so the death of its owner is reported by the next lock.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+unsafe fn set_robust(attr: *mut libc::pthread_mutexattr_t) -> io::Result<()> {
+    check(libc::pthread_mutexattr_setrobust(
+        attr,
+        libc::PTHREAD_MUTEX_ROBUST,
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+unsafe fn set_robust(_: *mut libc::pthread_mutexattr_t) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+impl fmt::Debug for AtomicU64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+    }
+}
@@ -0,0 +1,129 @@
+//! The rate of events over a sliding window, such as requests per second, from a ring of atomic buckets.
+//!
+//! Each bucket counts the events of a slice of the window, and is reused for a later slice once the window
+//! moved past it. The count and the slice it belongs to are packed in the same 64-bit atomic, the shim where
+//! the target lacks it, so the thread moving a bucket to a new slice resets its count in the same compare-exchange,
+//! and no event counted meanwhile lands in the wrong slice.
+
+use crate::{Atomic64Ext, AtomicU64};
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+/// Events per second over the last window, counted in a ring of buckets.
+///
+/// The window is split in as many slices as there are buckets, so the rate moves in steps of a slice, and the
+/// events of the current slice count towards it as soon as they are recorded. Each bucket counts up to
+/// `u32::MAX` events per slice, and saturates past them. The operations are `Relaxed`, like a
+/// [`Counter`](crate::Counter)'s.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::RateCounter;
+/// use std::time::Duration;
+///
+/// let requests = RateCounter::new(Duration::from_secs(10), 10);
+/// for _ in 0..50 {
+///     requests.inc();
+/// }
+///
+/// // the 50 requests are in the last 10 seconds
+/// assert_eq!(requests.count(), 50);
+/// assert_eq!(requests.rate(), 5.0);
+/// ```
+pub struct RateCounter {
+    /// The slice each bucket counts, in the high half, and its count, in the low half.
+    buckets: Box<[AtomicU64]>,
+    /// The duration of a slice, in nanoseconds.
+    slice: u64,
+    window: Duration,
+    start: Instant,
+}
+
+impl RateCounter {
+    /// Creates a counter over the last `window`, split in `buckets` slices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is 0, or if a slice of the window would be shorter than a nanosecond.
+    pub fn new(window: Duration, buckets: usize) -> Self {
+        assert!(buckets > 0, "a RateCounter needs at least one bucket");
+        let slice = u64::try_from(window.as_nanos() / buckets as u128).unwrap_or(u64::MAX);
+        assert!(
+            slice > 0,
+            "the slices of a RateCounter must last at least a nanosecond"
+        );
+        Self {
+            buckets: (0..buckets).map(|_| AtomicU64::new(0)).collect(),
+            slice,
+            window,
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the window the rate is computed over.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns the current slice, since the counter was created.
+    fn now(&self) -> u64 {
+        let elapsed = u64::try_from(self.start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        elapsed / self.slice
+    }
+
+    /// Records an event.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Records `n` events.
+    pub fn add(&self, n: u32) {
+        let now = self.now();
+        let slice = now as u32;
+        let bucket = &self.buckets[(now % self.buckets.len() as u64) as usize];
+        let _ = bucket.fetch_update_with_backoff(Ordering::Relaxed, Ordering::Relaxed, |packed| {
+            let counted = (packed >> 32) as u32;
+            if slice.wrapping_sub(counted) as i32 > 0 {
+                // The bucket counted an older slice, which is out of the window now.
+                Some((u64::from(slice) << 32) | u64::from(n))
+            } else {
+                // A thread which read the time earlier may find the bucket moved to a later slice already,
+                // and counts the event there rather than moving the bucket back.
+                Some((u64::from(counted) << 32) | u64::from((packed as u32).saturating_add(n)))
+            }
+        });
+    }
+
+    /// Returns the number of events recorded over the window.
+    ///
+    /// Each bucket is loaded separately, so the count isn't a snapshot of the events recorded meanwhile.
+    pub fn count(&self) -> u64 {
+        let now = self.now() as u32;
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .filter(|packed| {
+                (now.wrapping_sub((packed >> 32) as u32) as usize) < self.buckets.len()
+            })
+            .map(|packed| u64::from(packed as u32))
+            .sum()
+    }
+
+    /// Returns the events per second over the window.
+    pub fn rate(&self) -> f64 {
+        self.count() as f64 / self.window.as_secs_f64()
+    }
+}
+
+impl fmt::Debug for RateCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateCounter")
+            .field("window", &self.window)
+            .field("buckets", &self.buckets.len())
+            .field("rate", &self.rate())
+            .finish()
+    }
+}
@@ -0,0 +1,277 @@
+#[cfg(any(atomic_shim_fallback_u64, atomic_shim_fallback_i64))]
+use crate::striped::Striped;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! raw_atomic {
+    ($raw:ident, $int:ident) => {
+        /// Atomic operations on an integer the atomic doesn't own, such as a field of a C-allocated or `mmap`'d struct.
+        ///
+        /// On the native implementation, the operations are the `std` atomic ones. On the shim, they are serialized
+        /// by a global table of locks keyed by address, as there is no room for a lock next to the value.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use atomic_shim::", stringify!($raw), ";")]
+        /// use std::sync::atomic::Ordering;
+        ///
+        /// #[repr(C, align(8))]
+        /// struct Telemetry {
+        #[doc = concat!("    packets: ", stringify!($int), ",")]
+        /// }
+        ///
+        /// let mut telemetry = Telemetry { packets: 1 };
+        #[doc = concat!("let packets = unsafe { ", stringify!($raw), "::from_raw(&mut telemetry.packets) };")]
+        /// packets.fetch_add(2, Ordering::SeqCst);
+        /// assert_eq!(packets.load(Ordering::SeqCst), 3);
+        /// ```
+        pub struct $raw {
+            ptr: *mut $int,
+        }
+
+        unsafe impl Send for $raw {}
+        unsafe impl Sync for $raw {}
+
+        impl $raw {
+            /// Wraps the integer at `ptr`, without taking ownership of it.
+            ///
+            /// # Safety
+            ///
+            /// `ptr` must be aligned to 8 bytes, and valid for reads and writes for as long as the atomic is used.
+            /// While it is used, the integer must only be accessed through atomics wrapping the same location.
+            /// With native atomics, C code using the same atomic operations may access it too. On the shim, the
+            /// atomics take a lock of a table local to the process, which C code and other processes can't take,
+            /// so only the atomics of the same process may access it. Plain reads and writes, including through
+            /// references, are data races.
+            pub unsafe fn from_raw(ptr: *mut $int) -> Self {
+                debug_assert_eq!(ptr as usize % 8, 0, "unaligned pointer");
+                Self { ptr }
+            }
+
+            /// Consumes the atomic, returning the pointer it wraps.
+            pub fn into_raw(self) -> *mut $int {
+                self.ptr
+            }
+
+            /// Returns the pointer the atomic wraps.
+            pub fn as_ptr(&self) -> *mut $int {
+                self.ptr
+            }
+
+            /// Loads a value from the integer.
+            pub fn load(&self, order: Ordering) -> $int {
+                self.atomic().load(order)
+            }
+
+            /// Stores a value into the integer.
+            pub fn store(&self, value: $int, order: Ordering) {
+                self.atomic().store(value, order)
+            }
+
+            /// Stores a value into the integer, returning the previous value.
+            pub fn swap(&self, value: $int, order: Ordering) -> $int {
+                self.atomic().swap(value, order)
+            }
+
+            /// Stores a value into the integer if the current value is the same as the `current` value.
+            ///
+            /// The return value is a result indicating whether the new value was written and containing the previous value.
+            /// On success this value is guaranteed to be equal to `current`.
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.atomic()
+                    .compare_exchange(current, new, success, failure)
+            }
+
+            /// Stores a value into the integer if the current value is the same as the `current` value,
+            /// and is allowed to spuriously fail.
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.atomic()
+                    .compare_exchange_weak(current, new, success, failure)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                self.atomic().fetch_add(val, order)
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                self.atomic().fetch_sub(val, order)
+            }
+
+            /// Bitwise "and" with the current value, returning the previous value.
+            pub fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                self.atomic().fetch_and(val, order)
+            }
+
+            /// Bitwise "nand" with the current value, returning the previous value.
+            pub fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                self.atomic().fetch_nand(val, order)
+            }
+
+            /// Bitwise "or" with the current value, returning the previous value.
+            pub fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                self.atomic().fetch_or(val, order)
+            }
+
+            /// Bitwise "xor" with the current value, returning the previous value.
+            pub fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                self.atomic().fetch_xor(val, order)
+            }
+        }
+
+        impl fmt::Debug for $raw {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+    };
+}
+
+raw_atomic!(RawAtomicU64, u64);
+raw_atomic!(RawAtomicI64, i64);
+
+#[cfg(not(atomic_shim_fallback_u64))]
+impl RawAtomicU64 {
+    fn atomic(&self) -> &std::sync::atomic::AtomicU64 {
+        unsafe { &*(self.ptr as *const std::sync::atomic::AtomicU64) }
+    }
+}
+
+#[cfg(atomic_shim_fallback_u64)]
+impl RawAtomicU64 {
+    fn atomic(&self) -> Striped<u64> {
+        unsafe { Striped::new(self.ptr) }
+    }
+}
+
+#[cfg(not(atomic_shim_fallback_i64))]
+impl RawAtomicI64 {
+    fn atomic(&self) -> &std::sync::atomic::AtomicI64 {
+        unsafe { &*(self.ptr as *const std::sync::atomic::AtomicI64) }
+    }
+}
+
+#[cfg(atomic_shim_fallback_i64)]
+impl RawAtomicI64 {
+    fn atomic(&self) -> Striped<i64> {
+        unsafe { Striped::new(self.ptr) }
+    }
+}
+
+/// An integer which can be accessed as an [`AtomicField`], implemented by `u64` and `i64`.
+///
+/// The trait is sealed, and can't be implemented outside of this crate.
+pub trait FieldInt: sealed::Sealed + Sized {
+    /// The atomic wrapping a pointer to the integer.
+    type Atomic;
+
+    /// Wraps the integer at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`RawAtomicU64::from_raw`].
+    unsafe fn from_raw(ptr: *mut Self) -> Self::Atomic;
+}
+
+macro_rules! field_int {
+    ($($int:ident => $raw:ident),*) => {
+        $(
+            impl sealed::Sealed for $int {}
+
+            impl FieldInt for $int {
+                type Atomic = $raw;
+
+                unsafe fn from_raw(ptr: *mut Self) -> $raw {
+                    $raw::from_raw(ptr)
+                }
+            }
+        )*
+    };
+}
+
+field_int!(u64 => RawAtomicU64, i64 => RawAtomicI64);
+
+/// Atomic access to an integer field of a `#[repr(C)]` struct, located by its byte offset,
+/// such as a header parsed from a buffer.
+///
+/// It dereferences to [`RawAtomicU64`] or [`RawAtomicI64`], so on the shim, the operations are serialized
+/// by the global table of locks keyed by address, and every field located at the same address shares its lock.
+/// That table is local to the process, so on the shim, C code, firmware and other processes writing the field bypass it.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicField;
+/// use std::sync::atomic::Ordering;
+///
+/// #[repr(C, align(8))]
+/// struct Telemetry {
+///     status: u32,
+///     flags: u32,
+///     packets: u64,
+/// }
+///
+/// let mut telemetry = Telemetry { status: 0, flags: 0, packets: 1 };
+/// let packets = unsafe { AtomicField::<u64>::at(&mut telemetry, 8) };
+/// packets.fetch_add(2, Ordering::SeqCst);
+/// assert_eq!(packets.load(Ordering::SeqCst), 3);
+/// ```
+pub struct AtomicField<T: FieldInt> {
+    atomic: T::Atomic,
+}
+
+impl<T: FieldInt> AtomicField<T> {
+    /// Locates the integer at `offset` bytes from `base`.
+    ///
+    /// # Safety
+    ///
+    /// `base` plus `offset` must be within the same allocation, and the integer there must follow the
+    /// requirements of [`RawAtomicU64::from_raw`]: aligned to 8 bytes, valid for as long as the field is used,
+    /// and only accessed atomically during that time.
+    pub unsafe fn at<B>(base: *mut B, offset: usize) -> Self {
+        let ptr = (base as *mut u8).add(offset) as *mut T;
+        Self {
+            atomic: T::from_raw(ptr),
+        }
+    }
+}
+
+impl<T: FieldInt> Deref for AtomicField<T> {
+    type Target = T::Atomic;
+
+    fn deref(&self) -> &T::Atomic {
+        &self.atomic
+    }
+}
+
+impl<T: FieldInt> fmt::Debug for AtomicField<T>
+where
+    T::Atomic: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.atomic, f)
+    }
+}
@@ -0,0 +1,263 @@
+//! A registry of the live shimmed atomics, behind the `registry` feature.
+//!
+//! Attached to a wedged process, on hardware without a debugger, [`debug_dump`] lists every shimmed atomic
+//! with its label, value and contention, such as from a signal handler thread or a debug endpoint.
+//! The atomics are registered when created and unregistered when dropped. Their storage moves to the heap,
+//! so the registry keeps a stable address while they move.
+//!
+//! The native atomics aren't registered.
+#![cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+
+#[cfg(atomic_shim_fallback)]
+use crate::shim::Slot;
+use crate::stats::Counted;
+#[cfg(atomic_shim_fallback)]
+use crate::stats::Stats;
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(atomic_shim_fallback)]
+use std::mem::{self, ManuallyDrop};
+#[cfg(atomic_shim_fallback)]
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// A live shimmed atomic, listed by [`debug_dump`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    label: Option<String>,
+    type_name: &'static str,
+    value: String,
+    contentions: u64,
+}
+
+impl Entry {
+    /// The label given with `set_label`, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The type of the value, such as `u64`.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The value, formatted with `Debug`.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// How many operations found the lock held by another thread, like [`Stats::contentions`](crate::stats::Stats::contentions).
+    pub fn contentions(&self) -> u64 {
+        self.contentions
+    }
+}
+
+/// Formats the entry on a line, such as `requests: u64 = 42 (3 contentions)`.
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} = {} ({} contentions)",
+            self.label.as_deref().unwrap_or("<unlabeled>"),
+            self.type_name,
+            self.value,
+            self.contentions
+        )
+    }
+}
+
+/// Returns every live shimmed atomic, in the order they were created.
+///
+/// The values are read like `Debug` does, so an atomic being written is listed once the write is done.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64;
+/// use std::sync::atomic::Ordering;
+///
+/// let requests = AtomicU64::new(0);
+/// requests.set_label("requests");
+/// requests.fetch_add(42, Ordering::SeqCst);
+///
+/// let dump = atomic_shim::debug_dump();
+/// if !AtomicU64::IS_LOCK_FREE {
+///     let entry = dump.iter().find(|entry| entry.label() == Some("requests")).unwrap();
+///     assert_eq!(entry.value(), "42");
+///     println!("{}", entry);
+/// }
+/// ```
+pub fn debug_dump() -> Vec<Entry> {
+    let registry = lock();
+    registry
+        .values()
+        .map(|node| unsafe { (*node.0).entry() })
+        .collect()
+}
+
+/// A registered atomic, which the registry reads through a pointer, as the atomics have different types.
+trait Dump: Sync {
+    fn entry(&self) -> Entry;
+}
+
+/// The pointer to a registered node, which stays valid until it is removed, and can be read from any thread.
+struct Handle(*const dyn Dump);
+
+unsafe impl Send for Handle {}
+
+static REGISTRY: Mutex<BTreeMap<usize, Handle>> = Mutex::new(BTreeMap::new());
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn lock() -> std::sync::MutexGuard<'static, BTreeMap<usize, Handle>> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The storage of a registered atomic, on the heap.
+struct Node<S> {
+    id: usize,
+    slot: Counted<S>,
+    label: Mutex<Option<String>>,
+    type_name: &'static str,
+    describe: fn(&Counted<S>) -> String,
+    /// Cleared while the value is borrowed mutably, as the registry can't read it then.
+    registered: AtomicBool,
+}
+
+impl<S: Sync> Dump for Node<S> {
+    fn entry(&self) -> Entry {
+        Entry {
+            label: self
+                .label
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            type_name: self.type_name,
+            value: (self.describe)(&self.slot),
+            contentions: self.slot.stats().contentions(),
+        }
+    }
+}
+
+/// A storage registered in the registry while it is alive.
+pub(crate) struct Registered<S: Sync + 'static> {
+    node: Box<Node<S>>,
+}
+
+impl<S: Sync + 'static> Registered<S> {
+    pub(crate) fn set_label(&self, label: String) {
+        *self
+            .node
+            .label
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(label);
+    }
+
+    /// Registers the node again after a mutable borrow.
+    fn register(&self) {
+        if !self.node.registered.load(Ordering::Acquire) {
+            let node: &(dyn Dump + 'static) = &*self.node;
+            lock().insert(self.node.id, Handle(node));
+            self.node.registered.store(true, Ordering::Release);
+        }
+    }
+
+    /// Removes the node, waiting for the dumps reading it.
+    fn unregister(&self) {
+        lock().remove(&self.node.id);
+        self.node.registered.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<S: Sync + 'static> Registered<S> {
+    pub(crate) fn stats(&self) -> Stats {
+        self.node.slot.stats()
+    }
+}
+
+impl<S: Sync + 'static> Drop for Registered<S> {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T: fmt::Debug, S: Slot<T> + Sync + 'static> Slot<T> for Registered<S> {
+    const FOOTPRINT: usize = mem::size_of::<usize>();
+    const IS_LOCK_FREE: bool = S::IS_LOCK_FREE;
+
+    #[cfg_attr(feature = "ordering-audit", track_caller)]
+    fn new(value: T) -> Self {
+        let registered = Registered {
+            node: Box::new(Node {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                slot: Counted::new(value),
+                label: Mutex::new(None),
+                type_name: std::any::type_name::<T>(),
+                describe: |slot| format!("{:?}", Slot::<T>::snapshot(slot)),
+                registered: AtomicBool::new(false),
+            }),
+        };
+        registered.register();
+        registered
+    }
+
+    fn is_lock_free(&self) -> bool {
+        self.node.slot.is_lock_free()
+    }
+
+    /// The registry stops reading the value until the next operation, as it is borrowed mutably.
+    fn get_mut(&mut self) -> &mut T {
+        self.unregister();
+        self.node.slot.get_mut()
+    }
+
+    fn into_inner(self) -> T {
+        self.unregister();
+        let this = ManuallyDrop::new(self);
+        let node = unsafe { ptr::read(&this.node) };
+        node.slot.into_inner()
+    }
+
+    fn load(&self, order: Ordering) -> T {
+        self.register();
+        self.node.slot.load(order)
+    }
+
+    fn update<F: Fn(T) -> T>(&self, order: Ordering, f: F) -> T {
+        self.register();
+        self.node.slot.update(order, f)
+    }
+
+    fn apply<F: Fn(T) -> T>(&self, op: &'static str, order: Ordering, f: F) -> T {
+        self.register();
+        self.node.slot.apply(op, order, f)
+    }
+
+    fn compare_and_swap(&self, order: Ordering, current: T, new: T) -> T
+    where
+        T: Copy + PartialEq,
+    {
+        self.register();
+        self.node.slot.compare_and_swap(order, current, new)
+    }
+
+    fn snapshot(&self) -> T {
+        self.node.slot.snapshot()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.node.slot.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {
+        self.node.slot.forget_poisoning()
+    }
+
+    #[cfg(feature = "history")]
+    fn history(&self) -> Vec<crate::history::Operation<T>> {
+        self.node.slot.history()
+    }
+}
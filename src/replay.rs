@@ -0,0 +1,258 @@
+//! A test-only scheduler which records the order of the shim's operations, and replays it deterministically.
+//!
+//! A race seen on CI, or reported from PowerPC hardware, is usually hard to reproduce, as it depends on one
+//! interleaving of the threads. With `features = ["replay", "mutex"]`, a test runs its scenario with [`record`],
+//! which returns the [`Schedule`] the operations ran in, and prints it on failure. [`replay`] then runs the
+//! same scenario, forcing the operations to run in the recorded order, on any machine.
+//!
+//! Only the threads taking part in the scenario are scheduled. Each one calls [`register`] with an id which
+//! identifies it across runs, such as its index, as thread ids are not stable. The operations of the shims
+//! are scheduled, while other threads, and the native atomics, run freely.
+//! With the feature, the 64-bit shims always use the lock, like on other targets.
+//!
+//! The scheduler is global, so concurrent sessions run one after the other. It makes every operation of a
+//! registered thread wait for its turn, so it is only meant for tests.
+//!
+//! # Examples
+//!
+//! ```
+//! use atomic_shim::replay::{self, Schedule};
+//! use atomic_shim::AtomicU64;
+//! use std::sync::atomic::Ordering;
+//! use std::sync::Arc;
+//! use std::thread;
+//!
+//! fn scenario() -> u64 {
+//!     let value = Arc::new(AtomicU64::new(0));
+//!     let threads: Vec<_> = (0..2)
+//!         .map(|id| {
+//!             let value = value.clone();
+//!             thread::spawn(move || {
+//!                 let _registration = replay::register(id);
+//!                 value.store(id as u64, Ordering::SeqCst);
+//!             })
+//!         })
+//!         .collect();
+//!     for t in threads {
+//!         t.join().unwrap();
+//!     }
+//!     value.load(Ordering::SeqCst)
+//! }
+//!
+//! let (first, schedule) = replay::record(scenario);
+//! let text = schedule.to_string();
+//!
+//! let schedule: Schedule = text.parse().unwrap();
+//! assert_eq!(replay::replay(&schedule, scenario), first);
+//! ```
+
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+/// The order the registered threads ran their operations in, as their ids.
+///
+/// It is displayed as the ids separated by commas, such as `0,1,1,0`, and parsed back with `FromStr`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schedule {
+    steps: Vec<usize>,
+}
+
+impl Schedule {
+    /// Returns the ids of the threads, in the order their operations ran.
+    pub fn steps(&self) -> &[usize] {
+        &self.steps
+    }
+}
+
+impl From<Vec<usize>> for Schedule {
+    fn from(steps: Vec<usize>) -> Self {
+        Self { steps }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when parsing a [`Schedule`] which isn't a list of ids separated by commas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseScheduleError {
+    step: String,
+}
+
+impl fmt::Display for ParseScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid thread id in schedule: {:?}", self.step)
+    }
+}
+
+impl Error for ParseScheduleError {}
+
+impl FromStr for Schedule {
+    type Err = ParseScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        s.split(',')
+            .map(|step| {
+                step.trim().parse().map_err(|_| ParseScheduleError {
+                    step: step.to_owned(),
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(|steps| Self { steps })
+    }
+}
+
+enum Mode {
+    Off,
+    Record(Vec<usize>),
+    Replay {
+        steps: Vec<usize>,
+        next: usize,
+        finished: Vec<usize>,
+    },
+}
+
+struct Scheduler {
+    mode: Mutex<Mode>,
+    turn: Condvar,
+    session: Mutex<()>,
+}
+
+static SCHEDULER: Scheduler = Scheduler {
+    mode: Mutex::new(Mode::Off),
+    turn: Condvar::new(),
+    session: Mutex::new(()),
+};
+
+thread_local! {
+    static ID: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// The scheduler's state stays consistent on panics, which are reported by the scenario.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs an operation of the shim, in its turn when the current thread is registered.
+///
+/// Without the shim, nothing is scheduled, and sessions record empty schedules.
+#[cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+pub(crate) fn step<R>(op: impl FnOnce() -> R) -> R {
+    let id = match ID.with(Cell::get) {
+        Some(id) => id,
+        None => return op(),
+    };
+
+    let mut mode = lock(&SCHEDULER.mode);
+    loop {
+        match &mut *mode {
+            Mode::Off => return op(),
+            Mode::Record(steps) => {
+                let result = op();
+                steps.push(id);
+                return result;
+            }
+            Mode::Replay {
+                steps,
+                next,
+                finished,
+            } => {
+                // Steps of threads which already finished can't run anymore, as the scenario diverged.
+                while *next < steps.len() && finished.contains(&steps[*next]) {
+                    *next += 1;
+                }
+                if *next >= steps.len() || steps[*next] == id {
+                    *next += 1;
+                    let result = op();
+                    SCHEDULER.turn.notify_all();
+                    return result;
+                }
+            }
+        }
+        mode = SCHEDULER
+            .turn
+            .wait(mode)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+}
+
+/// Registers the current thread with the scheduler, until the returned value is dropped.
+///
+/// The `id` identifies the thread in the [`Schedule`], and must be unique among the threads of a scenario.
+#[must_use = "the thread is unregistered when the registration is dropped"]
+pub fn register(id: usize) -> Registration {
+    ID.with(|current| current.set(Some(id)));
+    Registration { id }
+}
+
+/// Keeps the current thread registered with the scheduler, returned by [`register`].
+#[derive(Debug)]
+pub struct Registration {
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        ID.with(|current| current.set(None));
+        if let Mode::Replay { finished, .. } = &mut *lock(&SCHEDULER.mode) {
+            finished.push(self.id);
+        }
+        SCHEDULER.turn.notify_all();
+    }
+}
+
+fn session<R>(mode: Mode, scenario: impl FnOnce() -> R) -> (R, Mode) {
+    let _session = lock(&SCHEDULER.session);
+    *lock(&SCHEDULER.mode) = mode;
+
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            *lock(&SCHEDULER.mode) = Mode::Off;
+            SCHEDULER.turn.notify_all();
+        }
+    }
+
+    let reset = Reset;
+    let result = scenario();
+    let mode = std::mem::replace(&mut *lock(&SCHEDULER.mode), Mode::Off);
+    drop(reset);
+    (result, mode)
+}
+
+/// Runs `scenario`, returning its result and the order its registered threads ran their operations in.
+pub fn record<R>(scenario: impl FnOnce() -> R) -> (R, Schedule) {
+    match session(Mode::Record(Vec::new()), scenario) {
+        (result, Mode::Record(steps)) => (result, Schedule { steps }),
+        (result, _) => (result, Schedule::default()),
+    }
+}
+
+/// Runs `scenario`, making its registered threads run their operations in the order of `schedule`.
+///
+/// If the scenario diverges from the schedule, the steps of threads which finished are skipped,
+/// and the operations past the end of the schedule run freely.
+pub fn replay<R>(schedule: &Schedule, scenario: impl FnOnce() -> R) -> R {
+    let mode = Mode::Replay {
+        steps: schedule.steps.clone(),
+        next: 0,
+        finished: Vec::new(),
+    };
+    session(mode, scenario).0
+}
@@ -0,0 +1,260 @@
+//! A small value shared between threads, read without blocking, like the storage of the 64-bit shims.
+//!
+//! The shim's seqlock splits a 64-bit integer in two 32-bit atomics. A value of any size is split the same way, in
+//! atomic words, and atomic bytes for its end: writers store them under a lock, bumping a version before and after,
+//! and readers load them between two reads of the version, retrying when a write overlapped them. A copy overlapping a
+//! write may be torn, so it is kept as `MaybeUninit` until the version shows it wasn't, and is never used as a `T`
+//! otherwise. Padding would be loaded as uninitialized integers, so the value must be [`PlainData`].
+
+use crate::Backoff;
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::{self, MaybeUninit};
+use std::sync::atomic::{fence, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A `Copy` type whose bytes are all initialized, so a [`SeqLock`] can copy it as atomic integers.
+///
+/// It is implemented for the primitive integers and floats, `bool`, `char`, and arrays of them.
+///
+/// # Safety
+///
+/// The type must have no padding, between its fields or after them, and no pointers or references, whose
+/// provenance would be lost by the copy. A struct whose fields implement the trait, and whose sizes leave no gap
+/// for their alignment, can implement it.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{PlainData, SeqLock};
+///
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Rgba {
+///     red: u8,
+///     green: u8,
+///     blue: u8,
+///     alpha: u8,
+/// }
+///
+/// // four bytes, without padding
+/// unsafe impl PlainData for Rgba {}
+///
+/// let color = SeqLock::new(Rgba { red: 0, green: 0, blue: 0, alpha: 255 });
+/// color.write(|color| color.red = 255);
+/// assert_eq!(color.read().red, 255);
+/// ```
+pub unsafe trait PlainData: Copy {}
+
+macro_rules! plain_data {
+    ($($ty:ty),*) => {
+        $(unsafe impl PlainData for $ty {})*
+    };
+}
+
+plain_data!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+
+unsafe impl<T: PlainData, const N: usize> PlainData for [T; N] {}
+
+/// The value, aligned to be copied as words.
+#[repr(C)]
+struct Words<T> {
+    _align: [usize; 0],
+    value: T,
+}
+
+const WORD: usize = mem::size_of::<usize>();
+
+/// A value written under a lock, and read by copying it without taking the lock, retrying while it is written.
+///
+/// Readers never wait for the lock, and never make writers wait, so a reader polling the value doesn't slow down
+/// the writer, but a reader retries for as long as writes overlap its copy. The value should be small, so that
+/// copying it is quick, and can't be shared as a reference.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{PlainData, SeqLock};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Position {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// // two f64s, without padding
+/// unsafe impl PlainData for Position {}
+///
+/// let position = Arc::new(SeqLock::new(Position { x: 0.0, y: 0.0 }));
+/// let tracker = {
+///     let position = position.clone();
+///     thread::spawn(move || {
+///         for i in 1..=100 {
+///             position.write(|position| {
+///                 position.x = i as f64;
+///                 position.y = -(i as f64);
+///             });
+///         }
+///     })
+/// };
+///
+/// for _ in 0..100 {
+///     // the coordinates are always read from the same write
+///     let Position { x, y } = position.read();
+///     assert_eq!(x, -y);
+/// }
+/// tracker.join().unwrap();
+/// assert_eq!(position.read(), Position { x: 100.0, y: -100.0 });
+/// ```
+pub struct SeqLock<T: PlainData> {
+    value: UnsafeCell<Words<T>>,
+    /// Odd while a write is in progress.
+    version: AtomicUsize,
+    lock: Mutex<()>,
+}
+
+unsafe impl<T: PlainData + Send> Send for SeqLock<T> {}
+unsafe impl<T: PlainData + Send> Sync for SeqLock<T> {}
+
+impl<T: PlainData> SeqLock<T> {
+    /// Creates a lock holding `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(Words { _align: [], value }),
+            version: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// No closure given to `write` runs with the version odd, so a poisoned lock can't have left the value torn.
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Copies the value with `Relaxed` loads, which may be torn if a write overlaps them.
+    fn load_words(&self) -> MaybeUninit<Words<T>> {
+        let mut copy = MaybeUninit::<Words<T>>::uninit();
+        let src = self.value.get() as *const u8;
+        let dst = copy.as_mut_ptr() as *mut u8;
+        let words = mem::size_of::<T>() / WORD;
+        // Both are aligned for words, and the value is only written by `store_words`, as the same atomics.
+        unsafe {
+            for i in 0..words {
+                let word = (*(src as *const AtomicUsize).add(i)).load(Ordering::Relaxed);
+                (dst as *mut usize).add(i).write(word);
+            }
+            for i in words * WORD..mem::size_of::<T>() {
+                let byte = (*(src.add(i) as *const AtomicU8)).load(Ordering::Relaxed);
+                dst.add(i).write(byte);
+            }
+        }
+        copy
+    }
+
+    /// Replaces the value with `Relaxed` stores, split like the loads of `load_words`.
+    fn store_words(&self, value: T) {
+        let value = Words { _align: [], value };
+        let src = &value as *const Words<T> as *const u8;
+        let dst = self.value.get() as *const u8;
+        let words = mem::size_of::<T>() / WORD;
+        unsafe {
+            for i in 0..words {
+                let word = (src as *const usize).add(i).read();
+                (*(dst as *const AtomicUsize).add(i)).store(word, Ordering::Relaxed);
+            }
+            for i in words * WORD..mem::size_of::<T>() {
+                (*(dst.add(i) as *const AtomicU8)).store(src.add(i).read(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns a copy of the value.
+    pub fn read(&self) -> T {
+        self.read_with(|| ()).0
+    }
+
+    /// Returns a copy of the value, and the result of `f`, called while copying it, so the atomics written by a
+    /// [`store_with`](Self::store_with) are read from the same write as the value.
+    pub(crate) fn read_with<R>(&self, f: impl Fn() -> R) -> (T, R) {
+        let backoff = Backoff::new();
+        loop {
+            let version = self.version.load(Ordering::SeqCst);
+            if version & 1 == 0 {
+                let copy = self.load_words();
+                let result = f();
+                fence(Ordering::Acquire);
+                if self.version.load(Ordering::Relaxed) == version {
+                    return (unsafe { copy.assume_init() }.value, result);
+                }
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Updates the value with `f`, returning its result.
+    ///
+    /// `f` runs on a copy of the value, which replaces the value once `f` returns, so readers never see the
+    /// updates `f` makes one by one, and a panic in `f` leaves the value as it was.
+    pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let _guard = self.lock();
+        // Only the writers holding the lock change the value, so the copy can't be torn.
+        let mut value = unsafe { self.load_words().assume_init() }.value;
+        let result = f(&mut value);
+        self.replace(value, || ());
+        result
+    }
+
+    /// Replaces the value with `value`.
+    pub fn store(&self, value: T) {
+        self.store_with(value, || ())
+    }
+
+    /// Replaces the value with `value`, and calls `f` before the readers can read it, returning its result.
+    pub(crate) fn store_with<R>(&self, value: T, f: impl FnOnce() -> R) -> R {
+        let _guard = self.lock();
+        self.replace(value, f)
+    }
+
+    /// Writes `value` with the version odd, calling `f` before making it even again. The lock must be held.
+    fn replace<R>(&self, value: T, f: impl FnOnce() -> R) -> R {
+        let version = self.version.load(Ordering::Relaxed);
+        self.version
+            .store(version.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.store_words(value);
+        let result = f();
+        self.version
+            .store(version.wrapping_add(2), Ordering::SeqCst);
+        result
+    }
+
+    /// Returns a mutable reference to the value, as the borrow prevents any other access.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value.get_mut().value
+    }
+
+    /// Consumes the lock, returning the value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner().value
+    }
+}
+
+impl<T: PlainData + Default> Default for SeqLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: PlainData> From<T> for SeqLock<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: PlainData + fmt::Debug> fmt::Debug for SeqLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.read(), f)
+    }
+}
@@ -0,0 +1,146 @@
+//! A ticket lock, serving the threads in the order they asked for it, in a single 64-bit atomic.
+//!
+//! A thread takes the next ticket from the high half of the atomic, and waits until the low half, the ticket
+//! being served, reaches it, so the threads are served in order, and none of them starves. Both halves moving in
+//! the same atomic, the lock works where the target lacks a native 64-bit `fetch_add`, with the shim. The threads
+//! waiting for their turn block with [`wait`](crate::AtomicU64::wait) instead of spinning.
+
+use crate::{Atomic64Ext, AtomicU64};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// A lock granted in the order it was asked for, first in, first out.
+///
+/// The tickets are numbered in 32 bits, wrapping around, so up to `2^32 - 1` threads can wait at once.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::Sequencer;
+/// use std::sync::{Arc, Mutex};
+/// use std::thread;
+///
+/// let sequencer = Arc::new(Sequencer::new());
+/// let served = Arc::new(Mutex::new(Vec::new()));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let (sequencer, served) = (sequencer.clone(), served.clone());
+///         thread::spawn(move || {
+///             let turn = sequencer.lock();
+///             served.lock().unwrap().push(turn.ticket());
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+///
+/// // the workers were served in the order of their tickets
+/// assert_eq!(*served.lock().unwrap(), [0, 1, 2, 3]);
+/// ```
+pub struct Sequencer {
+    /// The next ticket in the high half, and the ticket served in the low half.
+    state: AtomicU64,
+}
+
+impl Sequencer {
+    /// Creates an unlocked sequencer, whose first ticket is 0.
+    pub fn new() -> Self {
+        Self {
+            state: AtomicU64::new(0),
+        }
+    }
+
+    /// Takes the next ticket, and blocks until it is served.
+    ///
+    /// The lock is held until the returned guard is dropped, which serves the next ticket.
+    pub fn lock(&self) -> SequencerGuard<'_> {
+        let ticket = (self.state.fetch_add(1 << 32, Ordering::Relaxed) >> 32) as u32;
+        loop {
+            let state = self.state.load(Ordering::Acquire);
+            if state as u32 == ticket {
+                return SequencerGuard {
+                    sequencer: self,
+                    ticket,
+                };
+            }
+            self.state.wait(state, Ordering::Acquire);
+        }
+    }
+
+    /// Takes the next ticket if it is served right away, without blocking.
+    pub fn try_lock(&self) -> Option<SequencerGuard<'_>> {
+        let state = self.state.load(Ordering::Relaxed);
+        let ticket = (state >> 32) as u32;
+        if state as u32 != ticket {
+            return None;
+        }
+        self.state
+            .compare_exchange(
+                state,
+                state.wrapping_add(1 << 32),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .ok()
+            .map(|_| SequencerGuard {
+                sequencer: self,
+                ticket,
+            })
+    }
+
+    /// Serves the next ticket, and wakes the threads waiting for their turn.
+    fn unlock(&self) {
+        // Only the low half moves, wrapping around without carrying into the tickets.
+        let _ =
+            self.state
+                .fetch_update_with_backoff(Ordering::Release, Ordering::Relaxed, |state| {
+                    Some((state & !u64::from(u32::MAX)) | u64::from((state as u32).wrapping_add(1)))
+                });
+        self.state.notify_all();
+    }
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Sequencer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.load(Ordering::Relaxed);
+        f.debug_struct("Sequencer")
+            .field("next", &((state >> 32) as u32))
+            .field("serving", &(state as u32))
+            .finish()
+    }
+}
+
+/// The turn of a thread holding a [`Sequencer`], which serves the next ticket when dropped.
+#[must_use = "the sequencer serves the next ticket as soon as the guard is dropped"]
+pub struct SequencerGuard<'a> {
+    sequencer: &'a Sequencer,
+    ticket: u32,
+}
+
+impl SequencerGuard<'_> {
+    /// Returns the ticket being served.
+    pub fn ticket(&self) -> u32 {
+        self.ticket
+    }
+}
+
+impl Drop for SequencerGuard<'_> {
+    fn drop(&mut self) {
+        self.sequencer.unlock();
+    }
+}
+
+impl fmt::Debug for SequencerGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SequencerGuard")
+            .field("ticket", &self.ticket)
+            .finish()
+    }
+}
@@ -1,92 +1,1307 @@
+#[cfg(loom)]
+use self::loom_lock::ShardedLock;
+#[cfg(feature = "ordering-audit")]
+use crate::audit::Audited;
+#[cfg(feature = "diagnostics")]
+pub(crate) use crate::diagnostics;
+#[cfg(any(feature = "tracing", feature = "log", feature = "metrics"))]
+use crate::events;
+#[cfg(feature = "history")]
+use crate::history::Operation;
+#[cfg(feature = "registry")]
+use crate::registry::Registered;
+#[cfg(feature = "replay")]
+pub(crate) use crate::replay::step;
+#[cfg(feature = "stats")]
+pub(crate) use crate::stats;
+#[cfg(all(feature = "stats", not(feature = "registry")))]
+use crate::stats::Counted;
+#[cfg(feature = "stats")]
+use crate::stats::Stats;
+#[cfg(feature = "tsan")]
+use crate::tsan;
+use crate::Backoff;
+#[cfg(not(loom))]
 use crossbeam_utils::sync::ShardedLock;
+use std::fmt;
+use std::hint;
+use std::mem;
+use std::ptr;
 use std::sync::atomic::Ordering;
+use std::sync::{LockResult, PoisonError, TryLockError, TryLockResult};
 
-/// An integer type which can be safely shared between threads.
-#[derive(Debug, Default)]
-pub struct AtomicU64 {
-    value: ShardedLock<u64>,
+/// Under `--cfg loom`, the lock is loom's `RwLock`, so loom explores the interleavings of the shimmed types.
+#[cfg(loom)]
+mod loom_lock {
+    use loom::sync::{LockResult, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockResult};
+
+    #[derive(Default)]
+    pub(crate) struct ShardedLock<T>(RwLock<T>);
+
+    impl<T> ShardedLock<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(RwLock::new(value))
+        }
+
+        pub(crate) fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+            self.0.read()
+        }
+
+        pub(crate) fn write(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+            self.0.write()
+        }
+
+        pub(crate) fn try_read(&self) -> TryLockResult<RwLockReadGuard<'_, T>> {
+            self.0.try_read()
+        }
+
+        pub(crate) fn try_write(&self) -> TryLockResult<RwLockWriteGuard<'_, T>> {
+            self.0.try_write()
+        }
+
+        pub(crate) fn get_mut(&mut self) -> LockResult<&mut T> {
+            self.0.get_mut()
+        }
+
+        pub(crate) fn into_inner(self) -> LockResult<T> {
+            self.0.into_inner()
+        }
+
+        /// Loom stops the model on the first panic, so the lock is never seen poisoned.
+        pub(crate) fn is_poisoned(&self) -> bool {
+            false
+        }
+    }
 }
 
-impl AtomicU64 {
-    /// Creates a new atomic integer.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use atomic_shim::AtomicU64;
-    /// let atomic_forty_two = AtomicU64::new(42);
-    /// ```
-    pub fn new(v: u64) -> Self {
-        Self {
-            value: ShardedLock::new(v),
+/// How many times a busy lock is tried, spinning exponentially longer in between, before blocking on it,
+/// as the critical sections are a handful of instructions. It is set by `ATOMIC_SHIM_SPIN_LIMIT` at build time.
+#[cfg(not(loom))]
+const SPIN_LIMIT: u32 = parse_limit(env!("ATOMIC_SHIM_SPIN_LIMIT"));
+
+/// Spinning only multiplies the interleavings loom explores.
+#[cfg(loom)]
+const SPIN_LIMIT: u32 = 0;
+
+/// Parses the digits of the limit, which the build script validated.
+#[cfg(not(loom))]
+const fn parse_limit(digits: &str) -> u32 {
+    let digits = digits.as_bytes();
+    let mut limit = 0;
+    let mut i = 0;
+    while i < digits.len() {
+        limit = limit * 10 + (digits[i] - b'0') as u32;
+        i += 1;
+    }
+    limit
+}
+
+/// Takes a lock with `try_lock`, spinning exponentially longer between the attempts, and blocks with `lock`
+/// once `SPIN_LIMIT` attempts failed.
+pub(crate) fn acquire<G>(
+    try_lock: impl Fn() -> TryLockResult<G>,
+    lock: impl FnOnce() -> LockResult<G>,
+) -> LockResult<G> {
+    for step in 0..SPIN_LIMIT {
+        match try_lock() {
+            Ok(guard) => return Ok(guard),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                events::poisoned();
+                return Err(poisoned);
+            }
+            Err(TryLockError::WouldBlock) => {
+                if step == 0 {
+                    stats::contended();
+                    events::contended();
+                }
+                for _ in 0..1u32 << step {
+                    hint::spin_loop();
+                }
+            }
         }
     }
+    let result = if SPIN_LIMIT > 0 {
+        events::blocking(SPIN_LIMIT, lock)
+    } else {
+        lock()
+    };
+    if result.is_err() {
+        events::poisoned();
+    }
+    result
+}
 
-    /// Returns a mutable reference to the underlying integer.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+/// Returns the guard of the lock of `atomic`, or panics, reporting the panic which poisoned it.
+pub(crate) fn unpoisoned<G, T>(result: LockResult<G>, atomic: &T) -> G {
+    result.unwrap_or_else(|_| diagnostics::poisoned(atomic))
+}
+
+/// Runs an operation of the shim, which the replay scheduler orders when it is enabled.
+#[cfg(not(feature = "replay"))]
+pub(crate) fn step<R>(op: impl FnOnce() -> R) -> R {
+    op()
+}
+
+/// ThreadSanitizer annotations of the lock, which are only emitted with the `tsan` feature.
+#[cfg(not(feature = "tsan"))]
+mod tsan {
+    pub(crate) fn acquire<T>(_: &T) {}
+
+    pub(crate) fn release<T>(_: &T) {}
+}
+
+/// Storage of the shims, which is the weak memory simulation when it is enabled.
+///
+/// It is unused when only the 64-bit shims are selected, and they don't use the lock.
+#[cfg(any(loom, not(feature = "weak-memory")))]
+#[allow(dead_code)]
+type Lock<T> = ShardedLock<T>;
+
+#[cfg(all(feature = "weak-memory", not(loom)))]
+type Lock<T> = crate::weak_memory::WeakMemory<T>;
+
+/// Timing of the writes for the watchdog, and recording of the panics poisoning the locks, which only happen with
+/// the `diagnostics` feature.
+#[cfg(not(feature = "diagnostics"))]
+pub(crate) mod diagnostics {
+    pub(crate) struct Section;
+
+    impl Section {
+        pub(crate) fn start() -> Self {
+            Section
+        }
+
+        pub(crate) fn end(self) -> Slow {
+            Slow
+        }
+    }
+
+    pub(crate) struct Slow;
+
+    impl Slow {
+        pub(crate) fn report<T>(self, _: &T) {}
+    }
+
+    pub(crate) struct Witness;
+
+    impl Witness {
+        pub(crate) fn new<T>(_: &T) -> Self {
+            Witness
+        }
+    }
+
+    pub(crate) fn poisoned<T>(_: &T) -> ! {
+        panic!("the atomic's lock is poisoned")
+    }
+
+    pub(crate) fn poisoned_into_inner<T>(atomic: &T) -> ! {
+        poisoned(atomic)
+    }
+
+    pub(crate) fn forget<T>(_: &T) {}
+}
+
+/// Events of the shim, which are only emitted with the `tracing`, `log` or `metrics` features.
+#[cfg(not(any(feature = "tracing", feature = "log", feature = "metrics")))]
+mod events {
+    use std::sync::LockResult;
+
+    pub(crate) fn contended() {}
+
+    pub(crate) fn blocking<G>(_: u32, lock: impl FnOnce() -> LockResult<G>) -> LockResult<G> {
+        lock()
+    }
+
+    pub(crate) fn poisoned() {}
+
+    pub(crate) fn compare_exchanged(_: bool) {}
+}
+
+/// Counting of the operations, which only happens with the `stats` feature.
+#[cfg(not(feature = "stats"))]
+pub(crate) mod stats {
+    pub(crate) fn contended() {}
+}
+
+#[cfg(not(feature = "stats"))]
+type Counted<S> = S;
+
+/// The storage of an integer, with the ring of its writes when the `history` feature is enabled.
+#[cfg(feature = "history")]
+macro_rules! recorded {
+    ($int:ty, $slot:ty) => {
+        crate::history::History<$int, $slot>
+    };
+}
+
+#[cfg(not(feature = "history"))]
+macro_rules! recorded {
+    ($int:ty, $slot:ty) => {
+        $slot
+    };
+}
+
+/// Auditing of the orderings, which only happens with the `ordering-audit` feature.
+#[cfg(not(feature = "ordering-audit"))]
+type Audited<S> = S;
+
+/// Registration of the atomics, which only happens with the `registry` feature.
+#[cfg(not(feature = "registry"))]
+type Registered<S> = Counted<S>;
+
+/// Returns the value, even if the lock is poisoned, for formatting.
+fn snapshot<T: Copy>(lock: &ShardedLock<T>) -> T {
+    match lock.read() {
+        Ok(value) => *value,
+        Err(poisoned) => *poisoned.into_inner(),
+    }
+}
+
+/// Formats the value like `std` atomics do, followed by a marker when the lock is poisoned.
+///
+/// It never panics, as a poisoned lock is still useful to see on logs.
+fn debug_value<T: fmt::Debug, S: Slot<T>>(slot: &S, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(&slot.snapshot(), f)?;
+    if slot.is_poisoned() {
+        f.write_str(" (poisoned)")?;
+    }
+    Ok(())
+}
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// Storage of a shimmed integer, and the accesses the atomic operations are built on.
+pub(crate) trait Slot<T> {
+    /// The most bytes the storage may take inline, which every shim checks at compile time,
+    /// as arrays of counters add up on targets with little memory.
+    const FOOTPRINT: usize;
+
+    /// Whether the accesses are lock-free on every CPU the target runs on.
+    const IS_LOCK_FREE: bool;
+
+    fn new(value: T) -> Self;
+
+    /// Whether the accesses are lock-free, which may only be known at runtime.
+    fn is_lock_free(&self) -> bool;
+
+    /// Panics if the lock is poisoned.
+    fn get_mut(&mut self) -> &mut T;
+
+    /// Panics if the lock is poisoned.
+    fn into_inner(self) -> T;
+
+    /// Panics if the lock is poisoned.
     ///
-    /// # Panics
+    /// Only the weak memory simulation looks at the ordering, the other storages are sequentially consistent.
+    fn load(&self, order: Ordering) -> T;
+
+    /// Replaces the value with `f(value)`, returning the previous value.
     ///
-    /// Panics if the Mutex is poisoned
+    /// `f` may be called more than once by lock-free implementations.
+    /// Panics if the lock is poisoned.
+    fn update<F: Fn(T) -> T>(&self, order: Ordering, f: F) -> T;
+
+    /// Like `update`, for the operation named `op`, which the history feature records.
+    fn apply<F: Fn(T) -> T>(&self, _op: &'static str, order: Ordering, f: F) -> T {
+        self.update(order, f)
+    }
+
+    /// Replaces the value with `new` if it is `current`, returning the previous value.
+    fn compare_and_swap(&self, order: Ordering, current: T, new: T) -> T
+    where
+        T: Copy + PartialEq,
+    {
+        self.update(order, |prev| if prev == current { new } else { prev })
+    }
+
+    /// Returns the value, even if the lock is poisoned, for formatting.
+    fn snapshot(&self) -> T;
+
+    fn is_poisoned(&self) -> bool;
+
+    /// Forgets the panic which poisoned the lock, which the `diagnostics` feature keeps, as the atomic is dropped.
+    fn forget_poisoning(&self);
+
+    /// The recorded writes, which only the history keeps.
+    #[cfg(feature = "history")]
+    fn history(&self) -> Vec<Operation<T>> {
+        Vec::new()
+    }
+}
+
+impl<T: Copy> Slot<T> for ShardedLock<T> {
+    /// The pointer and length of the boxed shards, and the value padded to a word.
+    /// The 8 shards, a cache line each, are allocated on the heap.
+    const FOOTPRINT: usize =
+        2 * mem::size_of::<usize>() + max(mem::size_of::<T>(), mem::size_of::<usize>());
+    const IS_LOCK_FREE: bool = false;
+
+    fn new(value: T) -> Self {
+        ShardedLock::new(value)
+    }
+
+    fn is_lock_free(&self) -> bool {
+        false
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        if ShardedLock::is_poisoned(self) {
+            diagnostics::poisoned(self)
+        }
+        ShardedLock::get_mut(self).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn into_inner(self) -> T {
+        if ShardedLock::is_poisoned(&self) {
+            diagnostics::poisoned_into_inner(&self)
+        }
+        ShardedLock::into_inner(self).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn load(&self, _: Ordering) -> T {
+        step(|| {
+            let lock = unpoisoned(acquire(|| self.try_read(), || self.read()), self);
+            tsan::acquire(self);
+            *lock
+        })
+    }
+
+    fn update<F: Fn(T) -> T>(&self, _: Ordering, f: F) -> T {
+        step(|| {
+            let mut lock = unpoisoned(acquire(|| self.try_write(), || self.write()), self);
+            let _witness = diagnostics::Witness::new(self);
+            let section = diagnostics::Section::start();
+            tsan::acquire(self);
+            let prev = *lock;
+            *lock = f(prev);
+            tsan::release(self);
+            let slow = section.end();
+            drop(lock);
+            slow.report(self);
+            prev
+        })
+    }
+
+    fn snapshot(&self) -> T {
+        snapshot(self)
+    }
+
+    fn is_poisoned(&self) -> bool {
+        ShardedLock::is_poisoned(self)
+    }
+
+    fn forget_poisoning(&self) {
+        if ShardedLock::is_poisoned(self) {
+            diagnostics::forget(self)
+        }
+    }
+}
+
+/// A 64-bit integer which can be stored as `u64`.
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    target_has_atomic = "32",
+    not(all(target_family = "wasm", not(target_feature = "atomics")))
+))]
+pub(crate) trait Bits64: Copy {
+    fn to_bits(self) -> u64;
+    fn from_bits(bits: u64) -> Self;
+}
+
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    target_has_atomic = "32",
+    not(all(target_family = "wasm", not(target_feature = "atomics")))
+))]
+impl Bits64 for u64 {
+    fn to_bits(self) -> u64 {
+        self
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits
+    }
+}
+
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    target_has_atomic = "32",
+    not(all(target_family = "wasm", not(target_feature = "atomics")))
+))]
+impl Bits64 for i64 {
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as i64
+    }
+}
+
+/// Storage of the 64-bit shims. On ARM Linux, the CPU may have 64-bit atomics the target doesn't assume,
+/// so it is selected at runtime. On 32-bit x86 Windows, with `windows-intrinsics`, it uses the `Interlocked*64` functions,
+/// and elsewhere it is a sequence lock, whose loads don't take the lock. Without 32-bit atomics, or under loom,
+/// the replay scheduler or the weak memory simulation, it is always the `Lock`.
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    target_arch = "arm",
+    target_os = "linux",
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+type Slot64<T> = crate::arm_linux::Dispatch<T>;
+
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    windows,
+    target_arch = "x86",
+    feature = "windows-intrinsics",
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+type Slot64<T> = crate::windows::Interlocked<T>;
+
+#[cfg(all(
+    not(any(loom, feature = "replay", feature = "weak-memory")),
+    not(all(target_arch = "arm", target_os = "linux")),
+    not(all(windows, target_arch = "x86", feature = "windows-intrinsics")),
+    target_has_atomic = "32",
+    not(all(target_family = "wasm", not(target_feature = "atomics"))),
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+type Slot64<T> = crate::versioned::Versioned<T>;
+
+#[cfg(all(
+    any(
+        loom,
+        feature = "replay",
+        feature = "weak-memory",
+        all(
+            not(all(target_arch = "arm", target_os = "linux")),
+            not(all(windows, target_arch = "x86", feature = "windows-intrinsics")),
+            any(
+                not(target_has_atomic = "32"),
+                all(target_family = "wasm", not(target_feature = "atomics"))
+            )
+        )
+    ),
+    any(atomic_shim_fallback_u64, atomic_shim_fallback_i64)
+))]
+type Slot64<T> = Lock<T>;
+
+macro_rules! shim_atomic {
+    ($atomic:ident, $int:ident, $path:literal) => {
+        shim_atomic!($atomic, $int, $path, Lock<$int>);
+    };
+    ($atomic:ident, $int:ident, $path:literal, $slot:ty) => {
+        /// An integer type which can be safely shared between threads.
+        pub struct $atomic {
+            value: Registered<Audited<recorded!($int, $slot)>>,
+        }
+
+        impl $atomic {
+            /// Whether the operations are lock-free, which on the shim implementation is only the case
+            /// for the 64-bit types on 32-bit x86 Windows with `windows-intrinsics`.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            #[doc = concat!("if !", stringify!($atomic), "::IS_LOCK_FREE {")]
+            ///     // avoid hammering the lock
+            /// }
+            /// ```
+            pub const IS_LOCK_FREE: bool = <Registered<Audited<recorded!($int, $slot)>> as Slot<$int>>::IS_LOCK_FREE;
+
+            /// Creates a new atomic integer.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            #[doc = concat!("let atomic_forty_two = ", stringify!($atomic), "::new(42);")]
+            /// ```
+            #[cfg_attr(feature = "ordering-audit", track_caller)]
+            pub fn new(v: $int) -> Self {
+                Self {
+                    value: Slot::new(v),
+                }
+            }
+
+            /// Returns whether the operations on this type are lock-free.
+            ///
+            /// Mirrors C++'s `atomic::is_lock_free`. It agrees with `IS_LOCK_FREE`, except on ARM Linux,
+            /// where it also returns `true` for the 64-bit types if the atomics were found at runtime.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// if !some_var.is_lock_free() {
+            ///     // avoid hammering the lock
+            /// }
+            /// ```
+            pub fn is_lock_free(&self) -> bool {
+                self.value.is_lock_free()
+            }
+
+            /// Returns a mutable reference to the underlying integer.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let mut some_var = ", stringify!($atomic), "::new(10);")]
+            /// assert_eq!(*some_var.get_mut(), 10);
+            /// *some_var.get_mut() = 5;
+            /// assert_eq!(some_var.load(Ordering::SeqCst), 5);
+            /// ```
+            pub fn get_mut(&mut self) -> &mut $int {
+                Slot::get_mut(&mut self.value)
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.into_inner(), 5);
+            /// ```
+            pub fn into_inner(self) -> $int {
+                let this = mem::ManuallyDrop::new(self);
+                Slot::into_inner(unsafe { ptr::read(&this.value) })
+            }
+
+            /// Loads a value from the atomic integer.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 5);
+            /// ```
+            pub fn load(&self, order: Ordering) -> $int {
+                self.value.load(order)
+            }
+
+            /// Stores a value into the atomic integer.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// some_var.store(10, Ordering::Relaxed);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            pub fn store(&self, value: $int, order: Ordering) {
+                self.value.apply("store", order, |_| value);
+            }
+
+            /// Stores a value into the atomic integer, returning the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.swap(10, Ordering::Relaxed), 5);
+            /// ```
+            pub fn swap(&self, value: $int, order: Ordering) -> $int {
+                self.value.apply("swap", order, |_| value)
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the current value.
+            ///
+            /// The return value is always the previous value. If it is equal to current, then the value was updated.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.compare_and_swap(5, 10, Ordering::Relaxed), 5);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// assert_eq!(some_var.compare_and_swap(6, 12, Ordering::Relaxed), 10);
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            pub fn compare_and_swap(&self, current: $int, new: $int, order: Ordering) -> $int {
+                let prev = self.value.compare_and_swap(order, current, new);
+                events::compare_exchanged(prev == current);
+                prev
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the current value.
+            ///
+            /// The return value is a result indicating whether the new value was written and containing the previous value. On success this value is guaranteed to be equal to current.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+            /// assert_eq!(some_var.compare_exchange(5, 10,
+            ///                                      Ordering::Acquire,
+            ///                                      Ordering::Relaxed),
+            ///            Ok(5));
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// assert_eq!(some_var.compare_exchange(6, 12,
+            ///                                      Ordering::SeqCst,
+            ///                                      Ordering::Acquire),
+            ///            Err(10));
+            /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+            /// ```
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                _: Ordering,
+            ) -> Result<$int, $int> {
+                let prev = self.compare_and_swap(current, new, success);
+                if prev == current {
+                    Ok(current)
+                } else {
+                    Err(prev)
+                }
+            }
+
+            /// Stores a value into the atomic integer if the current value is the same as the current value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let val = ", stringify!($atomic), "::new(4);")]
+            /// let mut old = val.load(Ordering::Relaxed);
+            /// loop {
+            ///     let new = old * 2;
+            ///     match val.compare_exchange_weak(old, new, Ordering::SeqCst, Ordering::Relaxed) {
+            ///         Ok(_) => break,
+            ///         Err(x) => old = x,
+            ///     }
+            /// }
+            /// ```
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.compare_exchange(current, new, success, failure)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0);")]
+            /// assert_eq!(foo.fetch_add(10, Ordering::SeqCst), 0);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 10);
+            /// ```
+            pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_add", order, |prev| prev.wrapping_add(val))
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            ///
+            /// This operation wraps around on overflow.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(20);")]
+            /// assert_eq!(foo.fetch_sub(10, Ordering::SeqCst), 20);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 10);
+            /// ```
+            pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_sub", order, |prev| prev.wrapping_sub(val))
+            }
+
+            /// Bitwise "and" with the current value.
+            ///
+            /// Performs a bitwise "and" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0b101101);")]
+            /// assert_eq!(foo.fetch_and(0b110011, Ordering::SeqCst), 0b101101);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 0b100001);
+            /// ```
+            pub fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_and", order, |prev| prev & val)
+            }
+
+            /// Bitwise "nand" with the current value.
+            ///
+            /// Performs a bitwise "nand" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0x13);")]
+            /// assert_eq!(foo.fetch_nand(0x31, Ordering::SeqCst), 0x13);
+            /// assert_eq!(foo.load(Ordering::SeqCst), !(0x13 & 0x31));
+            /// ```
+            pub fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_nand", order, |prev| !(prev & val))
+            }
+
+            /// Bitwise "or" with the current value.
+            ///
+            /// Performs a bitwise "or" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0b101101);")]
+            /// assert_eq!(foo.fetch_or(0b110011, Ordering::SeqCst), 0b101101);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 0b111111);
+            /// ```
+            pub fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_or", order, |prev| prev | val)
+            }
+
+            /// Bitwise "xor" with the current value.
+            ///
+            /// Performs a bitwise "xor" operation on the current value and the argument val, and sets the new value to the result.
+            /// Returns the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(0b101101);")]
+            /// assert_eq!(foo.fetch_xor(0b110011, Ordering::SeqCst), 0b101101);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 0b011110);
+            /// ```
+            pub fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_xor", order, |prev| prev ^ val)
+            }
+
+            /// Fetches the value, and applies a function to it that returns an optional new value.
+            ///
+            /// Returns `Ok(previous_value)` if the function returned `Some(_)`, else `Err(previous_value)`.
+            /// The function may be called several times if other threads change the value meanwhile.
+            ///
+            #[doc = concat!("It ignores the Ordering arguments, but they are required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let x = ", stringify!($atomic), "::new(7);")]
+            /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| None), Err(7));
+            /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| Some(x + 1)), Ok(7));
+            /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| Some(x + 1)), Ok(8));
+            /// assert_eq!(x.load(Ordering::SeqCst), 9);
+            /// ```
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                let mut prev = self.load(fetch_order);
+                let backoff = Backoff::new();
+                while let Some(next) = f(prev) {
+                    match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
+                        Ok(value) => return Ok(value),
+                        Err(actual) => {
+                            prev = actual;
+                            backoff.spin();
+                        }
+                    }
+                }
+                Err(prev)
+            }
+
+            /// Maximum with the current value, returning the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(23);")]
+            /// assert_eq!(foo.fetch_max(42, Ordering::SeqCst), 23);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 42);
+            /// ```
+            pub fn fetch_max(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_max", order, |prev| prev.max(val))
+            }
+
+            /// Minimum with the current value, returning the previous value.
+            ///
+            #[doc = concat!("It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::", stringify!($atomic), "`")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the Mutex is poisoned
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(23);")]
+            /// assert_eq!(foo.fetch_min(42, Ordering::SeqCst), 23);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 23);
+            /// assert_eq!(foo.fetch_min(22, Ordering::SeqCst), 23);
+            /// assert_eq!(foo.load(Ordering::SeqCst), 22);
+            /// ```
+            pub fn fetch_min(&self, val: $int, order: Ordering) -> $int {
+                self.value.apply("fetch_min", order, |prev| prev.min(val))
+            }
+
+            /// Returns how many times the atomic was loaded, written, failed a compare-exchange,
+            /// and found its lock busy, since it was created.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(5);")]
+            /// foo.fetch_add(1, Ordering::SeqCst);
+            /// assert!(foo.compare_exchange(5, 10, Ordering::SeqCst, Ordering::SeqCst).is_err());
+            /// assert_eq!(foo.load(Ordering::SeqCst), 6);
+            ///
+            /// let stats = foo.stats();
+            #[doc = concat!("if !", stringify!($atomic), "::IS_LOCK_FREE {")]
+            ///     assert_eq!((stats.loads(), stats.stores(), stats.cas_failures()), (1, 1, 1));
+            /// }
+            /// ```
+            #[cfg(feature = "stats")]
+            pub fn stats(&self) -> Stats {
+                self.value.stats()
+            }
+
+            /// Labels the atomic in [`debug_dump`](crate::debug_dump), replacing the previous label.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let connections = ", stringify!($atomic), "::new(0);")]
+            /// connections.set_label("connections");
+            /// ```
+            #[cfg(feature = "registry")]
+            pub fn set_label(&self, label: impl Into<String>) {
+                self.value.set_label(label.into());
+            }
+
+            /// Returns the last writes to the atomic, from the oldest to the latest.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let foo = ", stringify!($atomic), "::new(5);")]
+            /// foo.fetch_add(1, Ordering::SeqCst);
+            /// foo.store(0, Ordering::SeqCst);
+            ///
+            #[doc = concat!("if !", stringify!($atomic), "::IS_LOCK_FREE {")]
+            ///     let reset = foo.history().pop().unwrap();
+            ///     assert_eq!((reset.op(), reset.old_value(), reset.new_value()), ("store", 6, 0));
+            ///     assert_eq!(reset.thread(), std::thread::current().id());
+            /// }
+            /// ```
+            #[cfg(feature = "history")]
+            pub fn history(&self) -> Vec<Operation<$int>> {
+                Slot::history(&self.value)
+            }
+        }
+
+        impl Default for $atomic {
+            #[cfg_attr(feature = "ordering-audit", track_caller)]
+            fn default() -> Self {
+                Self::new(Default::default())
+            }
+        }
+
+        impl Drop for $atomic {
+            fn drop(&mut self) {
+                self.value.forget_poisoning();
+            }
+        }
+
+        #[cfg(not(loom))]
+        const _: () =
+            assert!(mem::size_of::<$atomic>() <= <Registered<Audited<recorded!($int, $slot)>> as Slot<$int>>::FOOTPRINT);
+
+        /// Formats the value like `std` atomics, followed by ` (poisoned)` when the lock is poisoned.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+        #[doc = concat!("assert_eq!(format!(\"{:?}\", ", stringify!($atomic), "::new(5)), \"5\");")]
+        /// ```
+        impl fmt::Debug for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                debug_value(&self.value, f)
+            }
+        }
+
+        /// Formats a snapshot of the value as `Display`, `LowerHex`, `UpperHex` and `Binary`.
+        ///
+        /// Like `Debug`, it never panics, and formats the last value even if the lock is poisoned.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+        ///
+        #[doc = concat!("let mask = ", stringify!($atomic), "::new(42);")]
+        /// assert_eq!(format!("{} {:x} {:X} {:b}", mask, mask, mask, mask), "42 2a 2A 101010");
+        /// ```
+        impl fmt::Display for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.value.snapshot(), f)
+            }
+        }
+
+        impl fmt::LowerHex for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::LowerHex::fmt(&self.value.snapshot(), f)
+            }
+        }
+
+        impl fmt::UpperHex for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::UpperHex::fmt(&self.value.snapshot(), f)
+            }
+        }
+
+        impl fmt::Binary for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Binary::fmt(&self.value.snapshot(), f)
+            }
+        }
+
+        /// Creates a new atomic holding a snapshot of the value, loaded with `Ordering::SeqCst`.
+        ///
+        /// Only available with the `clone` feature, as cloning an atomic is usually a mistake.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use std::sync::atomic::Ordering;
+        #[doc = concat!("use ", $path, "::", stringify!($atomic), ";")]
+        ///
+        #[doc = concat!("let some_var = ", stringify!($atomic), "::new(5);")]
+        /// let snapshot = some_var.clone();
+        /// some_var.store(10, Ordering::SeqCst);
+        /// assert_eq!(snapshot.load(Ordering::SeqCst), 5);
+        /// ```
+        #[cfg(feature = "clone")]
+        impl Clone for $atomic {
+            fn clone(&self) -> Self {
+                Self::new(self.load(Ordering::SeqCst))
+            }
+        }
+
+        impl From<$int> for $atomic {
+            #[cfg_attr(feature = "ordering-audit", track_caller)]
+            fn from(value: $int) -> Self {
+                $atomic::new(value)
+            }
+        }
+    };
+}
+
+/// Interoperability with the `std` atomic, for targets which have it but use the shim anyway,
+/// such as when the `mutex` feature is enabled.
+macro_rules! std_conversions {
+    ($atomic:ident, $int:ident) => {
+        impl $atomic {
+            #[doc = concat!("Returns the native `std::sync::atomic::", stringify!($atomic), "`, which is never available on the shim implementation.")]
+            ///
+            /// It is only defined when the target has the `std` type, for compatibility with the native implementation.
+            pub fn as_native(&self) -> Option<&std::sync::atomic::$atomic> {
+                None
+            }
+        }
+
+        impl From<std::sync::atomic::$atomic> for $atomic {
+            #[cfg_attr(feature = "ordering-audit", track_caller)]
+            fn from(value: std::sync::atomic::$atomic) -> Self {
+                $atomic::new(value.into_inner())
+            }
+        }
+
+        impl From<$atomic> for std::sync::atomic::$atomic {
+            fn from(value: $atomic) -> Self {
+                std::sync::atomic::$atomic::new(value.into_inner())
+            }
+        }
+    };
+}
+
+#[cfg(atomic_shim_fallback_u64)]
+shim_atomic!(AtomicU64, u64, "atomic_shim", Slot64<u64>);
+
+#[cfg(all(atomic_shim_fallback_u64, target_has_atomic = "64"))]
+std_conversions!(AtomicU64, u64);
+
+#[cfg(atomic_shim_fallback_i64)]
+shim_atomic!(AtomicI64, i64, "atomic_shim", Slot64<i64>);
+
+#[cfg(all(atomic_shim_fallback_i64, target_has_atomic = "64"))]
+std_conversions!(AtomicI64, i64);
+
+#[cfg(atomic_shim_fallback_u8)]
+shim_atomic!(AtomicU8, u8, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_u8, target_has_atomic = "8"))]
+std_conversions!(AtomicU8, u8);
+
+#[cfg(atomic_shim_fallback_i8)]
+shim_atomic!(AtomicI8, i8, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_i8, target_has_atomic = "8"))]
+std_conversions!(AtomicI8, i8);
+
+#[cfg(atomic_shim_fallback_u16)]
+shim_atomic!(AtomicU16, u16, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_u16, target_has_atomic = "16"))]
+std_conversions!(AtomicU16, u16);
+
+#[cfg(atomic_shim_fallback_i16)]
+shim_atomic!(AtomicI16, i16, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_i16, target_has_atomic = "16"))]
+std_conversions!(AtomicI16, i16);
+
+#[cfg(atomic_shim_fallback_u32)]
+shim_atomic!(AtomicU32, u32, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_u32, target_has_atomic = "32"))]
+std_conversions!(AtomicU32, u32);
+
+#[cfg(atomic_shim_fallback_i32)]
+shim_atomic!(AtomicI32, i32, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_i32, target_has_atomic = "32"))]
+std_conversions!(AtomicI32, i32);
+
+#[cfg(atomic_shim_fallback_usize)]
+shim_atomic!(AtomicUsize, usize, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_usize, target_has_atomic = "ptr"))]
+std_conversions!(AtomicUsize, usize);
+
+#[cfg(atomic_shim_fallback_isize)]
+shim_atomic!(AtomicIsize, isize, "atomic_shim::atomic");
+
+#[cfg(all(atomic_shim_fallback_isize, target_has_atomic = "ptr"))]
+std_conversions!(AtomicIsize, isize);
+
+/// A boolean type which can be safely shared between threads.
+#[cfg(atomic_shim_fallback_bool)]
+#[derive(Default)]
+pub struct AtomicBool {
+    value: Lock<bool>,
+}
+
+#[cfg(atomic_shim_fallback_bool)]
+impl AtomicBool {
+    /// Whether the operations are lock-free, which is never the case on the shim implementation.
+    pub const IS_LOCK_FREE: bool = false;
+
+    /// Creates a new `AtomicBool`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let mut some_var = AtomicU64::new(10);
-    /// assert_eq!(*some_var.get_mut(), 10);
-    /// *some_var.get_mut() = 5;
-    /// assert_eq!(some_var.load(Ordering::SeqCst), 5);
+    /// use atomic_shim::atomic::AtomicBool;
+    /// let atomic_true = AtomicBool::new(true);
     /// ```
-    pub fn get_mut(&mut self) -> &mut u64 {
-        self.value.get_mut().unwrap()
+    pub fn new(v: bool) -> Self {
+        Self {
+            value: Slot::new(v),
+        }
     }
 
-    /// Consumes the atomic and returns the contained value.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// Returns whether the operations on this type are lock-free.
+    pub fn is_lock_free(&self) -> bool {
+        Self::IS_LOCK_FREE
+    }
+
+    /// Returns a mutable reference to the underlying bool.
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::AtomicU64;
-    /// let some_var = AtomicU64::new(5);
-    /// assert_eq!(some_var.into_inner(), 5);
-    /// ```
-    pub fn into_inner(self) -> u64 {
-        self.value.into_inner().unwrap()
+    pub fn get_mut(&mut self) -> &mut bool {
+        Slot::get_mut(&mut self.value)
     }
 
-    /// Loads a value from the atomic integer.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// Consumes the atomic and returns the contained value.
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    /// let some_var = AtomicU64::new(5);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 5);
-    /// ```
-    pub fn load(&self, _: Ordering) -> u64 {
-        *self.value.read().unwrap()
+    pub fn into_inner(self) -> bool {
+        let this = mem::ManuallyDrop::new(self);
+        Slot::into_inner(unsafe { ptr::read(&this.value) })
     }
 
-    /// Stores a value into the atomic integer.
+    /// Loads a value from the bool.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
@@ -96,77 +1311,39 @@ impl AtomicU64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let some_var = AtomicU64::new(5);
-    /// some_var.store(10, Ordering::Relaxed);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+    /// use atomic_shim::atomic::AtomicBool;
+    /// let some_bool = AtomicBool::new(true);
+    /// assert_eq!(some_bool.load(Ordering::Relaxed), true);
     /// ```
-    pub fn store(&self, value: u64, _: Ordering) {
-        let mut lock = self.value.write().unwrap();
-        *lock = value;
+    pub fn load(&self, order: Ordering) -> bool {
+        Slot::load(&self.value, order)
     }
 
-    /// Stores a value into the atomic integer, returning the previous value.
+    /// Stores a value into the bool.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let some_var = AtomicU64::new(5);
-    /// assert_eq!(some_var.swap(10, Ordering::Relaxed), 5);
-    /// ```
-    pub fn swap(&self, value: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = value;
-        prev
+    pub fn store(&self, value: bool, order: Ordering) {
+        self.value.update(order, |_| value);
     }
 
-    /// Stores a value into the atomic integer if the current value is the same as the current value.
-    ///
-    /// The return value is always the previous value. If it is equal to current, then the value was updated.
+    /// Stores a value into the bool, returning the previous value.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let some_var = AtomicU64::new(5);
-    /// assert_eq!(some_var.compare_and_swap(5, 10, Ordering::Relaxed), 5);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// assert_eq!(some_var.compare_and_swap(6, 12, Ordering::Relaxed), 10);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// ```
-    pub fn compare_and_swap(&self, current: u64, new: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        if prev == current {
-            *lock = new;
-        };
-        prev
+    pub fn swap(&self, value: bool, order: Ordering) -> bool {
+        self.value.update(order, |_| value)
     }
 
-    /// Stores a value into the atomic integer if the current value is the same as the current value.
+    /// Stores a value into the bool if the current value is the same as the current value.
     ///
-    /// The return value is a result indicating whether the new value was written and containing the previous value. On success this value is guaranteed to be equal to current.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
@@ -176,187 +1353,93 @@ impl AtomicU64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let some_var = AtomicU64::new(5);
-    /// assert_eq!(some_var.compare_exchange(5, 10,
-    ///                                      Ordering::Acquire,
-    ///                                      Ordering::Relaxed),
-    ///            Ok(5));
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// assert_eq!(some_var.compare_exchange(6, 12,
-    ///                                      Ordering::SeqCst,
-    ///                                      Ordering::Acquire),
-    ///            Err(10));
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
+    /// use atomic_shim::atomic::AtomicBool;
+    ///
+    /// let some_bool = AtomicBool::new(true);
+    /// assert_eq!(some_bool.compare_exchange(true, false, Ordering::Acquire, Ordering::Relaxed), Ok(true));
+    /// assert_eq!(some_bool.compare_exchange(true, true, Ordering::SeqCst, Ordering::Acquire), Err(false));
     /// ```
     pub fn compare_exchange(
         &self,
-        current: u64,
-        new: u64,
-        _: Ordering,
+        current: bool,
+        new: bool,
+        success: Ordering,
         _: Ordering,
-    ) -> Result<u64, u64> {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
+    ) -> Result<bool, bool> {
+        let prev = self
+            .value
+            .update(success, |prev| if prev == current { new } else { prev });
         if prev == current {
-            *lock = new;
             Ok(current)
         } else {
             Err(prev)
         }
     }
 
-    /// Stores a value into the atomic integer if the current value is the same as the current value.
+    /// Stores a value into the bool if the current value is the same as the current value.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let val = AtomicU64::new(4);
-    /// let mut old = val.load(Ordering::Relaxed);
-    /// loop {
-    ///     let new = old * 2;
-    ///     match val.compare_exchange_weak(old, new, Ordering::SeqCst, Ordering::Relaxed) {
-    ///         Ok(_) => break,
-    ///         Err(x) => old = x,
-    ///     }
-    /// }
-    /// ```
     pub fn compare_exchange_weak(
         &self,
-        current: u64,
-        new: u64,
+        current: bool,
+        new: bool,
         success: Ordering,
         failure: Ordering,
-    ) -> Result<u64, u64> {
+    ) -> Result<bool, bool> {
         self.compare_exchange(current, new, success, failure)
     }
 
-    /// Adds to the current value, returning the previous value.
+    /// Logical "and" with a boolean value, returning the previous value.
     ///
-    /// This operation wraps around on overflow.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let foo = AtomicU64::new(0);
-    /// assert_eq!(foo.fetch_add(10, Ordering::SeqCst), 0);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 10);
-    /// ```
-    pub fn fetch_add(&self, val: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev.wrapping_add(val);
-        prev
+    pub fn fetch_and(&self, val: bool, order: Ordering) -> bool {
+        self.value.update(order, |prev| prev & val)
     }
 
-    /// Subtracts from the current value, returning the previous value.
+    /// Logical "nand" with a boolean value, returning the previous value.
     ///
-    /// This operation wraps around on overflow.
-    ///    
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let foo = AtomicU64::new(20);
-    /// assert_eq!(foo.fetch_sub(10, Ordering::SeqCst), 20);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 10);
-    /// ```
-    pub fn fetch_sub(&self, val: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev.wrapping_sub(val);
-        prev
+    pub fn fetch_nand(&self, val: bool, order: Ordering) -> bool {
+        self.value.update(order, |prev| !(prev & val))
     }
 
-    /// Bitwise "and" with the current value.
-    ///
-    /// Performs a bitwise "and" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
+    /// Logical "or" with a boolean value, returning the previous value.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let foo = AtomicU64::new(0b101101);
-    /// assert_eq!(foo.fetch_and(0b110011, Ordering::SeqCst), 0b101101);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 0b100001);
-    /// ```
-    pub fn fetch_and(&self, val: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev & val;
-        prev
+    pub fn fetch_or(&self, val: bool, order: Ordering) -> bool {
+        self.value.update(order, |prev| prev | val)
     }
 
-    /// Bitwise "nand" with the current value.
+    /// Logical "xor" with a boolean value, returning the previous value.
     ///
-    /// Performs a bitwise "nand" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    ///
-    /// let foo = AtomicU64::new(0x13);
-    /// assert_eq!(foo.fetch_nand(0x31, Ordering::SeqCst), 0x13);
-    /// assert_eq!(foo.load(Ordering::SeqCst), !(0x13 & 0x31));
-    /// ```
-    pub fn fetch_nand(&self, val: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = !(prev & val);
-        prev
+    pub fn fetch_xor(&self, val: bool, order: Ordering) -> bool {
+        self.value.update(order, |prev| prev ^ val)
     }
 
-    /// Bitwise "or" with the current value.
-    ///
-    /// Performs a bitwise "or" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
+    /// Logical "not" of the current value, returning the previous value.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
@@ -366,299 +1449,222 @@ impl AtomicU64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
+    /// use atomic_shim::atomic::AtomicBool;
     ///
-    /// let foo = AtomicU64::new(0b101101);
-    /// assert_eq!(foo.fetch_or(0b110011, Ordering::SeqCst), 0b101101);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 0b111111);
+    /// let foo = AtomicBool::new(true);
+    /// assert_eq!(foo.fetch_not(Ordering::SeqCst), true);
+    /// assert_eq!(foo.load(Ordering::SeqCst), false);
     /// ```
-    pub fn fetch_or(&self, val: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev | val;
-        prev
+    pub fn fetch_not(&self, order: Ordering) -> bool {
+        self.value.update(order, |prev| !prev)
     }
 
-    /// Bitwise "xor" with the current value.
+    /// Fetches the value, and applies a function to it that returns an optional new value.
     ///
-    /// Performs a bitwise "xor" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
+    /// Returns `Ok(previous_value)` if the function returned `Some(_)`, else `Err(previous_value)`.
+    /// The function may be called several times if other threads change the value meanwhile.
     ///
-    ///     
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicU64`
+    /// It ignores the Ordering arguments, but they are required for compatibility with `std::sync::atomic::AtomicBool`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
     ///
-    ///  # Examples
+    /// # Examples
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicU64;
-    /// let foo = AtomicU64::new(0b101101);
-    /// assert_eq!(foo.fetch_xor(0b110011, Ordering::SeqCst), 0b101101);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 0b011110);
+    /// use atomic_shim::atomic::AtomicBool;
+    ///
+    /// let x = AtomicBool::new(false);
+    /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| None), Err(false));
+    /// assert_eq!(x.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| Some(!x)), Ok(false));
+    /// assert_eq!(x.load(Ordering::SeqCst), true);
     /// ```
-    pub fn fetch_xor(&self, val: u64, _: Ordering) -> u64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev ^ val;
-        prev
+    pub fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<bool, bool>
+    where
+        F: FnMut(bool) -> Option<bool>,
+    {
+        let mut prev = self.load(fetch_order);
+        let backoff = Backoff::new();
+        while let Some(next) = f(prev) {
+            match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
+                Ok(value) => return Ok(value),
+                Err(actual) => {
+                    prev = actual;
+                    backoff.spin();
+                }
+            }
+        }
+        Err(prev)
     }
 }
 
-impl From<u64> for AtomicU64 {
-    fn from(value: u64) -> Self {
-        AtomicU64::new(value)
+#[cfg(all(atomic_shim_fallback_bool, not(loom)))]
+const _: () = assert!(mem::size_of::<AtomicBool>() <= <Lock<bool> as Slot<bool>>::FOOTPRINT);
+
+#[cfg(atomic_shim_fallback_bool)]
+impl Drop for AtomicBool {
+    fn drop(&mut self) {
+        self.value.forget_poisoning();
     }
 }
 
-/// An integer type which can be safely shared between threads.
-#[derive(Debug, Default)]
-pub struct AtomicI64 {
-    value: ShardedLock<i64>,
+#[cfg(atomic_shim_fallback_bool)]
+impl fmt::Debug for AtomicBool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_value(&self.value, f)
+    }
 }
 
-impl AtomicI64 {
-    /// Creates a new atomic integer.
+#[cfg(atomic_shim_fallback_bool)]
+impl From<bool> for AtomicBool {
+    fn from(value: bool) -> Self {
+        AtomicBool::new(value)
+    }
+}
+
+/// A raw pointer type which can be safely shared between threads.
+#[cfg(atomic_shim_fallback_ptr)]
+pub struct AtomicPtr<T> {
+    value: Lock<*mut T>,
+}
+
+// The pointer is only ever accessed through the lock, like `std::sync::atomic::AtomicPtr`.
+#[cfg(atomic_shim_fallback_ptr)]
+unsafe impl<T> Send for AtomicPtr<T> {}
+#[cfg(atomic_shim_fallback_ptr)]
+unsafe impl<T> Sync for AtomicPtr<T> {}
+
+#[cfg(atomic_shim_fallback_ptr)]
+impl<T> AtomicPtr<T> {
+    /// Whether the operations are lock-free, which is never the case on the shim implementation.
+    pub const IS_LOCK_FREE: bool = false;
+
+    /// Creates a new `AtomicPtr`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use atomic_shim::AtomicI64;
-    /// let atomic_forty_two = AtomicI64::new(42);
+    /// use atomic_shim::atomic::AtomicPtr;
+    /// let ptr = &mut 5;
+    /// let atomic_ptr = AtomicPtr::new(ptr);
     /// ```
-    pub fn new(v: i64) -> Self {
+    pub fn new(p: *mut T) -> Self {
         Self {
-            value: ShardedLock::new(v),
+            value: Slot::new(p),
         }
     }
 
-    /// Returns a mutable reference to the underlying integer.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
-    ///
-    /// # Panics
-    ///
-    /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let mut some_var = AtomicI64::new(10);
-    /// assert_eq!(*some_var.get_mut(), 10);
-    /// *some_var.get_mut() = 5;
-    /// assert_eq!(some_var.load(Ordering::SeqCst), 5);
-    /// ```
-    pub fn get_mut(&mut self) -> &mut i64 {
-        self.value.get_mut().unwrap()
+    /// Returns whether the operations on this type are lock-free.
+    pub fn is_lock_free(&self) -> bool {
+        Self::IS_LOCK_FREE
     }
 
-    /// Consumes the atomic and returns the contained value.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// Returns a mutable reference to the underlying pointer.
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::AtomicI64;
-    /// let some_var = AtomicI64::new(5);
-    /// assert_eq!(some_var.into_inner(), 5);
-    /// ```
-    pub fn into_inner(self) -> i64 {
-        self.value.into_inner().unwrap()
+    pub fn get_mut(&mut self) -> &mut *mut T {
+        Slot::get_mut(&mut self.value)
     }
 
-    /// Loads a value from the atomic integer.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// Consumes the atomic and returns the contained value.
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    /// let some_var = AtomicI64::new(5);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 5);
-    /// ```
-    pub fn load(&self, _: Ordering) -> i64 {
-        *self.value.read().unwrap()
+    pub fn into_inner(self) -> *mut T {
+        let this = mem::ManuallyDrop::new(self);
+        Slot::into_inner(unsafe { ptr::read(&this.value) })
     }
 
-    /// Stores a value into the atomic integer.
+    /// Loads a value from the pointer.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let some_var = AtomicI64::new(5);
-    /// some_var.store(10, Ordering::Relaxed);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// ```
-    pub fn store(&self, value: i64, _: Ordering) {
-        let mut lock = self.value.write().unwrap();
-        *lock = value;
+    pub fn load(&self, order: Ordering) -> *mut T {
+        Slot::load(&self.value, order)
     }
 
-    /// Stores a value into the atomic integer, returning the previous value.
+    /// Stores a value into the pointer.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let some_var = AtomicI64::new(5);
-    /// assert_eq!(some_var.swap(10, Ordering::Relaxed), 5);
-    /// ```
-    pub fn swap(&self, value: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = value;
-        prev
+    pub fn store(&self, ptr: *mut T, order: Ordering) {
+        self.value.update(order, |_| ptr);
     }
 
-    /// Stores a value into the atomic integer if the current value is the same as the current value.
+    /// Stores a value into the pointer, returning the previous value.
     ///
-    /// The return value is always the previous value. If it is equal to current, then the value was updated.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let some_var = AtomicI64::new(5);
-    /// assert_eq!(some_var.compare_and_swap(5, 10, Ordering::Relaxed), 5);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// assert_eq!(some_var.compare_and_swap(6, 12, Ordering::Relaxed), 10);
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// ```
-    pub fn compare_and_swap(&self, current: i64, new: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        if prev == current {
-            *lock = new;
-        };
-        prev
+    pub fn swap(&self, ptr: *mut T, order: Ordering) -> *mut T {
+        self.value.update(order, |_| ptr)
     }
 
-    /// Stores a value into the atomic integer if the current value is the same as the current value.
-    ///
-    /// The return value is a result indicating whether the new value was written and containing the previous value. On success this value is guaranteed to be equal to current.
+    /// Stores a value into the pointer if the current value is the same as the current value.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let some_var = AtomicI64::new(5);
-    /// assert_eq!(some_var.compare_exchange(5, 10,
-    ///                                      Ordering::Acquire,
-    ///                                      Ordering::Relaxed),
-    ///            Ok(5));
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// assert_eq!(some_var.compare_exchange(6, 12,
-    ///                                      Ordering::SeqCst,
-    ///                                      Ordering::Acquire),
-    ///            Err(10));
-    /// assert_eq!(some_var.load(Ordering::Relaxed), 10);
-    /// ```
     pub fn compare_exchange(
         &self,
-        current: i64,
-        new: i64,
-        _: Ordering,
+        current: *mut T,
+        new: *mut T,
+        success: Ordering,
         _: Ordering,
-    ) -> Result<i64, i64> {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
+    ) -> Result<*mut T, *mut T> {
+        let prev = self
+            .value
+            .update(success, |prev| if prev == current { new } else { prev });
         if prev == current {
-            *lock = new;
             Ok(current)
         } else {
             Err(prev)
         }
     }
 
-    /// Stores a value into the atomic integer if the current value is the same as the current value.
+    /// Stores a value into the pointer if the current value is the same as the current value.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let val = AtomicI64::new(4);
-    /// let mut old = val.load(Ordering::Relaxed);
-    /// loop {
-    ///     let new = old * 2;
-    ///     match val.compare_exchange_weak(old, new, Ordering::SeqCst, Ordering::Relaxed) {
-    ///         Ok(_) => break,
-    ///         Err(x) => old = x,
-    ///     }
-    /// }
-    /// ```
     pub fn compare_exchange_weak(
         &self,
-        current: i64,
-        new: i64,
+        current: *mut T,
+        new: *mut T,
         success: Ordering,
         failure: Ordering,
-    ) -> Result<i64, i64> {
+    ) -> Result<*mut T, *mut T> {
         self.compare_exchange(current, new, success, failure)
     }
 
-    /// Adds to the current value, returning the previous value.
+    /// Fetches the value, and applies a function to it that returns an optional new value.
     ///
-    /// This operation wraps around on overflow.
+    /// Returns `Ok(previous_value)` if the function returned `Some(_)`, else `Err(previous_value)`.
+    /// The function may be called several times if other threads change the value meanwhile.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering arguments, but they are required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
@@ -668,24 +1674,40 @@ impl AtomicI64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
+    /// use atomic_shim::atomic::AtomicPtr;
     ///
-    /// let foo = AtomicI64::new(0);
-    /// assert_eq!(foo.fetch_add(10, Ordering::SeqCst), 0);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 10);
+    /// let mut values = [1, 2];
+    /// let ptr = AtomicPtr::new(&mut values[0] as *mut i32);
+    /// let next = &mut values[1] as *mut i32;
+    /// assert!(ptr.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(next)).is_ok());
+    /// assert_eq!(ptr.load(Ordering::SeqCst), next);
     /// ```
-    pub fn fetch_add(&self, val: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev.wrapping_add(val);
-        prev
+    pub fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<*mut T, *mut T>
+    where
+        F: FnMut(*mut T) -> Option<*mut T>,
+    {
+        let mut prev = self.load(fetch_order);
+        let backoff = Backoff::new();
+        while let Some(next) = f(prev) {
+            match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
+                Ok(value) => return Ok(value),
+                Err(actual) => {
+                    prev = actual;
+                    backoff.spin();
+                }
+            }
+        }
+        Err(prev)
     }
 
-    /// Subtracts from the current value, returning the previous value.
+    /// Offsets the pointer by `val` elements of `T`, wrapping around, returning the previous pointer.
     ///
-    /// This operation wraps around on overflow.
-    ///    
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
@@ -695,53 +1717,31 @@ impl AtomicI64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
+    /// use atomic_shim::atomic::AtomicPtr;
     ///
-    /// let foo = AtomicI64::new(20);
-    /// assert_eq!(foo.fetch_sub(10, Ordering::SeqCst), 20);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 10);
+    /// let values = [1u64, 2, 3];
+    /// let ptr = AtomicPtr::new(values.as_ptr() as *mut u64);
+    /// assert_eq!(ptr.fetch_ptr_add(2, Ordering::SeqCst), values.as_ptr() as *mut u64);
+    /// assert_eq!(unsafe { *ptr.load(Ordering::SeqCst) }, 3);
     /// ```
-    pub fn fetch_sub(&self, val: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev.wrapping_sub(val);
-        prev
+    pub fn fetch_ptr_add(&self, val: usize, order: Ordering) -> *mut T {
+        self.value.update(order, |prev| prev.wrapping_add(val))
     }
 
-    /// Bitwise "and" with the current value.
+    /// Offsets the pointer back by `val` elements of `T`, wrapping around, returning the previous pointer.
     ///
-    /// Performs a bitwise "and" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let foo = AtomicI64::new(0b101101);
-    /// assert_eq!(foo.fetch_and(0b110011, Ordering::SeqCst), 0b101101);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 0b100001);
-    /// ```
-    pub fn fetch_and(&self, val: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev & val;
-        prev
+    pub fn fetch_ptr_sub(&self, val: usize, order: Ordering) -> *mut T {
+        self.value.update(order, |prev| prev.wrapping_sub(val))
     }
 
-    /// Bitwise "nand" with the current value.
+    /// Offsets the pointer by `val` bytes, wrapping around, returning the previous pointer.
     ///
-    /// Performs a bitwise "nand" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
-    ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
@@ -751,25 +1751,31 @@ impl AtomicI64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
+    /// use atomic_shim::atomic::AtomicPtr;
     ///
-    /// let foo = AtomicI64::new(0x13);
-    /// assert_eq!(foo.fetch_nand(0x31, Ordering::SeqCst), 0x13);
-    /// assert_eq!(foo.load(Ordering::SeqCst), !(0x13 & 0x31));
+    /// let ptr = AtomicPtr::<u64>::new(std::ptr::null_mut());
+    /// ptr.fetch_byte_add(3, Ordering::SeqCst);
+    /// ptr.fetch_byte_sub(1, Ordering::SeqCst);
+    /// assert_eq!(ptr.load(Ordering::SeqCst) as usize, 2);
     /// ```
-    pub fn fetch_nand(&self, val: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = !(prev & val);
-        prev
+    pub fn fetch_byte_add(&self, val: usize, order: Ordering) -> *mut T {
+        self.value.update(order, |prev| prev.wrapping_byte_add(val))
     }
 
-    /// Bitwise "or" with the current value.
+    /// Offsets the pointer back by `val` bytes, wrapping around, returning the previous pointer.
+    ///
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
+    ///
+    /// # Panics
     ///
-    /// Performs a bitwise "or" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
+    /// Panics if the Mutex is poisoned
+    pub fn fetch_byte_sub(&self, val: usize, order: Ordering) -> *mut T {
+        self.value.update(order, |prev| prev.wrapping_byte_sub(val))
+    }
+
+    /// Bitwise "or" of the address with `val`, returning the previous pointer, such as to set a tag in its low bits.
     ///
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
@@ -779,50 +1785,80 @@ impl AtomicI64 {
     ///
     /// ```
     /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    ///
-    /// let foo = AtomicI64::new(0b101101);
-    /// assert_eq!(foo.fetch_or(0b110011, Ordering::SeqCst), 0b101101);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 0b111111);
+    /// use atomic_shim::atomic::AtomicPtr;
+    ///
+    /// let mut value = 5u64;
+    /// let untagged = &mut value as *mut u64;
+    /// let ptr = AtomicPtr::new(untagged);
+    /// assert_eq!(ptr.fetch_or(1, Ordering::SeqCst), untagged);
+    /// assert_eq!(ptr.fetch_and(!1, Ordering::SeqCst) as usize, untagged as usize | 1);
+    /// assert_eq!(ptr.fetch_xor(1, Ordering::SeqCst), untagged);
+    /// assert_eq!(ptr.load(Ordering::SeqCst) as usize, untagged as usize ^ 1);
     /// ```
-    pub fn fetch_or(&self, val: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev | val;
-        prev
+    pub fn fetch_or(&self, val: usize, order: Ordering) -> *mut T {
+        self.value
+            .update(order, |prev| with_addr(prev, prev as usize | val))
     }
 
-    /// Bitwise "xor" with the current value.
-    ///
-    /// Performs a bitwise "xor" operation on the current value and the argument val, and sets the new value to the result.
-    /// Returns the previous value.
+    /// Bitwise "and" of the address with `val`, returning the previous pointer, such as to clear a tag in its low bits.
     ///
-    ///     
-    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::AtomicI64`
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned
+    pub fn fetch_and(&self, val: usize, order: Ordering) -> *mut T {
+        self.value
+            .update(order, |prev| with_addr(prev, prev as usize & val))
+    }
+
+    /// Bitwise "xor" of the address with `val`, returning the previous pointer, such as to toggle a tag in its low bits.
     ///
-    ///  # Examples
+    /// It ignores the Ordering argument, but it is required for compatibility with `std::sync::atomic::AtomicPtr`
     ///
-    /// ```
-    /// use std::sync::atomic::Ordering;
-    /// use atomic_shim::AtomicI64;
-    /// let foo = AtomicI64::new(0b101101);
-    /// assert_eq!(foo.fetch_xor(0b110011, Ordering::SeqCst), 0b101101);
-    /// assert_eq!(foo.load(Ordering::SeqCst), 0b011110);
-    /// ```
-    pub fn fetch_xor(&self, val: i64, _: Ordering) -> i64 {
-        let mut lock = self.value.write().unwrap();
-        let prev = *lock;
-        *lock = prev ^ val;
-        prev
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned
+    pub fn fetch_xor(&self, val: usize, order: Ordering) -> *mut T {
+        self.value
+            .update(order, |prev| with_addr(prev, prev as usize ^ val))
+    }
+}
+
+/// Returns a pointer to `addr` with the provenance of `ptr`, like `pointer::with_addr`, which is newer than the crate.
+#[cfg(atomic_shim_fallback_ptr)]
+fn with_addr<T>(ptr: *mut T, addr: usize) -> *mut T {
+    ptr.wrapping_byte_add(addr.wrapping_sub(ptr as usize))
+}
+
+#[cfg(atomic_shim_fallback_ptr)]
+impl<T> Default for AtomicPtr<T> {
+    fn default() -> Self {
+        Self::new(std::ptr::null_mut())
+    }
+}
+
+#[cfg(atomic_shim_fallback_ptr)]
+impl<T> From<*mut T> for AtomicPtr<T> {
+    fn from(p: *mut T) -> Self {
+        Self::new(p)
+    }
+}
+
+#[cfg(all(atomic_shim_fallback_ptr, not(loom)))]
+const _: () =
+    assert!(mem::size_of::<AtomicPtr<u8>>() <= <Lock<*mut u8> as Slot<*mut u8>>::FOOTPRINT);
+
+#[cfg(atomic_shim_fallback_ptr)]
+impl<T> Drop for AtomicPtr<T> {
+    fn drop(&mut self) {
+        self.value.forget_poisoning();
     }
 }
 
-impl From<i64> for AtomicI64 {
-    fn from(value: i64) -> Self {
-        AtomicI64::new(value)
+#[cfg(atomic_shim_fallback_ptr)]
+impl<T> fmt::Debug for AtomicPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_value(&self.value, f)
     }
 }
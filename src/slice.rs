@@ -0,0 +1,158 @@
+//! Atomic operations on the elements of a borrowed slice of `u64`s, such as a large preallocated table of counters.
+//!
+//! Each element is an integer the atomic doesn't own, like those of [`RawAtomicU64`], so on the shim, the
+//! operations are serialized by the global table of locks keyed by address, and the slice doesn't need a lock per
+//! element. The borrow keeps the elements from being read or written other than atomically while the view is used.
+
+use crate::RawAtomicU64;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
+
+/// A view of a `&mut [u64]` whose elements are changed atomically through a shared reference.
+///
+/// Each method takes the index of the element it operates on, and panics if the index is out of bounds, like
+/// indexing the slice. Once the threads are done, [`into_inner`](AtomicU64Slice::into_inner) gives the slice back.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicU64Slice;
+/// use std::sync::atomic::Ordering;
+/// use std::thread;
+///
+/// let mut buckets = vec![0u64; 4];
+/// let view = AtomicU64Slice::new(&mut buckets);
+/// thread::scope(|s| {
+///     for t in 0..4 {
+///         let view = &view;
+///         s.spawn(move || view.fetch_add(t % 2, 1, Ordering::Relaxed));
+///     }
+/// });
+///
+/// assert_eq!(view.into_inner(), &[2, 2, 0, 0]);
+/// ```
+pub struct AtomicU64Slice<'a> {
+    ptr: *mut u64,
+    len: usize,
+    _marker: PhantomData<&'a mut [u64]>,
+}
+
+unsafe impl Send for AtomicU64Slice<'_> {}
+unsafe impl Sync for AtomicU64Slice<'_> {}
+
+impl<'a> AtomicU64Slice<'a> {
+    /// Borrows `slice` for atomic operations on its elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slice` isn't aligned to 8 bytes, which a `u64` is only on some targets, such as
+    /// x86_64. Slices of a `Vec<u64>` or a `[u64; N]` on a 32-bit target may be aligned to 4 bytes only.
+    pub fn new(slice: &'a mut [u64]) -> Self {
+        assert_eq!(
+            slice.as_ptr() as usize % 8,
+            0,
+            "an AtomicU64Slice must be aligned to 8 bytes"
+        );
+        Self {
+            ptr: slice.as_mut_ptr(),
+            len: slice.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the slice has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the atomic operating on the element at `index`.
+    fn at(&self, index: usize) -> RawAtomicU64 {
+        assert!(
+            index < self.len,
+            "index out of bounds: the len is {} but the index is {}",
+            self.len,
+            index
+        );
+        // The element is within the borrowed slice, and only accessed through atomics until the borrow ends.
+        unsafe { RawAtomicU64::from_raw(self.ptr.add(index)) }
+    }
+
+    /// Loads the element at `index`.
+    pub fn load(&self, index: usize, order: Ordering) -> u64 {
+        self.at(index).load(order)
+    }
+
+    /// Stores `value` into the element at `index`.
+    pub fn store(&self, index: usize, value: u64, order: Ordering) {
+        self.at(index).store(value, order)
+    }
+
+    /// Stores `value` into the element at `index`, returning the previous value.
+    pub fn swap(&self, index: usize, value: u64, order: Ordering) -> u64 {
+        self.at(index).swap(value, order)
+    }
+
+    /// Stores `new` into the element at `index` if it is the same as `current`.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing the previous value.
+    pub fn compare_exchange(
+        &self,
+        index: usize,
+        current: u64,
+        new: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64> {
+        self.at(index)
+            .compare_exchange(current, new, success, failure)
+    }
+
+    /// Adds `val` to the element at `index`, returning the previous value.
+    ///
+    /// This operation wraps around on overflow.
+    pub fn fetch_add(&self, index: usize, val: u64, order: Ordering) -> u64 {
+        self.at(index).fetch_add(val, order)
+    }
+
+    /// Subtracts `val` from the element at `index`, returning the previous value.
+    ///
+    /// This operation wraps around on overflow.
+    pub fn fetch_sub(&self, index: usize, val: u64, order: Ordering) -> u64 {
+        self.at(index).fetch_sub(val, order)
+    }
+
+    /// Bitwise "and" with the element at `index`, returning the previous value.
+    pub fn fetch_and(&self, index: usize, val: u64, order: Ordering) -> u64 {
+        self.at(index).fetch_and(val, order)
+    }
+
+    /// Bitwise "or" with the element at `index`, returning the previous value.
+    pub fn fetch_or(&self, index: usize, val: u64, order: Ordering) -> u64 {
+        self.at(index).fetch_or(val, order)
+    }
+
+    /// Bitwise "xor" with the element at `index`, returning the previous value.
+    pub fn fetch_xor(&self, index: usize, val: u64, order: Ordering) -> u64 {
+        self.at(index).fetch_xor(val, order)
+    }
+
+    /// Ends the atomic operations, returning the slice.
+    pub fn into_inner(self) -> &'a mut [u64] {
+        // The atomics of the elements don't outlive the calls, so none is left once the view is consumed.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl fmt::Debug for AtomicU64Slice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len).map(|index| self.load(index, Ordering::Relaxed)))
+            .finish()
+    }
+}
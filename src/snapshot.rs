@@ -0,0 +1,88 @@
+//! Snapshots of several atomics which are consistent with each other, for scrapes of related counters.
+//!
+//! Loading counters one after the other reads each at a different time, so a scrape may see a request counted
+//! as failed but not as received. The snapshot loads every counter, then loads them again, until two rounds
+//! read the same values: the values were then all held at once, between the rounds, unless a counter changed
+//! and came back to the same value meanwhile, which counters only moving forward never do.
+
+use crate::{AtomicU64, Backoff};
+use std::sync::atomic::Ordering;
+
+/// Captures the values of several [`AtomicU64`]s as they were at a single point in time.
+///
+/// The atomics don't need to be created for it, such as in an [`AtomicGroup`](crate::AtomicGroup): any of them
+/// can be captured, and their writers aren't slowed down. A capture retries while the atomics change, backing off
+/// between its rounds of loads, so writers which never pause keep it waiting, and
+/// [`try_capture`](Snapshotter::try_capture) bounds the rounds.
+///
+/// The snapshot is consistent for atomics which never hold a value twice, such as counters which only increase.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{AtomicU64, Snapshotter};
+/// use std::sync::atomic::Ordering;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let received = Arc::new(AtomicU64::new(0));
+/// let failed = Arc::new(AtomicU64::new(0));
+/// let server = {
+///     let (received, failed) = (received.clone(), failed.clone());
+///     thread::spawn(move || {
+///         for _ in 0..1000 {
+///             received.fetch_add(1, Ordering::SeqCst);
+///             failed.fetch_add(1, Ordering::SeqCst);
+///         }
+///     })
+/// };
+///
+/// for _ in 0..10 {
+///     // loading them in turn could read the requests before they were counted, and their failures after
+///     let snapshot = Snapshotter::capture(&[&received, &failed]);
+///     assert!(snapshot[1] <= snapshot[0]);
+/// }
+/// server.join().unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Snapshotter;
+
+impl Snapshotter {
+    /// Returns the values of `atomics`, in the same order, as they all were at a point during the call.
+    pub fn capture(atomics: &[&AtomicU64]) -> Vec<u64> {
+        let backoff = Backoff::new();
+        let mut previous = collect(atomics);
+        loop {
+            let current = collect(atomics);
+            if current == previous {
+                return current;
+            }
+            previous = current;
+            backoff.snooze();
+        }
+    }
+
+    /// Returns the values of `atomics` like [`capture`](Snapshotter::capture), or `None` if they kept changing
+    /// during `rounds` rounds of loads.
+    pub fn try_capture(atomics: &[&AtomicU64], rounds: usize) -> Option<Vec<u64>> {
+        let backoff = Backoff::new();
+        let mut previous = collect(atomics);
+        for _ in 1..rounds {
+            let current = collect(atomics);
+            if current == previous {
+                return Some(current);
+            }
+            previous = current;
+            backoff.snooze();
+        }
+        None
+    }
+}
+
+/// Loads each of `atomics`.
+fn collect(atomics: &[&AtomicU64]) -> Vec<u64> {
+    atomics
+        .iter()
+        .map(|atomic| atomic.load(Ordering::SeqCst))
+        .collect()
+}
@@ -0,0 +1,175 @@
+//! Counts of the operations on each shimmed atomic, behind the `stats` feature.
+//!
+//! Profilers attribute the time spent waiting on a lock to the shim, rather than to the atomic which owns it.
+//! With the feature, every shimmed atomic counts its loads, writes, failed compare-exchanges and busy locks,
+//! so the hot ones can be found among many. The counts take four more words per atomic, and wrap around on overflow.
+//!
+//! The native atomics have no lock, and count nothing.
+#![cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+
+#[cfg(atomic_shim_fallback)]
+use crate::shim::Slot;
+use std::cell::Cell;
+#[cfg(atomic_shim_fallback)]
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The counts of the operations on an atomic, returned by its `stats` method.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Stats {
+    loads: u64,
+    stores: u64,
+    cas_failures: u64,
+    contentions: u64,
+}
+
+impl Stats {
+    /// How many times the value was loaded.
+    pub fn loads(&self) -> u64 {
+        self.loads
+    }
+
+    /// How many times the value was written, by a store, a swap, a `fetch_*` operation or a successful compare-exchange.
+    pub fn stores(&self) -> u64 {
+        self.stores
+    }
+
+    /// How many compare-exchanges failed, as the value wasn't the expected one.
+    pub fn cas_failures(&self) -> u64 {
+        self.cas_failures
+    }
+
+    /// How many operations found the lock held by another thread.
+    ///
+    /// Busy locks are noticed while spinning, so they aren't counted with `ATOMIC_SHIM_SPIN_LIMIT=0`.
+    pub fn contentions(&self) -> u64 {
+        self.contentions
+    }
+}
+
+thread_local! {
+    /// Whether the last lock taken by this thread was busy.
+    static CONTENDED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Called when a lock is busy, and counted by the operation which takes it.
+pub(crate) fn contended() {
+    let _ = CONTENDED.try_with(|contended| contended.set(true));
+}
+
+fn take_contended() -> bool {
+    CONTENDED
+        .try_with(|contended| contended.replace(false))
+        .unwrap_or(false)
+}
+
+/// A storage, with the counts of the operations on it.
+#[derive(Default)]
+pub(crate) struct Counted<S> {
+    slot: S,
+    loads: AtomicUsize,
+    stores: AtomicUsize,
+    cas_failures: AtomicUsize,
+    contentions: AtomicUsize,
+}
+
+impl<S> Counted<S> {
+    pub(crate) fn stats(&self) -> Stats {
+        let count = |counter: &AtomicUsize| counter.load(Ordering::Relaxed) as u64;
+        Stats {
+            loads: count(&self.loads),
+            stores: count(&self.stores),
+            cas_failures: count(&self.cas_failures),
+            contentions: count(&self.contentions),
+        }
+    }
+
+    /// Counts an operation, and whether the lock it took was busy.
+    fn count(&self, counter: &AtomicUsize) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        if take_contended() {
+            self.contentions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T, S: Slot<T>> Slot<T> for Counted<S> {
+    const FOOTPRINT: usize = S::FOOTPRINT + 4 * mem::size_of::<AtomicUsize>();
+    const IS_LOCK_FREE: bool = S::IS_LOCK_FREE;
+
+    #[cfg_attr(feature = "ordering-audit", track_caller)]
+    fn new(value: T) -> Self {
+        Counted {
+            slot: S::new(value),
+            loads: AtomicUsize::new(0),
+            stores: AtomicUsize::new(0),
+            cas_failures: AtomicUsize::new(0),
+            contentions: AtomicUsize::new(0),
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        self.slot.is_lock_free()
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        self.slot.get_mut()
+    }
+
+    fn into_inner(self) -> T {
+        self.slot.into_inner()
+    }
+
+    fn load(&self, order: Ordering) -> T {
+        take_contended();
+        let value = self.slot.load(order);
+        self.count(&self.loads);
+        value
+    }
+
+    fn update<F: Fn(T) -> T>(&self, order: Ordering, f: F) -> T {
+        take_contended();
+        let prev = self.slot.update(order, f);
+        self.count(&self.stores);
+        prev
+    }
+
+    fn apply<F: Fn(T) -> T>(&self, op: &'static str, order: Ordering, f: F) -> T {
+        take_contended();
+        let prev = self.slot.apply(op, order, f);
+        self.count(&self.stores);
+        prev
+    }
+
+    fn compare_and_swap(&self, order: Ordering, current: T, new: T) -> T
+    where
+        T: Copy + PartialEq,
+    {
+        take_contended();
+        let prev = self.slot.compare_and_swap(order, current, new);
+        if prev == current {
+            self.count(&self.stores);
+        } else {
+            self.count(&self.cas_failures);
+        }
+        prev
+    }
+
+    fn snapshot(&self) -> T {
+        self.slot.snapshot()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.slot.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {
+        self.slot.forget_poisoning()
+    }
+
+    #[cfg(feature = "history")]
+    fn history(&self) -> Vec<crate::history::Operation<T>> {
+        self.slot.history()
+    }
+}
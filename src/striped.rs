@@ -0,0 +1,131 @@
+//! A global table of locks keyed by address, for operations on memory the shim doesn't own.
+//!
+//! Memory owned by C code, or mapped from a file, has no room for a lock next to the value,
+//! so every location hashes to one of a fixed number of stripes instead.
+
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Number of locks in the table.
+const STRIPES: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const UNLOCKED: Mutex<()> = Mutex::new(());
+
+static LOCKS: [Mutex<()>; STRIPES] = [UNLOCKED; STRIPES];
+
+/// Locks the stripe of `addr`.
+///
+/// The locks guard no data, so a poisoned lock is still safe to use.
+fn lock(addr: usize) -> MutexGuard<'static, ()> {
+    LOCKS[(addr >> 3) % STRIPES]
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A location accessed under its stripe lock, with the methods of the `std` atomics.
+///
+/// It ignores the Ordering arguments, as the access happens under the lock.
+pub(crate) struct Striped<T> {
+    ptr: *mut T,
+}
+
+impl<T: Copy + PartialEq> Striped<T> {
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes while the returned value is used, and only be accessed
+    /// through `Striped` during that time.
+    pub(crate) unsafe fn new(ptr: *mut T) -> Self {
+        Self { ptr }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let _guard = lock(self.ptr as usize);
+        unsafe { f(&mut *self.ptr) }
+    }
+
+    pub(crate) fn load(&self, _: Ordering) -> T {
+        self.with(|v| *v)
+    }
+
+    pub(crate) fn store(&self, value: T, _: Ordering) {
+        self.with(|v| *v = value)
+    }
+
+    pub(crate) fn swap(&self, value: T, _: Ordering) -> T {
+        self.with(|v| std::mem::replace(v, value))
+    }
+
+    pub(crate) fn compare_exchange(
+        &self,
+        current: T,
+        new: T,
+        _: Ordering,
+        _: Ordering,
+    ) -> Result<T, T> {
+        self.with(|v| {
+            let prev = *v;
+            if prev == current {
+                *v = new;
+                Ok(prev)
+            } else {
+                Err(prev)
+            }
+        })
+    }
+
+    pub(crate) fn compare_exchange_weak(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        self.compare_exchange(current, new, success, failure)
+    }
+
+    fn update(&self, f: impl FnOnce(T) -> T) -> T {
+        self.with(|v| {
+            let prev = *v;
+            *v = f(prev);
+            prev
+        })
+    }
+}
+
+macro_rules! striped_int {
+    ($($int:ident),*) => {
+        $(
+            impl Striped<$int> {
+                pub(crate) fn fetch_add(&self, val: $int, _: Ordering) -> $int {
+                    self.update(|prev| prev.wrapping_add(val))
+                }
+
+                pub(crate) fn fetch_sub(&self, val: $int, _: Ordering) -> $int {
+                    self.update(|prev| prev.wrapping_sub(val))
+                }
+
+                pub(crate) fn fetch_and(&self, val: $int, _: Ordering) -> $int {
+                    self.update(|prev| prev & val)
+                }
+
+                pub(crate) fn fetch_nand(&self, val: $int, _: Ordering) -> $int {
+                    self.update(|prev| !(prev & val))
+                }
+
+                pub(crate) fn fetch_or(&self, val: $int, _: Ordering) -> $int {
+                    self.update(|prev| prev | val)
+                }
+
+                pub(crate) fn fetch_xor(&self, val: $int, _: Ordering) -> $int {
+                    self.update(|prev| prev ^ val)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(atomic_shim_fallback_u64)]
+striped_int!(u64);
+#[cfg(atomic_shim_fallback_i64)]
+striped_int!(i64);
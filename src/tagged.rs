@@ -0,0 +1,182 @@
+//! A pointer with a generation tag, swapped together, for lock-free structures on 32-bit targets.
+//!
+//! A compare-exchange of a bare pointer succeeds when a node was freed and another one allocated at the same address,
+//! the ABA problem, which corrupts lock-free stacks and free lists. Bumping a tag with every change of the pointer
+//! makes the stale exchange fail. On 32-bit targets, such as MIPS and PowerPC routers, the pointer and the tag fit in
+//! the 64-bit atomic, the shim where the target lacks it. On 64-bit targets, they are an [`AtomicU64Pair`].
+
+use crate::Backoff;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
+
+#[cfg(target_pointer_width = "64")]
+use crate::AtomicU64Pair;
+
+#[cfg(not(target_pointer_width = "64"))]
+type Storage = crate::AtomicU64;
+
+#[cfg(target_pointer_width = "64")]
+type Storage = AtomicU64Pair;
+
+/// A pointer and the generation tag bumped by every change of it.
+///
+/// The stores, swaps and compare-exchanges bump the tag, wrapping around after `u32::MAX`, so a compare-exchange
+/// from a stale load fails even if the pointer came back to the same address. The orderings are those of
+/// `AtomicPtr`, and are upgraded to `SeqCst` on 64-bit targets, where the pointer and the tag are an `AtomicU64Pair`.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicTaggedPtr;
+/// use std::sync::atomic::Ordering;
+///
+/// let (mut a, mut b) = (1, 2);
+/// let head = AtomicTaggedPtr::new(&mut a as *mut i32);
+/// let stale = head.load(Ordering::Acquire);
+///
+/// // another thread moves the head away and back
+/// head.store(&mut b, Ordering::Release);
+/// head.store(&mut a, Ordering::Release);
+///
+/// // the pointer is the same, but the tag tells the change apart
+/// assert_eq!(head.load(Ordering::Acquire), (&mut a as *mut i32, 2));
+/// assert!(head
+///     .compare_exchange(stale, &mut b, Ordering::AcqRel, Ordering::Acquire)
+///     .is_err());
+/// ```
+pub struct AtomicTaggedPtr<T> {
+    value: Storage,
+    _marker: PhantomData<*mut T>,
+}
+
+// Like `AtomicPtr`, the atomic only shares the address, not the pointee.
+unsafe impl<T> Send for AtomicTaggedPtr<T> {}
+unsafe impl<T> Sync for AtomicTaggedPtr<T> {}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn pack(ptr: usize, tag: u32) -> u64 {
+    (u64::from(tag) << 32) | ptr as u64
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn unpack(value: u64) -> (usize, u32) {
+    (value as u32 as usize, (value >> 32) as u32)
+}
+
+#[cfg(target_pointer_width = "64")]
+fn pack(ptr: usize, tag: u32) -> (u64, u64) {
+    (ptr as u64, u64::from(tag))
+}
+
+#[cfg(target_pointer_width = "64")]
+fn unpack(value: (u64, u64)) -> (usize, u32) {
+    (value.0 as usize, value.1 as u32)
+}
+
+impl<T> AtomicTaggedPtr<T> {
+    /// Creates an atomic holding `ptr`, with the tag at 0.
+    pub fn new(ptr: *mut T) -> Self {
+        Self::with_tag(ptr, 0)
+    }
+
+    /// Creates an atomic holding `ptr` and `tag`.
+    pub fn with_tag(ptr: *mut T, tag: u32) -> Self {
+        Self {
+            value: Storage::new(pack(ptr as usize, tag)),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the pointer and its tag.
+    pub fn load(&self, order: Ordering) -> (*mut T, u32) {
+        #[cfg(not(target_pointer_width = "64"))]
+        let value = self.value.load(order);
+        #[cfg(target_pointer_width = "64")]
+        let value = {
+            let _ = order;
+            self.value.load()
+        };
+        let (ptr, tag) = unpack(value);
+        (ptr as *mut T, tag)
+    }
+
+    /// Stores `ptr`, bumping the tag.
+    pub fn store(&self, ptr: *mut T, order: Ordering) {
+        self.swap(ptr, order);
+    }
+
+    /// Stores `ptr`, bumping the tag, and returns the previous pointer and tag.
+    pub fn swap(&self, ptr: *mut T, order: Ordering) -> (*mut T, u32) {
+        let load = match order {
+            Ordering::Release => Ordering::Relaxed,
+            Ordering::AcqRel => Ordering::Acquire,
+            order => order,
+        };
+        let mut current = self.load(load);
+        let backoff = Backoff::new();
+        loop {
+            match self.compare_exchange(current, ptr, order, load) {
+                Ok(prev) => return prev,
+                Err(actual) => {
+                    current = actual;
+                    backoff.spin();
+                }
+            }
+        }
+    }
+
+    /// Stores `new` if the atomic holds the pointer and the tag of `current`, bumping the tag,
+    /// and returns the previous pointer and tag, as `Ok` if it was stored, and as `Err` if it wasn't.
+    pub fn compare_exchange(
+        &self,
+        current: (*mut T, u32),
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(*mut T, u32), (*mut T, u32)> {
+        let (ptr, tag) = current;
+        let current = pack(ptr as usize, tag);
+        let new = pack(new as usize, tag.wrapping_add(1));
+        #[cfg(not(target_pointer_width = "64"))]
+        let result = self.value.compare_exchange(current, new, success, failure);
+        #[cfg(target_pointer_width = "64")]
+        let result = {
+            let _ = (success, failure);
+            self.value.compare_exchange(current, new)
+        };
+        let pair = |value| {
+            let (ptr, tag) = unpack(value);
+            (ptr as *mut T, tag)
+        };
+        result.map(pair).map_err(pair)
+    }
+
+    /// Consumes the atomic, returning the pointer and its tag.
+    pub fn into_inner(self) -> (*mut T, u32) {
+        let (ptr, tag) = unpack(self.value.into_inner());
+        (ptr as *mut T, tag)
+    }
+}
+
+impl<T> Default for AtomicTaggedPtr<T> {
+    fn default() -> Self {
+        Self::new(std::ptr::null_mut())
+    }
+}
+
+impl<T> From<*mut T> for AtomicTaggedPtr<T> {
+    fn from(ptr: *mut T) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<T> fmt::Debug for AtomicTaggedPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ptr, tag) = self.load(Ordering::SeqCst);
+        f.debug_struct("AtomicTaggedPtr")
+            .field("ptr", &ptr)
+            .field("tag", &tag)
+            .finish()
+    }
+}
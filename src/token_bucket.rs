@@ -0,0 +1,133 @@
+//! A token bucket in a single 64-bit atomic, for admission control without a lock on native targets.
+//!
+//! The bucket holds the tokens left, and the time of its last refill, in the same atomic, so taking tokens
+//! refills the bucket for the time passed and takes them in one compare-exchange, and two threads can't both
+//! spend the same refill. The time is counted in intervals from the creation of the bucket, so the fraction of
+//! an interval passed since the last refill isn't lost.
+
+use crate::duration::nanos;
+use crate::{AtomicU64, Backoff};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+/// The bits of the packed atomic holding the tokens, the others holding the interval of the last refill.
+const TOKEN_BITS: u32 = 24;
+
+const TOKEN_MASK: u64 = (1 << TOKEN_BITS) - 1;
+
+/// A rate limiter which holds up to a capacity of tokens, and gets one back every interval.
+///
+/// The bucket starts full. It holds up to `2^24 - 1` tokens, and counts the intervals in 40 bits, so with an
+/// interval of a microsecond, a bucket left untouched for more than 6 days may not be refilled up to its capacity.
+/// The operations are `Relaxed`, as the tokens don't publish data.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::TokenBucket;
+/// use std::time::Duration;
+///
+/// // bursts of up to 10 requests, then one every 100 milliseconds
+/// let limiter = TokenBucket::new(10, Duration::from_millis(100));
+/// assert!(limiter.try_acquire(10));
+/// assert!(!limiter.try_acquire(1));
+/// ```
+pub struct TokenBucket {
+    /// The interval of the last refill, in the high bits, and the tokens, in the low ones.
+    state: AtomicU64,
+    capacity: u32,
+    /// The nanoseconds between two tokens.
+    interval: u64,
+    start: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket of `capacity` tokens, getting one back every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` doesn't fit in 24 bits, or if `interval` is zero.
+    pub fn new(capacity: u32, interval: Duration) -> Self {
+        assert!(
+            u64::from(capacity) <= TOKEN_MASK,
+            "a TokenBucket holds up to 2^24 - 1 tokens"
+        );
+        assert!(
+            !interval.is_zero(),
+            "the interval of a TokenBucket can't be zero"
+        );
+        Self {
+            state: AtomicU64::new(u64::from(capacity)),
+            capacity,
+            interval: nanos(interval),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the most tokens the bucket holds.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Returns the intervals passed since the creation of the bucket.
+    fn now(&self) -> u64 {
+        nanos(self.start.elapsed()) / self.interval
+    }
+
+    /// Returns the interval of the refill, and the tokens, of the packed `state` once refilled at the interval `now`.
+    fn refill(&self, state: u64, now: u64) -> (u64, u64) {
+        const INTERVAL_MASK: u64 = u64::MAX >> TOKEN_BITS;
+        let last = state >> TOKEN_BITS;
+        let passed = now.wrapping_sub(last) & INTERVAL_MASK;
+        if passed > INTERVAL_MASK / 2 {
+            // Another thread refilled the bucket at a later interval than the one this thread read.
+            return (last, state & TOKEN_MASK);
+        }
+        let tokens = (state & TOKEN_MASK)
+            .saturating_add(passed)
+            .min(u64::from(self.capacity));
+        (now, tokens)
+    }
+
+    /// Takes `n` tokens if the bucket holds them, returning whether it did.
+    pub fn try_acquire(&self, n: u32) -> bool {
+        let now = self.now();
+        let mut state = self.state.load(Ordering::Relaxed);
+        let backoff = Backoff::new();
+        loop {
+            let (refill, tokens) = self.refill(state, now);
+            if tokens < u64::from(n) {
+                return false;
+            }
+            let next = (refill << TOKEN_BITS) | (tokens - u64::from(n));
+            match self.state.compare_exchange_weak(
+                state,
+                next,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => {
+                    state = actual;
+                    backoff.spin();
+                }
+            }
+        }
+    }
+
+    /// Returns the tokens the bucket holds now.
+    pub fn available(&self) -> u32 {
+        self.refill(self.state.load(Ordering::Relaxed), self.now())
+            .1 as u32
+    }
+}
+
+impl fmt::Debug for TokenBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenBucket")
+            .field("available", &self.available())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
@@ -0,0 +1,325 @@
+use crate::atomic::{
+    AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32, AtomicU64,
+    AtomicU8, AtomicUsize, Ordering,
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An atomic integer of any width, implemented by every integer type of the [`atomic`](crate::atomic) facade.
+///
+/// It allows generic data structures to be written once, regardless of the width or whether the target uses the shim.
+/// The trait is sealed, and can't be implemented outside of this crate.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::AtomicInt;
+/// use atomic_shim::atomic::{AtomicU32, AtomicU64, Ordering};
+///
+/// fn bump<T, A: AtomicInt<T>>(atomic: &A, by: T) -> T {
+///     atomic.fetch_add(by, Ordering::SeqCst)
+/// }
+///
+/// assert_eq!(bump(&AtomicU32::new(1), 2), 1);
+/// assert_eq!(bump(&AtomicU64::new(3), 4), 3);
+/// ```
+pub trait AtomicInt<T>: sealed::Sealed + Send + Sync {
+    /// Creates a new atomic integer.
+    fn new(v: T) -> Self
+    where
+        Self: Sized;
+
+    /// Returns a mutable reference to the underlying integer.
+    fn get_mut(&mut self) -> &mut T;
+
+    /// Consumes the atomic and returns the contained value.
+    fn into_inner(self) -> T
+    where
+        Self: Sized;
+
+    /// Loads a value from the atomic integer.
+    fn load(&self, order: Ordering) -> T;
+
+    /// Stores a value into the atomic integer.
+    fn store(&self, value: T, order: Ordering);
+
+    /// Stores a value into the atomic integer, returning the previous value.
+    fn swap(&self, value: T, order: Ordering) -> T;
+
+    /// Stores a value into the atomic integer if the current value is the same as the current value.
+    fn compare_exchange(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T>;
+
+    /// Stores a value into the atomic integer if the current value is the same as the current value,
+    /// and is allowed to spuriously fail.
+    fn compare_exchange_weak(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T>;
+
+    /// Adds to the current value, returning the previous value.
+    fn fetch_add(&self, val: T, order: Ordering) -> T;
+
+    /// Subtracts from the current value, returning the previous value.
+    fn fetch_sub(&self, val: T, order: Ordering) -> T;
+
+    /// Bitwise "and" with the current value, returning the previous value.
+    fn fetch_and(&self, val: T, order: Ordering) -> T;
+
+    /// Bitwise "nand" with the current value, returning the previous value.
+    fn fetch_nand(&self, val: T, order: Ordering) -> T;
+
+    /// Bitwise "or" with the current value, returning the previous value.
+    fn fetch_or(&self, val: T, order: Ordering) -> T;
+
+    /// Bitwise "xor" with the current value, returning the previous value.
+    fn fetch_xor(&self, val: T, order: Ordering) -> T;
+}
+
+macro_rules! atomic_int {
+    ($($atomic:ident => $int:ident),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $atomic {}
+
+            impl AtomicInt<$int> for $atomic {
+                fn new(v: $int) -> Self {
+                    $atomic::new(v)
+                }
+
+                fn get_mut(&mut self) -> &mut $int {
+                    $atomic::get_mut(self)
+                }
+
+                fn into_inner(self) -> $int {
+                    $atomic::into_inner(self)
+                }
+
+                fn load(&self, order: Ordering) -> $int {
+                    $atomic::load(self, order)
+                }
+
+                fn store(&self, value: $int, order: Ordering) {
+                    $atomic::store(self, value, order)
+                }
+
+                fn swap(&self, value: $int, order: Ordering) -> $int {
+                    $atomic::swap(self, value, order)
+                }
+
+                fn compare_exchange(
+                    &self,
+                    current: $int,
+                    new: $int,
+                    success: Ordering,
+                    failure: Ordering,
+                ) -> Result<$int, $int> {
+                    $atomic::compare_exchange(self, current, new, success, failure)
+                }
+
+                fn compare_exchange_weak(
+                    &self,
+                    current: $int,
+                    new: $int,
+                    success: Ordering,
+                    failure: Ordering,
+                ) -> Result<$int, $int> {
+                    $atomic::compare_exchange_weak(self, current, new, success, failure)
+                }
+
+                fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                    $atomic::fetch_add(self, val, order)
+                }
+
+                fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                    $atomic::fetch_sub(self, val, order)
+                }
+
+                fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                    $atomic::fetch_and(self, val, order)
+                }
+
+                fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                    $atomic::fetch_nand(self, val, order)
+                }
+
+                fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                    $atomic::fetch_or(self, val, order)
+                }
+
+                fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                    $atomic::fetch_xor(self, val, order)
+                }
+            }
+        )*
+    };
+}
+
+atomic_int! {
+    AtomicU8 => u8,
+    AtomicI8 => i8,
+    AtomicU16 => u16,
+    AtomicI16 => i16,
+    AtomicU32 => u32,
+    AtomicI32 => i32,
+    AtomicU64 => u64,
+    AtomicI64 => i64,
+    AtomicUsize => usize,
+    AtomicIsize => isize,
+}
+
+/// An object-safe view of a 64-bit unsigned atomic, implemented by both `atomic_shim::AtomicU64`
+/// and `std::sync::atomic::AtomicU64`.
+///
+/// It allows holding counters as `Box<dyn AnyAtomicU64>` or `&dyn AnyAtomicU64`,
+/// regardless of which implementation, or which feature set, created them.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::{AnyAtomicU64, AtomicU64};
+/// use std::sync::atomic::Ordering;
+///
+/// let counters: Vec<Box<dyn AnyAtomicU64>> = vec![
+///     Box::new(AtomicU64::new(1)),
+///     Box::new(std::sync::atomic::AtomicU64::new(2)),
+/// ];
+///
+/// let total: u64 = counters.iter().map(|c| c.load(Ordering::SeqCst)).sum();
+/// assert_eq!(total, 3);
+///
+/// // as decided at runtime on ARM Linux
+/// assert_eq!(counters[0].is_lock_free(), AtomicU64::new(0).is_lock_free());
+/// assert!(counters[1].is_lock_free());
+/// ```
+pub trait AnyAtomicU64: Send + Sync {
+    /// Returns whether the operations on this atomic are lock-free.
+    fn is_lock_free(&self) -> bool;
+
+    /// Loads a value from the atomic integer.
+    fn load(&self, order: Ordering) -> u64;
+
+    /// Stores a value into the atomic integer.
+    fn store(&self, value: u64, order: Ordering);
+
+    /// Stores a value into the atomic integer, returning the previous value.
+    fn swap(&self, value: u64, order: Ordering) -> u64;
+
+    /// Stores a value into the atomic integer if the current value is the same as the current value.
+    fn compare_exchange(
+        &self,
+        current: u64,
+        new: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64>;
+
+    /// Stores a value into the atomic integer if the current value is the same as the current value,
+    /// and is allowed to spuriously fail.
+    fn compare_exchange_weak(
+        &self,
+        current: u64,
+        new: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64>;
+
+    /// Adds to the current value, returning the previous value.
+    fn fetch_add(&self, val: u64, order: Ordering) -> u64;
+
+    /// Subtracts from the current value, returning the previous value.
+    fn fetch_sub(&self, val: u64, order: Ordering) -> u64;
+
+    /// Bitwise "and" with the current value, returning the previous value.
+    fn fetch_and(&self, val: u64, order: Ordering) -> u64;
+
+    /// Bitwise "nand" with the current value, returning the previous value.
+    fn fetch_nand(&self, val: u64, order: Ordering) -> u64;
+
+    /// Bitwise "or" with the current value, returning the previous value.
+    fn fetch_or(&self, val: u64, order: Ordering) -> u64;
+
+    /// Bitwise "xor" with the current value, returning the previous value.
+    fn fetch_xor(&self, val: u64, order: Ordering) -> u64;
+}
+
+macro_rules! any_atomic_u64 {
+    ($atomic:ty, $lock_free:expr) => {
+        impl AnyAtomicU64 for $atomic {
+            fn is_lock_free(&self) -> bool {
+                $lock_free(self)
+            }
+
+            fn load(&self, order: Ordering) -> u64 {
+                <$atomic>::load(self, order)
+            }
+
+            fn store(&self, value: u64, order: Ordering) {
+                <$atomic>::store(self, value, order)
+            }
+
+            fn swap(&self, value: u64, order: Ordering) -> u64 {
+                <$atomic>::swap(self, value, order)
+            }
+
+            fn compare_exchange(
+                &self,
+                current: u64,
+                new: u64,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<u64, u64> {
+                <$atomic>::compare_exchange(self, current, new, success, failure)
+            }
+
+            fn compare_exchange_weak(
+                &self,
+                current: u64,
+                new: u64,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<u64, u64> {
+                <$atomic>::compare_exchange_weak(self, current, new, success, failure)
+            }
+
+            fn fetch_add(&self, val: u64, order: Ordering) -> u64 {
+                <$atomic>::fetch_add(self, val, order)
+            }
+
+            fn fetch_sub(&self, val: u64, order: Ordering) -> u64 {
+                <$atomic>::fetch_sub(self, val, order)
+            }
+
+            fn fetch_and(&self, val: u64, order: Ordering) -> u64 {
+                <$atomic>::fetch_and(self, val, order)
+            }
+
+            fn fetch_nand(&self, val: u64, order: Ordering) -> u64 {
+                <$atomic>::fetch_nand(self, val, order)
+            }
+
+            fn fetch_or(&self, val: u64, order: Ordering) -> u64 {
+                <$atomic>::fetch_or(self, val, order)
+            }
+
+            fn fetch_xor(&self, val: u64, order: Ordering) -> u64 {
+                <$atomic>::fetch_xor(self, val, order)
+            }
+        }
+    };
+}
+
+// ARM Linux only decides at runtime whether the shim is lock-free.
+any_atomic_u64!(AtomicU64, AtomicU64::is_lock_free);
+
+#[cfg(target_has_atomic = "64")]
+any_atomic_u64!(std::sync::atomic::AtomicU64, |_| true);
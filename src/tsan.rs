@@ -0,0 +1,26 @@
+//! ThreadSanitizer annotations of the shim's lock.
+//!
+//! `std` is not instrumented unless it is rebuilt with `-Zbuild-std`, and its locks are built on futexes
+//! on Linux, so ThreadSanitizer doesn't see the shim's lock. Code publishing data through a shimmed atomic
+//! then looks like a data race. These annotations tell it that every operation synchronizes with the previous ones
+//! on the same atomic, as the lock does.
+//!
+//! The functions are provided by the ThreadSanitizer runtime, so `features = ["tsan"]` must only be enabled
+//! when building with `-Zsanitizer=thread`.
+
+use std::os::raw::c_void;
+
+extern "C" {
+    fn __tsan_acquire(addr: *mut c_void);
+    fn __tsan_release(addr: *mut c_void);
+}
+
+/// Marks that the current thread synchronizes with the last release of `addr`.
+pub(crate) fn acquire<T>(addr: &T) {
+    unsafe { __tsan_acquire(addr as *const T as *mut c_void) }
+}
+
+/// Marks that the current thread's accesses happen before the next acquire of `addr`.
+pub(crate) fn release<T>(addr: &T) {
+    unsafe { __tsan_release(addr as *const T as *mut c_void) }
+}
@@ -0,0 +1,145 @@
+//! Storage of the 64-bit shims, whose loads never take the lock.
+//!
+//! The value is split in two 32-bit atomics, which writers update under a lock, bumping a version before and
+//! after. Readers read the version, the halves and the version again, and retry when a write overlapped them,
+//! so readers never wait for the lock behind a burst of writers. It needs 32-bit atomics, which every target with
+//! `std` has, besides WebAssembly without the `atomics` feature.
+
+use crate::shim::{acquire, diagnostics, unpoisoned, Bits64, Slot};
+use std::hint;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::atomic::{fence, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+fn split(bits: u64) -> [u32; 2] {
+    let bytes = bits.to_ne_bytes();
+    [
+        u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+    ]
+}
+
+fn join(halves: [u32; 2]) -> u64 {
+    let (low, high) = (halves[0].to_ne_bytes(), halves[1].to_ne_bytes());
+    u64::from_ne_bytes([
+        low[0], low[1], low[2], low[3], high[0], high[1], high[2], high[3],
+    ])
+}
+
+/// Storage of a 64-bit shim, read with a sequence lock.
+#[repr(C, align(8))]
+pub(crate) struct Versioned<T> {
+    /// The value, laid out like a `u64`, so `get_mut` can borrow it as one.
+    halves: [AtomicU32; 2],
+    /// Odd while a write is in progress.
+    version: AtomicU32,
+    lock: Mutex<()>,
+    int: PhantomData<T>,
+}
+
+impl<T: Bits64> Versioned<T> {
+    fn read(&self) -> T {
+        loop {
+            let version = self.version.load(Ordering::SeqCst);
+            if version & 1 == 0 {
+                let halves = [
+                    self.halves[0].load(Ordering::Relaxed),
+                    self.halves[1].load(Ordering::Relaxed),
+                ];
+                fence(Ordering::Acquire);
+                if self.version.load(Ordering::Relaxed) == version {
+                    return T::from_bits(join(halves));
+                }
+            }
+            hint::spin_loop();
+        }
+    }
+}
+
+impl<T: Bits64> Default for Versioned<T> {
+    fn default() -> Self {
+        Slot::new(T::from_bits(0))
+    }
+}
+
+impl<T: Bits64> Slot<T> for Versioned<T> {
+    /// The value, the version, and the lock of the writers, padded to 8 bytes.
+    const FOOTPRINT: usize = 16 + mem::size_of::<Mutex<()>>();
+    const IS_LOCK_FREE: bool = false;
+
+    fn new(value: T) -> Self {
+        let [low, high] = split(value.to_bits());
+        Versioned {
+            halves: [AtomicU32::new(low), AtomicU32::new(high)],
+            version: AtomicU32::new(0),
+            lock: Mutex::new(()),
+            int: PhantomData,
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        false
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        if self.lock.is_poisoned() {
+            diagnostics::poisoned(self)
+        }
+        unsafe { &mut *(self.halves.as_mut_ptr() as *mut T) }
+    }
+
+    fn into_inner(self) -> T {
+        if self.lock.is_poisoned() {
+            diagnostics::poisoned_into_inner(&self)
+        }
+        let [low, high] = self.halves;
+        T::from_bits(join([low.into_inner(), high.into_inner()]))
+    }
+
+    fn load(&self, _: Ordering) -> T {
+        if self.lock.is_poisoned() {
+            diagnostics::poisoned(self)
+        }
+        self.read()
+    }
+
+    fn update<F: Fn(T) -> T>(&self, _: Ordering, f: F) -> T {
+        let lock = unpoisoned(acquire(|| self.lock.try_lock(), || self.lock.lock()), self);
+        let _witness = diagnostics::Witness::new(self);
+        let section = diagnostics::Section::start();
+        let version = self.version.load(Ordering::Relaxed);
+        let prev = join([
+            self.halves[0].load(Ordering::Relaxed),
+            self.halves[1].load(Ordering::Relaxed),
+        ]);
+        // Computed before the write starts, so a panic can't leave the version odd.
+        let [low, high] = split(f(T::from_bits(prev)).to_bits());
+
+        self.version
+            .store(version.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.halves[0].store(low, Ordering::Relaxed);
+        self.halves[1].store(high, Ordering::Relaxed);
+        self.version
+            .store(version.wrapping_add(2), Ordering::SeqCst);
+        let slow = section.end();
+        drop(lock);
+        slow.report(self);
+        T::from_bits(prev)
+    }
+
+    fn snapshot(&self) -> T {
+        self.read()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.lock.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {
+        if self.lock.is_poisoned() {
+            diagnostics::forget(self)
+        }
+    }
+}
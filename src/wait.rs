@@ -0,0 +1,387 @@
+//! Blocking until a 64-bit atomic changes, like C++20's `atomic::wait` and `atomic::notify_one`.
+//!
+//! A thread spinning until another one stores a value burns the CPU, and the battery, the whole time.
+//! Waiting threads sleep instead, on a condition variable of a global table keyed by the atomic's address,
+//! which works the same for the native atomics and the shim, as Linux futexes only take 32-bit values.
+//! Atomics sharing an entry of the table wake each other, and check their value again.
+//!
+//! With the `async` feature, tasks wait with a future instead, whose waker is kept in the same entry,
+//! so an async runtime doesn't dedicate a thread to waiting.
+//!
+//! Waiting until a predicate holds polls the value instead, as the stores making it true may not be notified.
+
+#[cfg(all(feature = "weak-memory", not(loom)))]
+use crate::weak_memory::synchronize;
+use crate::{AtomicI64, AtomicU64, Backoff};
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::Waker;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The simulation of weak memory doesn't see the lock ordering the notifier's store before the check.
+#[cfg(not(all(feature = "weak-memory", not(loom))))]
+fn synchronize() {}
+
+/// Number of entries in the table.
+const BUCKETS: usize = 64;
+
+/// The threads and tasks waiting on the atomics of an entry.
+struct Bucket {
+    /// The number of waiting threads and tasks, so notifying without waiters doesn't take the lock.
+    waiters: AtomicUsize,
+    /// The wakers of the waiting tasks, with the id of their future.
+    wakers: Mutex<Vec<(usize, Waker)>>,
+    condvar: Condvar,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Bucket = Bucket {
+    waiters: AtomicUsize::new(0),
+    wakers: Mutex::new(Vec::new()),
+    condvar: Condvar::new(),
+};
+
+static TABLE: [Bucket; BUCKETS] = [EMPTY; BUCKETS];
+
+fn bucket(addr: usize) -> &'static Bucket {
+    &TABLE[(addr >> 3) % BUCKETS]
+}
+
+/// Blocks while `unchanged` returns true, checking it again whenever `addr` is notified.
+pub(crate) fn wait_while(addr: usize, unchanged: impl Fn() -> bool) {
+    bucket(addr).wait(unchanged)
+}
+
+/// Wakes the threads and tasks waiting on `addr`, and the ones sharing its entry.
+pub(crate) fn notify(addr: usize) {
+    bucket(addr).notify()
+}
+
+impl Bucket {
+    /// Locks the wakers, which stay valid if a waker panicked while they were locked.
+    fn lock(&self) -> MutexGuard<'_, Vec<(usize, Waker)>> {
+        self.wakers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Counts a new waiter, before it checks the value.
+    fn enter(&self) {
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        // Pairs with the fence of `notify`: either the notifier sees this waiter, or this waiter sees the new value.
+        fence(Ordering::SeqCst);
+    }
+
+    fn leave(&self) {
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Blocks while `unchanged` returns true, checking it again on every notification.
+    fn wait(&self, unchanged: impl Fn() -> bool) {
+        self.enter();
+        let mut guard = self.lock();
+        synchronize();
+        while unchanged() {
+            guard = self
+                .condvar
+                .wait(guard)
+                .unwrap_or_else(PoisonError::into_inner);
+            synchronize();
+        }
+        drop(guard);
+        self.leave();
+    }
+
+    /// Wakes every thread and task waiting on the entry, as they may be waiting on different atomics.
+    fn notify(&self) {
+        fence(Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) != 0 {
+            // Taking the lock waits for a waiter between its check and its sleep, so the wakeup isn't lost.
+            let wakers = std::mem::take(&mut *self.lock());
+            self.condvar.notify_all();
+            for (_, waker) in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
+
+macro_rules! wait {
+    ($atomic:ident, $int:ident) => {
+        impl $atomic {
+            /// Blocks the thread until the value isn't `expected` anymore, and a `notify_*` method was called.
+            ///
+            /// Returns immediately if the value already differs. The value is loaded with `order`, which can't be
+            /// `Release` or `AcqRel`, like `load`. A store waking the thread must be followed by
+            /// [`notify_one`](Self::notify_one) or [`notify_all`](Self::notify_all).
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            /// use std::sync::Arc;
+            /// use std::thread;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let lock = Arc::new(", stringify!($atomic), "::new(1));")]
+            /// let holder = {
+            ///     let lock = lock.clone();
+            ///     thread::spawn(move || {
+            ///         lock.store(0, Ordering::Release);
+            ///         lock.notify_all();
+            ///     })
+            /// };
+            ///
+            /// lock.wait(1, Ordering::Acquire);
+            /// assert_eq!(lock.load(Ordering::Acquire), 0);
+            /// holder.join().unwrap();
+            /// ```
+            pub fn wait(&self, expected: $int, order: Ordering) {
+                wait_while(self as *const Self as usize, || {
+                    self.load(order) == expected
+                });
+            }
+
+            /// Waits until the value isn't `expected` anymore, and a `notify_*` method was called, like [`wait`](Self::wait),
+            /// without blocking the thread.
+            ///
+            /// The future resolves immediately if the value already differs.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::future::Future;
+            /// use std::pin::pin;
+            /// use std::sync::atomic::Ordering;
+            /// use std::sync::Arc;
+            /// use std::task::{Context, Poll, Wake, Waker};
+            /// use std::thread::{self, Thread};
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            /// struct Unpark(Thread);
+            ///
+            /// impl Wake for Unpark {
+            ///     fn wake(self: Arc<Self>) {
+            ///         self.0.unpark();
+            ///     }
+            /// }
+            ///
+            /// fn block_on<F: Future>(future: F) -> F::Output {
+            ///     let mut future = pin!(future);
+            ///     let waker = Waker::from(Arc::new(Unpark(thread::current())));
+            ///     loop {
+            ///         match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            ///             Poll::Ready(output) => return output,
+            ///             Poll::Pending => thread::park(),
+            ///         }
+            ///     }
+            /// }
+            ///
+            #[doc = concat!("let jobs = Arc::new(", stringify!($atomic), "::new(0));")]
+            /// let producer = {
+            ///     let jobs = jobs.clone();
+            ///     thread::spawn(move || {
+            ///         jobs.store(3, Ordering::Release);
+            ///         jobs.notify_all();
+            ///     })
+            /// };
+            ///
+            /// block_on(jobs.wait_async(0, Ordering::Acquire));
+            /// assert_eq!(jobs.load(Ordering::Acquire), 3);
+            /// producer.join().unwrap();
+            /// ```
+            #[cfg(feature = "async")]
+            pub fn wait_async(&self, expected: $int, order: Ordering) -> WaitFuture<'_> {
+                WaitFuture {
+                    atomic: self,
+                    bucket: bucket(self as *const Self as usize),
+                    expected: expected as u64,
+                    order,
+                    id: None,
+                    waiting: false,
+                }
+            }
+
+            /// Blocks the thread until `pred` returns true for the value, and returns that value.
+            ///
+            /// Unlike [`wait`](Self::wait), it needs no notification: the value is loaded with `Ordering::Acquire`
+            /// between the waits of a [`Backoff`](crate::Backoff), which ends up sleeping 64 microseconds at a time,
+            /// instead of spinning on the CPU.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::sync::atomic::Ordering;
+            /// use std::sync::Arc;
+            /// use std::thread;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let done = Arc::new(", stringify!($atomic), "::new(0));")]
+            /// let workers: Vec<_> = (0..4)
+            ///     .map(|_| {
+            ///         let done = done.clone();
+            ///         thread::spawn(move || done.fetch_add(1, Ordering::Release))
+            ///     })
+            ///     .collect();
+            ///
+            /// assert_eq!(done.wait_until(|done| done == 4), 4);
+            /// # for worker in workers {
+            /// #     worker.join().unwrap();
+            /// # }
+            /// ```
+            pub fn wait_until(&self, pred: impl Fn($int) -> bool) -> $int {
+                let backoff = Backoff::new();
+                loop {
+                    let value = self.load(Ordering::Acquire);
+                    if pred(value) {
+                        return value;
+                    }
+                    backoff.snooze();
+                }
+            }
+
+            /// Blocks the thread until `pred` returns true for the value, like [`wait_until`](Self::wait_until),
+            /// or until `timeout` elapsed.
+            ///
+            /// Returns the value for which `pred` returned true, or `None` on timeout.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use std::time::Duration;
+            #[doc = concat!("use atomic_shim::", stringify!($atomic), ";")]
+            ///
+            #[doc = concat!("let ready = ", stringify!($atomic), "::new(0);")]
+            /// assert_eq!(ready.wait_until_timeout(|ready| ready != 0, Duration::from_millis(10)), None);
+            /// ```
+            pub fn wait_until_timeout(
+                &self,
+                pred: impl Fn($int) -> bool,
+                timeout: Duration,
+            ) -> Option<$int> {
+                let start = Instant::now();
+                let backoff = Backoff::new();
+                loop {
+                    let value = self.load(Ordering::Acquire);
+                    if pred(value) {
+                        return Some(value);
+                    }
+                    if start.elapsed() >= timeout {
+                        return None;
+                    }
+                    backoff.snooze();
+                }
+            }
+
+            /// Wakes a thread or task waiting on the atomic, if any.
+            ///
+            /// Unlike C++'s `notify_one`, it may wake more than one waiter: the waiters sleep on a condition
+            /// variable shared by the entry of the table, so it wakes every thread and task waiting on the atomic,
+            /// and on the other atomics of the entry. The ones whose value didn't change wait again.
+            pub fn notify_one(&self) {
+                self.notify_all();
+            }
+
+            /// Wakes every thread and task waiting on the atomic.
+            pub fn notify_all(&self) {
+                notify(self as *const Self as usize);
+            }
+        }
+    };
+}
+
+wait!(AtomicU64, u64);
+wait!(AtomicI64, i64);
+
+/// The atomics a [`WaitFuture`] waits on, which it loads through a pointer, as they have different types.
+#[cfg(feature = "async")]
+trait LoadBits: Sync {
+    /// Loads the value, as the bits of a `u64`.
+    fn load_bits(&self, order: Ordering) -> u64;
+}
+
+#[cfg(feature = "async")]
+impl LoadBits for AtomicU64 {
+    fn load_bits(&self, order: Ordering) -> u64 {
+        self.load(order)
+    }
+}
+
+#[cfg(feature = "async")]
+impl LoadBits for AtomicI64 {
+    fn load_bits(&self, order: Ordering) -> u64 {
+        self.load(order) as u64
+    }
+}
+
+/// The id of the next [`WaitFuture`] registering its waker.
+#[cfg(feature = "async")]
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The future returned by `wait_async`, which resolves once the value of the atomic changed.
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitFuture<'a> {
+    atomic: &'a dyn LoadBits,
+    bucket: &'static Bucket,
+    expected: u64,
+    order: Ordering,
+    /// The id of its waker in the entry, once it registered one.
+    id: Option<usize>,
+    /// Whether the future is counted in the waiters of its entry.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl WaitFuture<'_> {
+    /// Removes the waker of the future from the entry, which would otherwise keep it until the next notification.
+    fn unregister(&self, wakers: &mut Vec<(usize, Waker)>) {
+        if let Some(id) = self.id {
+            wakers.retain(|(other, _)| *other != id);
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Future for WaitFuture<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let bucket = self.bucket;
+        if !self.waiting {
+            bucket.enter();
+            self.waiting = true;
+        }
+        let mut wakers = bucket.lock();
+        synchronize();
+        if self.atomic.load_bits(self.order) != self.expected {
+            self.unregister(&mut wakers);
+            drop(wakers);
+            bucket.leave();
+            self.waiting = false;
+            return Poll::Ready(());
+        }
+        let id = *self
+            .id
+            .get_or_insert_with(|| NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        match wakers.iter_mut().find(|(other, _)| *other == id) {
+            Some((_, waker)) => waker.clone_from(cx.waker()),
+            None => wakers.push((id, cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for WaitFuture<'_> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.unregister(&mut self.bucket.lock());
+            self.bucket.leave();
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! A value published by a writer to many readers, with a version telling the readers when it changed.
+//!
+//! Configuration distributed to worker threads is read far more often than it changes, and each worker needs to
+//! know whether it changed since it last read it. The value is kept in a [`SeqLock`], so the readers never block
+//! the writer, and its version in an atomic bumped while the value is written, which the readers copy with the
+//! value, or check, or block on with [`wait`](crate::AtomicU64::wait), without copying the value.
+
+use crate::{AtomicU64, PlainData, SeqLock};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// A [`PlainData`] value and its version, which starts at 0 and is bumped by every publish.
+///
+/// It is a broadcast channel keeping only the latest value, like the `watch` channel of `tokio`, without a
+/// runtime. Publishing releases the writes made before it to the readers of the value.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::WatchCell;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let log_level = Arc::new(WatchCell::new(1u64));
+/// let (seen, level) = log_level.read();
+/// assert_eq!((seen, level), (0, 1));
+///
+/// let admin = {
+///     let log_level = log_level.clone();
+///     thread::spawn(move || log_level.publish(3))
+/// };
+///
+/// // the worker sleeps until the level changes
+/// let (version, level) = log_level.wait_for_change(seen);
+/// assert_eq!((version, level), (1, 3));
+/// assert!(!log_level.changed_since(version));
+/// admin.join().unwrap();
+/// ```
+pub struct WatchCell<T: PlainData> {
+    value: SeqLock<T>,
+    /// The version of the latest value, bumped while the value is written.
+    version: AtomicU64,
+}
+
+impl<T: PlainData> WatchCell<T> {
+    /// Creates a cell holding `value`, at version 0.
+    pub fn new(value: T) -> Self {
+        Self {
+            value: SeqLock::new(value),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    /// Replaces the value with `value`, wakes the readers waiting for a change, and returns the new version.
+    pub fn publish(&self, value: T) -> u64 {
+        let version = self
+            .value
+            .store_with(value, || self.version.fetch_add(1, Ordering::Release) + 1);
+        self.version.notify_all();
+        version
+    }
+
+    /// Returns the version and the value, read together.
+    pub fn read(&self) -> (u64, T) {
+        let (value, version) = self
+            .value
+            .read_with(|| self.version.load(Ordering::Relaxed));
+        (version, value)
+    }
+
+    /// Returns the version of the latest value, without copying the value.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Returns whether a value was published after `version`.
+    pub fn changed_since(&self, version: u64) -> bool {
+        self.version() != version
+    }
+
+    /// Blocks until a value was published after `version`, and returns the latest one with its version.
+    ///
+    /// Returns immediately if one already was.
+    pub fn wait_for_change(&self, version: u64) -> (u64, T) {
+        self.version.wait(version, Ordering::Acquire);
+        self.read()
+    }
+}
+
+impl<T: PlainData + Default> Default for WatchCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: PlainData + fmt::Debug> fmt::Debug for WatchCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (version, value) = self.read();
+        f.debug_struct("WatchCell")
+            .field("version", &version)
+            .field("value", &value)
+            .finish()
+    }
+}
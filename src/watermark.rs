@@ -0,0 +1,120 @@
+//! The highest or lowest value observed since the last read, such as the worst latency between two scrapes.
+//!
+//! An exporter tracking a high-water mark takes it at each scrape, and starts over from nothing, so the mark
+//! reflects the last interval. Taking it swaps the value with the one meaning nothing was observed, so an
+//! observation racing with the scrape is either in the mark taken, or in the next one.
+
+use crate::{Atomic64Ext, AtomicU64};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+macro_rules! watermark {
+    ($(#[$attr:meta])* $name:ident, $empty:expr, $exceeds:tt, $what:literal) => {
+        $(#[$attr])*
+        pub struct $name {
+            value: AtomicU64,
+        }
+
+        impl $name {
+            #[doc = concat!("Creates a tracker which observed nothing, holding `", stringify!($empty), "`.")]
+            pub fn new() -> Self {
+                Self {
+                    value: AtomicU64::new($empty),
+                }
+            }
+
+            #[doc = concat!("Observes `value`, keeping it if it is the ", $what, " so far.")]
+            ///
+            /// Values which don't replace the mark only load it, so they don't write to the atomic.
+            pub fn observe(&self, value: u64) {
+                let _ = self
+                    .value
+                    .fetch_update_with_backoff(Ordering::Relaxed, Ordering::Relaxed, |mark| {
+                        if value $exceeds mark {
+                            Some(value)
+                        } else {
+                            None
+                        }
+                    });
+            }
+
+            #[doc = concat!("Returns the ", $what, " value observed, or `", stringify!($empty), "` if none was.")]
+            pub fn get(&self) -> u64 {
+                self.value.load(Ordering::Relaxed)
+            }
+
+            #[doc = concat!("Returns the ", $what, " value observed, or `", stringify!($empty), "` if none was, and starts over.")]
+            pub fn take(&self) -> u64 {
+                self.value.swap($empty, Ordering::Relaxed)
+            }
+
+            /// Consumes the tracker, returning the mark.
+            pub fn into_inner(self) -> u64 {
+                self.value.into_inner()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&self.get()).finish()
+            }
+        }
+    };
+}
+
+watermark!(
+    /// The highest value observed since the tracker was last taken, such as a high-water mark of latencies.
+    ///
+    /// The operations are `Relaxed`, like a [`Counter`](crate::Counter)'s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atomic_shim::AtomicMax;
+    ///
+    /// let worst_latency = AtomicMax::new();
+    /// for latency in [12, 240, 31] {
+    ///     worst_latency.observe(latency);
+    /// }
+    ///
+    /// // each scrape reports the worst latency since the previous one
+    /// assert_eq!(worst_latency.take(), 240);
+    /// worst_latency.observe(17);
+    /// assert_eq!(worst_latency.take(), 17);
+    /// assert_eq!(worst_latency.take(), 0);
+    /// ```
+    AtomicMax,
+    0,
+    >,
+    "highest"
+);
+
+watermark!(
+    /// The lowest value observed since the tracker was last taken, such as a low-water mark of free buffers.
+    ///
+    /// The operations are `Relaxed`, like a [`Counter`](crate::Counter)'s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atomic_shim::AtomicMin;
+    ///
+    /// let free_buffers = AtomicMin::new();
+    /// for free in [64, 3, 20] {
+    ///     free_buffers.observe(free);
+    /// }
+    ///
+    /// assert_eq!(free_buffers.take(), 3);
+    /// assert_eq!(free_buffers.take(), u64::MAX);
+    /// ```
+    AtomicMin,
+    u64::MAX,
+    <,
+    "lowest"
+);
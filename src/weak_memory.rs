@@ -0,0 +1,335 @@
+//! A test-only simulation of weak memory, where the shims' loads may return values older than the latest store.
+//!
+//! x86 orders memory accesses strongly, so code missing an `Acquire` or a `Release` usually works there, and only
+//! fails on ARM or POWER. With `features = ["weak-memory", "mutex"]`, the shims keep the last stores of each
+//! atomic, and `Relaxed` and `Acquire` loads return one of them at random, among the ones the memory model allows:
+//!
+//! - a thread never reads a store older than one it already read or wrote on the same atomic;
+//! - a thread sees the stores made before its first access to a shim, as if they happened before it was spawned,
+//!   and the stores seen by the threads which exited, as if they were joined;
+//! - an `Acquire` load which reads a `Release` store sees everything the storing thread had seen;
+//! - read-modify-write operations, and `SeqCst` loads, read the latest store.
+//!
+//! Fences, and the synchronization of other primitives, such as a `Mutex` or a channel, are not tracked, so loads
+//! ordered by them may return stale values they couldn't return on hardware. Call [`synchronize`] after them.
+//! With the feature, every shim uses the simulation, instead of the lock or the 64-bit backends. The native types,
+//! which aren't shimmed, are not simulated.
+//!
+//! # Examples
+//!
+//! A flag published with `Relaxed` doesn't publish the data stored before it, so the reader may see the flag
+//! set and the data still zero. With `Release` and `Acquire`, as below, it never does.
+//!
+//! ```
+//! use atomic_shim::AtomicU64;
+//! use std::sync::atomic::Ordering;
+//! use std::sync::Arc;
+//! use std::thread;
+//!
+//! let data = Arc::new(AtomicU64::new(0));
+//! let flag = Arc::new(AtomicU64::new(0));
+//!
+//! let reader = {
+//!     let (data, flag) = (data.clone(), flag.clone());
+//!     thread::spawn(move || {
+//!         while flag.load(Ordering::Acquire) == 0 {}
+//!         data.load(Ordering::Relaxed)
+//!     })
+//! };
+//!
+//! data.store(42, Ordering::Relaxed);
+//! flag.store(1, Ordering::Release);
+//! assert_eq!(reader.join().unwrap(), 42);
+//! ```
+#![cfg_attr(not(atomic_shim_fallback), allow(dead_code))]
+
+#[cfg(atomic_shim_fallback)]
+use crate::shim::{diagnostics, step, Slot};
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+#[cfg(atomic_shim_fallback)]
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+#[cfg(atomic_shim_fallback)]
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// How many stores of each atomic the loads choose from.
+const HISTORY: usize = 8;
+
+/// For each atomic, by id, the index of the oldest store a thread may still read.
+type View = BTreeMap<usize, u64>;
+
+fn merge(into: &mut View, from: &View) {
+    for (&id, &index) in from {
+        let floor = into.entry(id).or_insert(index);
+        *floor = (*floor).max(index);
+    }
+}
+
+fn acquires(order: Ordering) -> bool {
+    matches!(
+        order,
+        Ordering::Acquire | Ordering::AcqRel | Ordering::SeqCst
+    )
+}
+
+fn releases(order: Ordering) -> bool {
+    matches!(
+        order,
+        Ordering::Release | Ordering::AcqRel | Ordering::SeqCst
+    )
+}
+
+struct Global {
+    /// The index of the latest store of every live atomic.
+    latest: View,
+    /// What the threads which exited had seen.
+    exited: View,
+}
+
+static GLOBAL: Mutex<Global> = Mutex::new(Global {
+    latest: BTreeMap::new(),
+    exited: BTreeMap::new(),
+});
+
+/// Bumped whenever a thread exits, so the others merge its view on their next access.
+static EXITED: AtomicUsize = AtomicUsize::new(0);
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The simulation's state stays consistent on panics, which are reported by the atomics' own locks.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct Thread {
+    view: View,
+    exited: usize,
+    random: u64,
+}
+
+impl Thread {
+    fn new() -> Self {
+        Thread {
+            view: lock(&GLOBAL).latest.clone(),
+            exited: 0,
+            // Each `RandomState` has its own keys, so hashing nothing gives a different seed per thread.
+            random: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    /// Merges the views of the threads which exited since the last access.
+    fn catch_up(&mut self) {
+        let exited = EXITED.load(Ordering::Acquire);
+        if exited != self.exited {
+            merge(&mut self.view, &lock(&GLOBAL).exited);
+            self.exited = exited;
+        }
+    }
+
+    /// Returns a number below `n`, with xorshift.
+    fn below(&mut self, n: usize) -> usize {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random % n as u64) as usize
+    }
+
+    /// Records the read of a store, and what it publishes if the load acquires it.
+    fn observe(&mut self, id: usize, index: u64, released: Option<&Arc<View>>, order: Ordering) {
+        let floor = self.view.entry(id).or_insert(index);
+        *floor = (*floor).max(index);
+        if let (true, Some(view)) = (acquires(order), released) {
+            merge(&mut self.view, view);
+        }
+    }
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        merge(&mut lock(&GLOBAL).exited, &self.view);
+        EXITED.fetch_add(1, Ordering::Release);
+    }
+}
+
+thread_local! {
+    static THREAD: RefCell<Thread> = RefCell::new(Thread::new());
+}
+
+/// Makes the latest stores of every shim visible to the current thread.
+///
+/// The simulation only tracks the synchronization of the shims, so call it where the thread synchronizes through
+/// something else, such as after locking a `Mutex`, receiving from a channel, or a fence.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::weak_memory;
+/// use atomic_shim::AtomicU64;
+/// use std::sync::atomic::Ordering;
+/// use std::sync::mpsc;
+/// use std::thread;
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let counter: &'static AtomicU64 = Box::leak(Box::new(AtomicU64::new(0)));
+///
+/// thread::spawn(move || {
+///     counter.store(1, Ordering::Relaxed);
+///     sender.send(()).unwrap();
+/// });
+///
+/// receiver.recv().unwrap();
+/// weak_memory::synchronize();
+/// assert_eq!(counter.load(Ordering::Relaxed), 1);
+/// ```
+pub fn synchronize() {
+    let _ = THREAD.try_with(|thread| {
+        let thread = &mut *thread.borrow_mut();
+        merge(&mut thread.view, &lock(&GLOBAL).latest);
+    });
+}
+
+#[cfg(atomic_shim_fallback)]
+struct Store<T> {
+    index: u64,
+    value: T,
+    /// The view of the thread which stored it, for the loads which acquire it.
+    released: Option<Arc<View>>,
+}
+
+/// Storage of the shims under the simulation, keeping the last stores of the atomic.
+#[cfg(atomic_shim_fallback)]
+pub(crate) struct WeakMemory<T> {
+    id: usize,
+    stores: Mutex<VecDeque<Store<T>>>,
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T: Copy + PartialEq + Default> Default for WeakMemory<T> {
+    fn default() -> Self {
+        Slot::new(T::default())
+    }
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T: Copy + PartialEq> Slot<T> for WeakMemory<T> {
+    /// The simulation is only meant for tests, so it isn't held to a budget.
+    const FOOTPRINT: usize = mem::size_of::<Self>();
+    const IS_LOCK_FREE: bool = false;
+
+    fn new(value: T) -> Self {
+        let mut stores = VecDeque::with_capacity(HISTORY + 1);
+        stores.push_back(Store {
+            index: 0,
+            value,
+            released: None,
+        });
+        WeakMemory {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            stores: Mutex::new(stores),
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        false
+    }
+
+    /// The exclusive borrow happens after every store, so only the latest is kept.
+    fn get_mut(&mut self) -> &mut T {
+        let stores = self.stores.get_mut().unwrap();
+        stores.drain(..stores.len() - 1);
+        &mut stores.back_mut().unwrap().value
+    }
+
+    fn into_inner(self) -> T {
+        self.stores.lock().unwrap().back().unwrap().value
+    }
+
+    fn load(&self, order: Ordering) -> T {
+        step(|| {
+            let stores = self.stores.lock().unwrap();
+            let latest = stores.back().unwrap();
+            THREAD
+                .try_with(|thread| {
+                    let thread = &mut *thread.borrow_mut();
+                    thread.catch_up();
+                    let store = if order == Ordering::SeqCst {
+                        latest
+                    } else {
+                        let floor = thread.view.get(&self.id).copied().unwrap_or(0);
+                        let first = stores.iter().position(|s| s.index >= floor).unwrap();
+                        &stores[first + thread.below(stores.len() - first)]
+                    };
+                    thread.observe(self.id, store.index, store.released.as_ref(), order);
+                    store.value
+                })
+                .unwrap_or(latest.value)
+        })
+    }
+
+    fn update<F: Fn(T) -> T>(&self, order: Ordering, f: F) -> T {
+        step(|| {
+            let mut stores = self.stores.lock().unwrap();
+            let section = diagnostics::Section::start();
+            let latest = stores.back().unwrap();
+            let (prev, index) = (latest.value, latest.index + 1);
+            let next = f(prev);
+            // A read-modify-write which doesn't change the value, such as a failed compare-exchange, only reads it.
+            let changed = next != prev;
+            let inherited = latest.released.clone();
+            let released = THREAD
+                .try_with(|thread| {
+                    let thread = &mut *thread.borrow_mut();
+                    thread.catch_up();
+                    thread.observe(self.id, latest.index, inherited.as_ref(), order);
+                    if changed {
+                        thread.view.insert(self.id, index);
+                    }
+                    if releases(order) {
+                        Some(Arc::new(thread.view.clone()))
+                    } else {
+                        inherited.clone()
+                    }
+                })
+                .unwrap_or_else(|_| inherited.clone());
+
+            if changed {
+                stores.push_back(Store {
+                    index,
+                    value: next,
+                    released,
+                });
+                if stores.len() > HISTORY {
+                    stores.pop_front();
+                }
+                lock(&GLOBAL).latest.insert(self.id, index);
+            }
+            let slow = section.end();
+            drop(stores);
+            slow.report(self);
+            prev
+        })
+    }
+
+    fn snapshot(&self) -> T {
+        lock(&self.stores).back().unwrap().value
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.stores.is_poisoned()
+    }
+
+    fn forget_poisoning(&self) {}
+}
+
+#[cfg(atomic_shim_fallback)]
+impl<T> Drop for WeakMemory<T> {
+    fn drop(&mut self) {
+        let mut global = lock(&GLOBAL);
+        global.latest.remove(&self.id);
+        global.exited.remove(&self.id);
+    }
+}
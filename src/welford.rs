@@ -0,0 +1,168 @@
+//! The mean and variance of samples recorded by many threads, with Welford's online algorithm.
+//!
+//! The algorithm updates the count, the mean and the sum of squared deviations together, so the three are
+//! atomics under a version, like the shim's seqlock: a writer makes the version odd while updating them, and a
+//! reader retries when the version changed while it read them. Readers never block writers, and the writers only
+//! wait for each other for the few instructions of an update.
+
+use crate::{AtomicU64, Backoff};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// The count, mean and variance of the samples recorded, such as the latencies of a service.
+///
+/// Every operation is sequentially consistent.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_shim::Welford;
+///
+/// let latencies = Welford::new();
+/// for latency in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+///     latencies.record(latency);
+/// }
+///
+/// let stats = latencies.snapshot();
+/// assert_eq!(stats.count(), 8);
+/// assert_eq!(stats.mean(), 5.0);
+/// assert_eq!(stats.variance(), 4.0);
+/// assert_eq!(stats.std_dev(), 2.0);
+/// ```
+pub struct Welford {
+    /// Odd while a writer updates the fields, and bumped by every update.
+    version: AtomicU64,
+    count: AtomicU64,
+    /// The bits of the mean, an `f64`.
+    mean: AtomicU64,
+    /// The bits of the sum of the squared deviations from the mean, an `f64`.
+    m2: AtomicU64,
+}
+
+impl Welford {
+    /// Creates an accumulator without samples.
+    pub fn new() -> Self {
+        Self {
+            version: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+            mean: AtomicU64::new(0.0f64.to_bits()),
+            m2: AtomicU64::new(0.0f64.to_bits()),
+        }
+    }
+
+    /// Runs `f` on the fields, with the version odd, so no reader uses them meanwhile.
+    fn write(&self, f: impl FnOnce(u64, f64, f64) -> (u64, f64, f64)) {
+        let backoff = Backoff::new();
+        let version = loop {
+            let version = self.version.load(Ordering::SeqCst);
+            if version & 1 == 0
+                && self
+                    .version
+                    .compare_exchange_weak(version, version + 1, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            {
+                break version;
+            }
+            backoff.snooze();
+        };
+        let (count, mean, m2) = f(
+            self.count.load(Ordering::SeqCst),
+            f64::from_bits(self.mean.load(Ordering::SeqCst)),
+            f64::from_bits(self.m2.load(Ordering::SeqCst)),
+        );
+        self.count.store(count, Ordering::SeqCst);
+        self.mean.store(mean.to_bits(), Ordering::SeqCst);
+        self.m2.store(m2.to_bits(), Ordering::SeqCst);
+        self.version
+            .store(version.wrapping_add(2), Ordering::SeqCst);
+    }
+
+    /// Records `sample`.
+    pub fn record(&self, sample: f64) {
+        self.write(|count, mean, m2| {
+            let count = count + 1;
+            let delta = sample - mean;
+            let mean = mean + delta / count as f64;
+            (count, mean, m2 + delta * (sample - mean))
+        });
+    }
+
+    /// Forgets every sample.
+    pub fn reset(&self) {
+        self.write(|_, _, _| (0, 0.0, 0.0));
+    }
+
+    /// Returns the count, mean and variance of the samples, read together.
+    pub fn snapshot(&self) -> WelfordSnapshot {
+        let backoff = Backoff::new();
+        loop {
+            let version = self.version.load(Ordering::SeqCst);
+            if version & 1 == 0 {
+                let snapshot = WelfordSnapshot {
+                    count: self.count.load(Ordering::SeqCst),
+                    mean: f64::from_bits(self.mean.load(Ordering::SeqCst)),
+                    m2: f64::from_bits(self.m2.load(Ordering::SeqCst)),
+                };
+                if self.version.load(Ordering::SeqCst) == version {
+                    return snapshot;
+                }
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+impl Default for Welford {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Welford {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.snapshot(), f)
+    }
+}
+
+/// The count, mean and variance of the samples of a [`Welford`] accumulator, read together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WelfordSnapshot {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl WelfordSnapshot {
+    /// Returns the number of samples.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean of the samples, or 0 without samples.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the population variance of the samples, or 0 without samples.
+    pub fn variance(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.m2 / self.count as f64
+        }
+    }
+
+    /// Returns the sample variance, with Bessel's correction, or 0 with fewer than two samples.
+    pub fn sample_variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Returns the population standard deviation of the samples.
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+}
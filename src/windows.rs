@@ -0,0 +1,97 @@
+//! Storage of the 64-bit shims on 32-bit x86 Windows, using the `Interlocked*64` functions instead of a lock.
+//!
+//! Windows guarantees `InterlockedCompareExchange64` on every x86 CPU it supports, so when the shim is forced
+//! there, with `features = ["windows-intrinsics"]`, the operations are compare-exchange loops over the function
+//! exported by `kernel32`. The other Windows targets have native 64-bit atomics, so forcing the shim on them
+//! keeps the lock-based backends, which is what `mutex` is for.
+
+use crate::shim::{Bits64, Slot};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn InterlockedCompareExchange64(destination: *mut i64, exchange: i64, comparand: i64) -> i64;
+}
+
+/// Stores `exchange` at `destination` if it holds `comparand`, returning the previous value.
+///
+/// # Safety
+///
+/// `destination` must be valid and aligned to 8 bytes.
+unsafe fn compare_exchange(destination: *mut i64, exchange: i64, comparand: i64) -> i64 {
+    InterlockedCompareExchange64(destination, exchange, comparand)
+}
+
+/// Storage of a 64-bit shim, accessed with `InterlockedCompareExchange64`.
+#[repr(C, align(8))]
+pub(crate) struct Interlocked<T> {
+    value: UnsafeCell<i64>,
+    int: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Sync for Interlocked<T> {}
+
+impl<T: Bits64> Interlocked<T> {
+    fn load_bits(&self) -> i64 {
+        // Exchanging zero for zero leaves the value as it was, and returns it without tearing.
+        unsafe { compare_exchange(self.value.get(), 0, 0) }
+    }
+}
+
+impl<T: Bits64> Default for Interlocked<T> {
+    fn default() -> Self {
+        Slot::new(T::from_bits(0))
+    }
+}
+
+impl<T: Bits64> Slot<T> for Interlocked<T> {
+    const FOOTPRINT: usize = 8;
+    const IS_LOCK_FREE: bool = true;
+
+    fn new(value: T) -> Self {
+        Interlocked {
+            value: UnsafeCell::new(value.to_bits() as i64),
+            int: PhantomData,
+        }
+    }
+
+    fn is_lock_free(&self) -> bool {
+        Self::IS_LOCK_FREE
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *(self.value.get() as *mut T) }
+    }
+
+    fn into_inner(self) -> T {
+        T::from_bits(self.value.into_inner() as u64)
+    }
+
+    fn load(&self, _: Ordering) -> T {
+        T::from_bits(self.load_bits() as u64)
+    }
+
+    fn update<F: Fn(T) -> T>(&self, _: Ordering, f: F) -> T {
+        let mut prev = self.load_bits();
+        loop {
+            let next = f(T::from_bits(prev as u64)).to_bits() as i64;
+            let found = unsafe { compare_exchange(self.value.get(), next, prev) };
+            if found == prev {
+                return T::from_bits(prev as u64);
+            }
+            prev = found;
+        }
+    }
+
+    fn snapshot(&self) -> T {
+        self.load(Ordering::SeqCst)
+    }
+
+    fn is_poisoned(&self) -> bool {
+        false
+    }
+
+    fn forget_poisoning(&self) {}
+}
@@ -0,0 +1,191 @@
+//! Checks the native wrappers are zero-cost: their operations compile to the same instructions as `std`'s.
+//!
+//! It compiles the crate, and a crate calling the same operations on the facade and on `std`, with
+//! `rustc -O --emit asm`, and compares the bodies of the functions. Set `RUSTC` to use another compiler
+//! than the one on the `PATH`.
+#![cfg(all(
+    target_has_atomic = "64",
+    not(atomic_shim_fallback_u64),
+    not(atomic_shim_fallback_i64)
+))]
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const OPERATIONS: [(&str, &str, &str); 7] = [
+    ("load", "(a: &ATOMIC) -> INT", "a.load(Ordering::Acquire)"),
+    (
+        "store",
+        "(a: &ATOMIC, v: INT)",
+        "a.store(v, Ordering::Release)",
+    ),
+    (
+        "swap",
+        "(a: &ATOMIC, v: INT) -> INT",
+        "a.swap(v, Ordering::AcqRel)",
+    ),
+    (
+        "fetch_add",
+        "(a: &ATOMIC, v: INT) -> INT",
+        "a.fetch_add(v, Ordering::Relaxed)",
+    ),
+    (
+        "fetch_sub",
+        "(a: &ATOMIC, v: INT) -> INT",
+        "a.fetch_sub(v, Ordering::Relaxed)",
+    ),
+    (
+        "fetch_or",
+        "(a: &ATOMIC, v: INT) -> INT",
+        "a.fetch_or(v, Ordering::SeqCst)",
+    ),
+    (
+        "compare_exchange",
+        "(a: &ATOMIC, c: INT, n: INT) -> Result<INT, INT>",
+        "a.compare_exchange(c, n, Ordering::AcqRel, Ordering::Acquire)",
+    ),
+];
+
+const TYPES: [(&str, &str); 2] = [("u64", "AtomicU64"), ("i64", "AtomicI64")];
+
+/// A crate with a function per operation and type, named `<facade|std>_<int>_<operation>`.
+fn probe_source() -> String {
+    let mut source = String::from("use std::sync::atomic::Ordering;\n");
+    for (int, atomic) in TYPES {
+        for (prefix, path) in [("facade", "atomic_shim"), ("std", "std::sync::atomic")] {
+            for (op, signature, body) in OPERATIONS {
+                let signature = signature
+                    .replace("ATOMIC", &format!("{}::{}", path, atomic))
+                    .replace("INT", int);
+                source.push_str(&format!(
+                    "#[no_mangle]\npub fn {}_{}_{}{} {{\n    {}\n}}\n",
+                    prefix, int, op, signature, body
+                ));
+            }
+        }
+    }
+    source
+}
+
+fn rustc(dir: &Path, args: &[&str]) {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let status = Command::new(rustc)
+        .current_dir(dir)
+        .args([
+            "--edition",
+            "2018",
+            "-C",
+            "opt-level=3",
+            "-C",
+            "codegen-units=1",
+        ])
+        .args(args)
+        .status()
+        .expect("failed to run rustc");
+    assert!(status.success(), "rustc {:?} failed", args);
+}
+
+fn is_probe(name: &str) -> bool {
+    name.starts_with("facade_") || name.starts_with("std_")
+}
+
+/// Returns the instructions of every function of the assembly, without labels and directives.
+///
+/// LLVM merges identical functions, emitting `name = other` aliases, which get the body of the function they alias.
+fn functions(asm: &str) -> HashMap<String, Vec<String>> {
+    let mut functions = HashMap::new();
+    let mut aliases = Vec::new();
+    let mut current: Option<(String, Vec<String>)> = None;
+    for line in asm.lines() {
+        let trimmed = line.trim();
+        if let Some((alias, target)) = trimmed.split_once(" = ") {
+            let alias = alias.trim_start_matches('_');
+            if is_probe(alias) {
+                aliases.push((alias.to_owned(), target.trim_start_matches('_').to_owned()));
+            }
+        } else if !line.starts_with(char::is_whitespace) && trimmed.ends_with(':') {
+            let name = trimmed.trim_end_matches(':').trim_start_matches('_');
+            if is_probe(name) {
+                functions.extend(current.take());
+                current = Some((name.to_owned(), Vec::new()));
+            }
+        } else if trimmed.starts_with(".cfi_endproc") || trimmed.starts_with(".Lfunc_end") {
+            functions.extend(current.take());
+        } else if let Some((_, body)) = &mut current {
+            let instruction = trimmed.split('#').next().unwrap_or("").trim();
+            if !instruction.is_empty()
+                && !instruction.starts_with('.')
+                && !instruction.ends_with(':')
+            {
+                body.push(instruction.to_owned());
+            }
+        }
+    }
+    functions.extend(current);
+    for (alias, target) in aliases {
+        let body = functions[&target].clone();
+        functions.insert(alias, body);
+    }
+    functions
+}
+
+#[test]
+fn native_operations_compile_like_std() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen");
+    fs::create_dir_all(&dir).unwrap();
+    let lib = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("lib.rs");
+    fs::write(dir.join("probe.rs"), probe_source()).unwrap();
+
+    rustc(
+        &dir,
+        &[
+            "--crate-type=rlib",
+            "--crate-name=atomic_shim",
+            "--cap-lints=allow",
+            lib.to_str().unwrap(),
+        ],
+    );
+    rustc(
+        &dir,
+        &[
+            "--crate-type=rlib",
+            "--emit=asm",
+            "--extern",
+            "atomic_shim=libatomic_shim.rlib",
+            "probe.rs",
+        ],
+    );
+
+    let asm = fs::read_to_string(dir.join("probe.s")).unwrap();
+    let functions = functions(&asm);
+    for (int, _) in TYPES {
+        for (op, _, _) in OPERATIONS {
+            let facade = &functions[&format!("facade_{}_{}", int, op)];
+            let std = &functions[&format!("std_{}_{}", int, op)];
+            assert_eq!(facade, std, "{}::{} compiles differently from std", int, op);
+        }
+    }
+
+    if cfg!(target_arch = "x86_64") {
+        // A load is a single `mov`, and `fetch_add` a single `lock xadd`, besides moving the registers.
+        let load = &functions["facade_u64_load"];
+        assert_eq!(
+            load.iter().filter(|i| !i.starts_with("ret")).count(),
+            1,
+            "{:?}",
+            load
+        );
+        let fetch_add = &functions["facade_u64_fetch_add"];
+        assert_eq!(
+            fetch_add.iter().filter(|i| i.starts_with("lock")).count(),
+            1,
+            "{:?}",
+            fetch_add
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Runs random sequences of operations against the crate's atomics and `std`'s, asserting identical results.
+//!
+//! On hosts with 64-bit atomics, run with `cargo test --features mutex --test equivalence` to check the shim.
+//! Without the feature, it checks the native wrappers.
+#![cfg(target_has_atomic = "64")]
+
+use proptest::prelude::*;
+use std::sync::atomic::Ordering;
+
+#[derive(Clone, Debug)]
+enum Op<T> {
+    Load,
+    Store(T),
+    Swap(T),
+    CompareExchange(T, T),
+    FetchAdd(T),
+    FetchSub(T),
+    FetchAnd(T),
+    FetchNand(T),
+    FetchOr(T),
+    FetchXor(T),
+}
+
+/// Picks operands close to the current values often enough for `compare_exchange` to succeed,
+/// and extreme ones to exercise wrapping.
+fn operand<T: Arbitrary + Copy + 'static>(edges: [T; 3]) -> impl Strategy<Value = T> {
+    prop_oneof![any::<T>(), Just(edges[0]), Just(edges[1]), Just(edges[2]),]
+}
+
+fn op<T: Arbitrary + Copy + std::fmt::Debug + 'static>(
+    edges: [T; 3],
+) -> impl Strategy<Value = Op<T>> {
+    let v = move || operand(edges);
+    prop_oneof![
+        Just(Op::Load),
+        v().prop_map(Op::Store),
+        v().prop_map(Op::Swap),
+        (v(), v()).prop_map(|(current, new)| Op::CompareExchange(current, new)),
+        v().prop_map(Op::FetchAdd),
+        v().prop_map(Op::FetchSub),
+        v().prop_map(Op::FetchAnd),
+        v().prop_map(Op::FetchNand),
+        v().prop_map(Op::FetchOr),
+        v().prop_map(Op::FetchXor),
+    ]
+}
+
+macro_rules! equivalence {
+    ($name:ident, $atomic:ident, $int:ident) => {
+        proptest! {
+            #[test]
+            fn $name(
+                initial in operand([0, $int::MIN, $int::MAX]),
+                ops in prop::collection::vec(op([0, $int::MIN, $int::MAX]), 0..64),
+            ) {
+                let shim = atomic_shim::$atomic::new(initial);
+                let std = std::sync::atomic::$atomic::new(initial);
+                let order = Ordering::SeqCst;
+
+                for op in ops {
+                    let (actual, expected) = match op {
+                        Op::Load => (Ok(shim.load(order)), Ok(std.load(order))),
+                        Op::Store(v) => {
+                            shim.store(v, order);
+                            std.store(v, order);
+                            (Ok(v), Ok(v))
+                        }
+                        Op::Swap(v) => (Ok(shim.swap(v, order)), Ok(std.swap(v, order))),
+                        Op::CompareExchange(current, new) => (
+                            shim.compare_exchange(current, new, order, order),
+                            std.compare_exchange(current, new, order, order),
+                        ),
+                        Op::FetchAdd(v) => (Ok(shim.fetch_add(v, order)), Ok(std.fetch_add(v, order))),
+                        Op::FetchSub(v) => (Ok(shim.fetch_sub(v, order)), Ok(std.fetch_sub(v, order))),
+                        Op::FetchAnd(v) => (Ok(shim.fetch_and(v, order)), Ok(std.fetch_and(v, order))),
+                        Op::FetchNand(v) => (Ok(shim.fetch_nand(v, order)), Ok(std.fetch_nand(v, order))),
+                        Op::FetchOr(v) => (Ok(shim.fetch_or(v, order)), Ok(std.fetch_or(v, order))),
+                        Op::FetchXor(v) => (Ok(shim.fetch_xor(v, order)), Ok(std.fetch_xor(v, order))),
+                    };
+                    prop_assert_eq!(actual, expected, "after {:?}", op);
+                }
+
+                prop_assert_eq!(shim.into_inner(), std.into_inner());
+            }
+        }
+    };
+}
+
+equivalence!(atomic_u64_matches_std, AtomicU64, u64);
+equivalence!(atomic_i64_matches_std, AtomicI64, i64);
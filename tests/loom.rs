@@ -0,0 +1,124 @@
+//! Model-checks the shim with loom, which explores every interleaving of the threads.
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release --test loom`. Under loom, every type is shimmed,
+//! and the shim's lock is loom's `RwLock`.
+#![cfg(loom)]
+
+use atomic_shim::atomic::{AtomicBool, AtomicU32};
+use atomic_shim::{AtomicI64, AtomicU64};
+use loom::sync::Arc;
+use loom::thread;
+use std::sync::atomic::Ordering;
+
+#[test]
+fn compare_exchange_has_a_single_winner() {
+    loom::model(|| {
+        let value = Arc::new(AtomicU64::new(0));
+
+        let threads: Vec<_> = (1..=2)
+            .map(|id| {
+                let value = value.clone();
+                thread::spawn(move || {
+                    value
+                        .compare_exchange(0, id, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                })
+            })
+            .collect();
+
+        let winners = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .filter(|&won| won)
+            .count();
+        assert_eq!(winners, 1);
+        assert_ne!(value.load(Ordering::Acquire), 0);
+    });
+}
+
+#[test]
+fn compare_exchange_reports_the_current_value() {
+    loom::model(|| {
+        let value = Arc::new(AtomicI64::new(1));
+
+        let writer = {
+            let value = value.clone();
+            thread::spawn(move || value.store(-1, Ordering::Release))
+        };
+
+        match value.compare_exchange(1, 2, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(prev) => assert_eq!(prev, 1),
+            Err(prev) => assert_eq!(prev, -1),
+        }
+
+        writer.join().unwrap();
+        let last = value.load(Ordering::Acquire);
+        assert!(last == -1 || last == 2, "unexpected value {}", last);
+    });
+}
+
+#[test]
+fn fetch_add_is_not_lost() {
+    loom::model(|| {
+        let counter = Arc::new(AtomicU64::new(0));
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })
+            })
+            .collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+    });
+}
+
+#[test]
+fn load_observes_a_stored_value() {
+    loom::model(|| {
+        let value = Arc::new(AtomicU32::new(1));
+
+        let writer = {
+            let value = value.clone();
+            thread::spawn(move || {
+                value.store(2, Ordering::Release);
+                value.store(3, Ordering::Release);
+            })
+        };
+
+        let first = value.load(Ordering::Acquire);
+        let second = value.load(Ordering::Acquire);
+        assert!((1..=3).contains(&first));
+        assert!(second >= first, "{} was read after {}", second, first);
+
+        writer.join().unwrap();
+        assert_eq!(value.load(Ordering::Acquire), 3);
+    });
+}
+
+#[test]
+fn flag_publishes_a_value() {
+    loom::model(|| {
+        let ready = Arc::new(AtomicBool::new(false));
+        let value = Arc::new(AtomicU64::new(0));
+
+        let writer = {
+            let (ready, value) = (ready.clone(), value.clone());
+            thread::spawn(move || {
+                value.store(42, Ordering::Relaxed);
+                ready.store(true, Ordering::Release);
+            })
+        };
+
+        if ready.load(Ordering::Acquire) {
+            assert_eq!(value.load(Ordering::Relaxed), 42);
+        }
+
+        writer.join().unwrap();
+    });
+}
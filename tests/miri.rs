@@ -0,0 +1,429 @@
+//! Exercises the shim, and the crate's unsafe code, in ways Miri checks for undefined behavior.
+//!
+//! Run with `cargo +nightly miri test --features mutex --test miri`, which forces the 64-bit shims,
+//! and without `--features mutex` for the native wrappers. The tests also run as part of `cargo test`.
+
+use atomic_shim::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
+use atomic_shim::{
+    ArcCell, AtomicField, AtomicI64, AtomicTaggedPtr, AtomicU64, AtomicU64Array, AtomicU64Pair,
+    AtomicU64Pool, AtomicU64Slice, EpochCounter, OnceU64, PlainData, RawAtomicI64, RawAtomicU64,
+    SeqLock,
+};
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+
+/// Miri is slow, so the threads only run a few operations each.
+const ITERATIONS: u64 = if cfg!(miri) { 20 } else { 1000 };
+
+#[test]
+fn shares_a_counter_between_threads() {
+    let counter = Arc::new(AtomicU64::new(0));
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(counter.load(Ordering::Relaxed), 2 * ITERATIONS);
+}
+
+#[test]
+fn publishes_data_through_a_flag() {
+    let ready = Arc::new(AtomicBool::new(false));
+    let data = Arc::new(AtomicI64::new(0));
+
+    let writer = {
+        let (ready, data) = (ready.clone(), data.clone());
+        thread::spawn(move || {
+            data.store(-7, Ordering::Relaxed);
+            ready.store(true, Ordering::Release);
+        })
+    };
+
+    while !ready.load(Ordering::Acquire) {
+        thread::yield_now();
+    }
+    assert_eq!(data.load(Ordering::Relaxed), -7);
+    writer.join().unwrap();
+}
+
+#[test]
+fn mutates_through_get_mut_and_into_inner() {
+    let mut value = AtomicU64::new(1);
+    *value.get_mut() += 1;
+    assert_eq!(value.swap(5, Ordering::SeqCst), 2);
+    assert_eq!(
+        value.compare_exchange(5, 6, Ordering::SeqCst, Ordering::SeqCst),
+        Ok(5)
+    );
+    assert_eq!(value.into_inner(), 6);
+
+    let mut flag = AtomicBool::new(false);
+    *flag.get_mut() = true;
+    assert!(flag.into_inner());
+}
+
+#[test]
+fn swaps_pointers_to_live_allocations() {
+    let first = Box::into_raw(Box::new(1u32));
+    let second = Box::into_raw(Box::new(2u32));
+    let pointer = AtomicPtr::new(first);
+
+    let prev = pointer.swap(second, Ordering::AcqRel);
+    assert_eq!(
+        pointer.compare_exchange(second, ptr::null_mut(), Ordering::AcqRel, Ordering::Acquire),
+        Ok(second)
+    );
+
+    unsafe {
+        assert_eq!(*prev, 1);
+        drop(Box::from_raw(prev));
+        drop(Box::from_raw(second));
+    }
+}
+
+#[test]
+fn wraps_integers_it_does_not_own() {
+    #[repr(C, align(8))]
+    struct Telemetry {
+        status: u32,
+        flags: u32,
+        packets: u64,
+        errors: i64,
+    }
+
+    let mut telemetry = Telemetry {
+        status: 0,
+        flags: 0,
+        packets: 1,
+        errors: -1,
+    };
+
+    {
+        let base: *mut Telemetry = &mut telemetry;
+        let packets = unsafe { RawAtomicU64::from_raw(ptr::addr_of_mut!((*base).packets)) };
+        let errors = unsafe { RawAtomicI64::from_raw(ptr::addr_of_mut!((*base).errors)) };
+        let field = unsafe { AtomicField::<u64>::at(base, 8) };
+
+        packets.fetch_add(2, Ordering::SeqCst);
+        field.fetch_add(3, Ordering::SeqCst);
+        errors.fetch_sub(1, Ordering::SeqCst);
+        assert_eq!(field.load(Ordering::SeqCst), 6);
+        assert_eq!(errors.load(Ordering::SeqCst), -2);
+    }
+
+    assert_eq!(telemetry.packets, 6);
+    assert_eq!(telemetry.status, 0);
+}
+
+#[test]
+fn counts_through_raw_atomics_from_threads() {
+    let value = Box::into_raw(Box::new(0u64));
+    let address = value as usize;
+
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            thread::spawn(move || {
+                let counter = unsafe { RawAtomicU64::from_raw(address as *mut u64) };
+                for _ in 0..ITERATIONS {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let value = unsafe { Box::from_raw(value) };
+    assert_eq!(*value, 2 * ITERATIONS);
+}
+
+#[test]
+fn initializes_a_once_cell_from_threads() {
+    let cell = Arc::new(OnceU64::new());
+    let threads: Vec<_> = (0..2)
+        .map(|i| {
+            let cell = cell.clone();
+            thread::spawn(move || cell.get_or_init(|| i))
+        })
+        .collect();
+    let values: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+    assert_eq!(values[0], values[1]);
+    assert_eq!(cell.get(), Some(values[0]));
+}
+
+#[test]
+fn exchanges_pairs_from_threads() {
+    let pair = Arc::new(AtomicU64Pair::new((0, 0)));
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let pair = pair.clone();
+            thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let mut current = pair.load();
+                    while let Err(actual) =
+                        pair.compare_exchange(current, (current.0 + 1, current.1 + 2))
+                    {
+                        current = actual;
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(pair.load(), (2 * ITERATIONS, 4 * ITERATIONS));
+}
+
+#[test]
+fn pushes_tagged_pointers_from_threads() {
+    struct Node {
+        value: u64,
+        next: *mut Node,
+    }
+
+    let head = Arc::new(AtomicTaggedPtr::<Node>::default());
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let head = head.clone();
+            thread::spawn(move || {
+                for value in 0..ITERATIONS {
+                    let node = Box::into_raw(Box::new(Node {
+                        value,
+                        next: ptr::null_mut(),
+                    }));
+                    let mut current = head.load(Ordering::Relaxed);
+                    loop {
+                        unsafe { (*node).next = current.0 };
+                        match head.compare_exchange(
+                            current,
+                            node,
+                            Ordering::Release,
+                            Ordering::Relaxed,
+                        ) {
+                            Ok(_) => break,
+                            Err(actual) => current = actual,
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let (mut node, tag) = head.load(Ordering::Acquire);
+    assert_eq!(tag as u64, 2 * ITERATIONS);
+    let mut sum = 0;
+    while !node.is_null() {
+        let boxed = unsafe { Box::from_raw(node) };
+        sum += boxed.value;
+        node = boxed.next;
+    }
+    assert_eq!(sum, ITERATIONS * (ITERATIONS - 1));
+}
+
+#[test]
+fn swaps_arcs_while_threads_load_them() {
+    let cell = Arc::new(ArcCell::new(Arc::new(vec![0u64; 4])));
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let cell = cell.clone();
+            thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let values = cell.load();
+                    assert!(values.iter().all(|value| *value == values[0]));
+                }
+            })
+        })
+        .collect();
+    for i in 1..=ITERATIONS {
+        cell.store(Arc::new(vec![i; 4]));
+    }
+    for t in readers {
+        t.join().unwrap();
+    }
+
+    let values = Arc::try_unwrap(Arc::try_unwrap(cell).unwrap().into_inner()).unwrap();
+    assert_eq!(values, [ITERATIONS; 4]);
+}
+
+#[test]
+fn counts_in_a_borrowed_slice_from_threads() {
+    let mut counters = vec![0u64; 4];
+    let view = AtomicU64Slice::new(&mut counters);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                for i in 0..ITERATIONS {
+                    view.fetch_add(i as usize % 4, 1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    assert_eq!(view.into_inner().iter().sum::<u64>(), 2 * ITERATIONS);
+    assert_eq!(counters[0], 2 * ITERATIONS / 4);
+}
+
+#[test]
+fn snapshots_an_array_while_threads_count() {
+    let buckets = Arc::new(AtomicU64Array::<16>::new());
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let buckets = buckets.clone();
+            thread::spawn(move || {
+                for i in 0..ITERATIONS {
+                    buckets.fetch_add(i as usize % 16, 1);
+                }
+            })
+        })
+        .collect();
+    let mut reset = 0;
+    for _ in 0..4 {
+        reset += buckets.reset().iter().sum::<u64>();
+    }
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let remaining: u64 = buckets.snapshot().iter().sum();
+    assert_eq!(reset + remaining, 2 * ITERATIONS);
+}
+
+#[test]
+fn reuses_pooled_counters_from_threads() {
+    let pool = AtomicU64Pool::new();
+    // more than a chunk, so the pool grows while the counters of the first one are used
+    let counters: Vec<_> = (0..1100).map(|i| pool.alloc(i)).collect();
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                for i in 0..ITERATIONS {
+                    counters[1099 - i as usize % 2].fetch_add(1, Ordering::Relaxed);
+                    let scratch = pool.alloc(i);
+                    scratch.fetch_add(1, Ordering::Relaxed);
+                    assert_eq!(scratch.load(Ordering::Relaxed), i + 1);
+                }
+            });
+        }
+    });
+
+    assert_eq!(counters[0].load(Ordering::Relaxed), 0);
+    assert_eq!(counters[1098].load(Ordering::Relaxed), 1098 + ITERATIONS);
+    assert_eq!(counters[1099].load(Ordering::Relaxed), 1099 + ITERATIONS);
+}
+
+#[test]
+fn reads_a_struct_while_threads_write_it() {
+    // twelve bytes, copied as a word and four bytes on 64-bit targets
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct Sample {
+        a: u32,
+        b: u32,
+        odd: u32,
+    }
+    unsafe impl PlainData for Sample {}
+
+    let lock = Arc::new(SeqLock::new(Sample { a: 0, b: 0, odd: 0 }));
+    let writers: Vec<_> = (0..2)
+        .map(|_| {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    lock.write(|sample| {
+                        sample.a += 1;
+                        sample.b += 1;
+                        sample.odd ^= 1;
+                    });
+                }
+            })
+        })
+        .collect();
+    for _ in 0..ITERATIONS {
+        let Sample { a, b, odd } = lock.read();
+        assert_eq!(a, b);
+        assert_eq!(odd, a & 1);
+    }
+    for t in writers {
+        t.join().unwrap();
+    }
+
+    let writes = 2 * ITERATIONS as u32;
+    assert_eq!(
+        lock.read(),
+        Sample {
+            a: writes,
+            b: writes,
+            odd: 0
+        }
+    );
+}
+
+#[test]
+fn frees_values_once_their_readers_unpinned_them() {
+    let epochs = Arc::new(EpochCounter::new());
+    let current = Arc::new(AtomicPtr::new(Box::into_raw(Box::new(0u64))));
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let (epochs, current) = (epochs.clone(), current.clone());
+            thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let pinned = epochs.pin();
+                    let value = unsafe { *current.load(Ordering::SeqCst) };
+                    assert!(value <= ITERATIONS);
+                    pinned.unpin();
+                }
+            })
+        })
+        .collect();
+    for i in 1..=ITERATIONS {
+        let old = current.swap(Box::into_raw(Box::new(i)), Ordering::SeqCst);
+        epochs.wait_for_quiescence();
+        drop(unsafe { Box::from_raw(old) });
+    }
+    for t in readers {
+        t.join().unwrap();
+    }
+
+    let last = unsafe { Box::from_raw(current.load(Ordering::SeqCst)) };
+    assert_eq!(*last, ITERATIONS);
+}
+
+#[test]
+fn drops_values_which_were_never_used() {
+    let counters: Vec<_> = (0..4).map(AtomicUsize::new).collect();
+    assert_eq!(counters[3].load(Ordering::Relaxed), 3);
+    drop(counters);
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn allocates_and_frees_counters_for_c() {
+    use atomic_shim::ffi::*;
+
+    let counter = atomic_shim_u64_new(1);
+    unsafe {
+        assert_eq!(atomic_shim_u64_fetch_add(counter, 2), 1);
+        assert!(atomic_shim_u64_cas(counter, 3, 4));
+        atomic_shim_u64_store(counter, 5);
+        assert_eq!(atomic_shim_u64_load(counter), 5);
+        atomic_shim_u64_free(counter);
+        atomic_shim_u64_free(ptr::null_mut());
+    }
+}
@@ -0,0 +1,131 @@
+//! Checks that the process-shared atomic synchronizes processes sharing a memory segment.
+#![cfg(all(unix, feature = "process-shared"))]
+
+use atomic_shim::process_shared::AtomicU64;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::Ordering;
+
+const PROCESSES: u64 = 4;
+const INCREMENTS: u64 = 10_000;
+
+#[test]
+fn counts_across_processes() {
+    unsafe {
+        let segment = libc::mmap(
+            ptr::null_mut(),
+            mem::size_of::<AtomicU64>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        assert_ne!(segment, libc::MAP_FAILED);
+        let counter = AtomicU64::init(segment.cast(), 0).unwrap();
+
+        let children: Vec<_> = (0..PROCESSES)
+            .map(|_| match libc::fork() {
+                -1 => panic!("fork failed"),
+                0 => {
+                    let counter = AtomicU64::from_ptr(segment.cast());
+                    for _ in 0..INCREMENTS {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                    libc::_exit(0)
+                }
+                pid => pid,
+            })
+            .collect();
+
+        for pid in children {
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        }
+
+        assert_eq!(counter.load(Ordering::SeqCst), PROCESSES * INCREMENTS);
+
+        AtomicU64::destroy(segment.cast());
+        libc::munmap(segment, mem::size_of::<AtomicU64>());
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn recovers_when_the_owner_dies() {
+    unsafe {
+        let segment = libc::mmap(
+            ptr::null_mut(),
+            mem::size_of::<AtomicU64>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        assert_ne!(segment, libc::MAP_FAILED);
+        let counter = AtomicU64::init_robust(segment.cast(), 1).unwrap();
+
+        match libc::fork() {
+            -1 => panic!("fork failed"),
+            0 => {
+                let counter = AtomicU64::from_ptr(segment.cast());
+                let mut guard = counter.lock().unwrap();
+                guard.set(2);
+                mem::forget(guard);
+                libc::_exit(0)
+            }
+            pid => {
+                let mut status = 0;
+                assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            }
+        }
+
+        let owner_died = counter.lock().unwrap_err();
+        assert_eq!(owner_died.value(), 2);
+        let mut guard = owner_died.recover();
+        guard.set(10);
+        drop(guard);
+
+        assert_eq!(counter.fetch_add(1, Ordering::SeqCst), 10);
+        assert!(counter.lock().is_ok());
+
+        AtomicU64::destroy(segment.cast());
+        libc::munmap(segment, mem::size_of::<AtomicU64>());
+    }
+}
+
+#[cfg(all(feature = "diagnostics", debug_assertions))]
+#[test]
+fn panics_on_lock_order_inversions() {
+    unsafe {
+        let mut segments = [
+            mem::MaybeUninit::<AtomicU64>::uninit(),
+            mem::MaybeUninit::uninit(),
+        ];
+        let a = AtomicU64::init(segments[0].as_mut_ptr(), 0).unwrap();
+        let b = AtomicU64::init(segments[1].as_mut_ptr(), 0).unwrap();
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _a = a.lock().unwrap();
+                b.fetch_add(1, Ordering::SeqCst);
+            });
+        });
+
+        let inversion = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _b = b.lock().unwrap();
+            a.fetch_add(1, Ordering::SeqCst);
+        }))
+        .unwrap_err();
+        let message = inversion.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("lock order inversion"), "{}", message);
+        assert_eq!(a.load(Ordering::SeqCst), 0);
+
+        let guard = a.lock().unwrap();
+        assert_eq!(b.fetch_add(1, Ordering::SeqCst), 1);
+        drop(guard);
+
+        AtomicU64::destroy(segments[0].as_mut_ptr());
+        AtomicU64::destroy(segments[1].as_mut_ptr());
+    }
+}
@@ -0,0 +1,60 @@
+//! Checks the replay scheduler forces the recorded interleaving.
+//!
+//! Run with `cargo test --features replay,mutex --test replay`.
+#![cfg(all(feature = "replay", atomic_shim_fallback_u64))]
+
+use atomic_shim::replay::{self, Schedule};
+use atomic_shim::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+
+/// Two threads increment with a separate load and store, which loses an update when they interleave.
+fn racy_increments() -> u64 {
+    let value = Arc::new(AtomicU64::new(0));
+    let threads: Vec<_> = (0..2)
+        .map(|id| {
+            let value = value.clone();
+            thread::spawn(move || {
+                let _registration = replay::register(id);
+                let current = value.load(Ordering::SeqCst);
+                value.store(current + 1, Ordering::SeqCst);
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    value.load(Ordering::SeqCst)
+}
+
+#[test]
+fn replays_an_interleaving_losing_an_update() {
+    let schedule: Schedule = "0,1,0,1".parse().unwrap();
+    for _ in 0..20 {
+        assert_eq!(replay::replay(&schedule, racy_increments), 1);
+    }
+}
+
+#[test]
+fn replays_a_serial_interleaving() {
+    let schedule: Schedule = "1,1,0,0".parse().unwrap();
+    for _ in 0..20 {
+        assert_eq!(replay::replay(&schedule, racy_increments), 2);
+    }
+}
+
+#[test]
+fn replays_a_recorded_schedule() {
+    let (recorded, schedule) = replay::record(racy_increments);
+    assert_eq!(schedule.steps().len(), 4);
+    assert_eq!(replay::replay(&schedule, racy_increments), recorded);
+}
+
+#[test]
+fn parses_its_display() {
+    let schedule = Schedule::from(vec![0, 1, 1, 0]);
+    assert_eq!(schedule.to_string(), "0,1,1,0");
+    assert_eq!(schedule.to_string().parse(), Ok(schedule));
+    assert!("0,x".parse::<Schedule>().is_err());
+}
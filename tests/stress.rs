@@ -0,0 +1,261 @@
+//! Hammers the atomics from many threads, validating invariants which hold under any interleaving.
+//!
+//! The tests are ignored by default. Run them on the target, such as MIPS or PowerPC hardware, with
+//! `cargo test --release --test stress -- --ignored`, configured by the environment:
+//!
+//! - `ATOMIC_SHIM_STRESS_THREADS`: number of threads, twice the available parallelism by default.
+//! - `ATOMIC_SHIM_STRESS_SECS`: duration of each test, 5 seconds by default.
+//! - `ATOMIC_SHIM_STRESS_MIX`: weights of the operations, `add=3,cas=2,load=4,store=1` by default.
+//!   Stores would break the monotonic counters, so they load instead there, and adding would break the
+//!   values checked for tearing, so they swap instead there.
+
+use atomic_shim::{AtomicI64, AtomicU64};
+use std::env;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Config {
+    threads: usize,
+    duration: Duration,
+    mix: Mix,
+}
+
+/// Cumulative weights of the operations, so a random number below `total` picks one.
+#[derive(Clone, Copy)]
+struct Mix {
+    add: u32,
+    cas: u32,
+    load: u32,
+    total: u32,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Cas,
+    Load,
+    Store,
+}
+
+impl Mix {
+    fn parse(spec: &str) -> Self {
+        let (mut add, mut cas, mut load, mut store) = (0, 0, 0, 0);
+        for entry in spec.split(',').filter(|e| !e.is_empty()) {
+            let (op, weight) = entry
+                .split_once('=')
+                .unwrap_or_else(|| panic!("expected op=weight, found {:?}", entry));
+            let weight: u32 = weight
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid weight in {:?}", entry));
+            match op.trim() {
+                "add" => add = weight,
+                "cas" => cas = weight,
+                "load" => load = weight,
+                "store" => store = weight,
+                other => panic!(
+                    "unknown operation {:?}, expected add, cas, load or store",
+                    other
+                ),
+            }
+        }
+        assert!(add + cas + load + store > 0, "the operation mix is empty");
+        Self {
+            add,
+            cas: add + cas,
+            load: add + cas + load,
+            total: add + cas + load + store,
+        }
+    }
+
+    fn pick(&self, random: u32) -> Op {
+        let n = random % self.total;
+        if n < self.add {
+            Op::Add
+        } else if n < self.cas {
+            Op::Cas
+        } else if n < self.load {
+            Op::Load
+        } else {
+            Op::Store
+        }
+    }
+}
+
+fn config() -> Config {
+    let var = |name| env::var(name).ok().filter(|v: &String| !v.is_empty());
+    let threads = var("ATOMIC_SHIM_STRESS_THREADS").map_or_else(
+        || thread::available_parallelism().map_or(4, |n| n.get() * 2),
+        |v| {
+            v.parse()
+                .expect("ATOMIC_SHIM_STRESS_THREADS must be a number")
+        },
+    );
+    let secs = var("ATOMIC_SHIM_STRESS_SECS").map_or(5, |v| {
+        v.parse().expect("ATOMIC_SHIM_STRESS_SECS must be a number")
+    });
+    let mix = var("ATOMIC_SHIM_STRESS_MIX").unwrap_or_else(|| "add=3,cas=2,load=4,store=1".into());
+    Config {
+        threads,
+        duration: Duration::from_secs(secs),
+        mix: Mix::parse(&mix),
+    }
+}
+
+/// A xorshift generator, seeded per thread, as the operations only need to vary.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Spreads a 32-bit value on both halves of a 64-bit one, so a torn read shows as different halves.
+fn mirrored(half: u32) -> u64 {
+    (half as u64) << 32 | half as u64
+}
+
+fn assert_mirrored(value: u64) {
+    assert_eq!(
+        value >> 32,
+        value & 0xffff_ffff,
+        "torn value {:#018x}: the halves were written by different operations",
+        value
+    );
+}
+
+/// Runs `work` on every thread until the deadline, passing the thread index and a generator, and sums the results.
+fn run<F>(config: &Config, work: F) -> u64
+where
+    F: Fn(usize, &mut Random, Instant) -> u64 + Send + Sync + 'static,
+{
+    let work = Arc::new(work);
+    let deadline = Instant::now() + config.duration;
+    let threads: Vec<_> = (0..config.threads)
+        .map(|index| {
+            let work = work.clone();
+            thread::spawn(move || {
+                let mut random = Random(0x9e37_79b9_7f4a_7c15 ^ (index as u64 + 1));
+                work(index, &mut random, deadline)
+            })
+        })
+        .collect();
+    threads.into_iter().map(|t| t.join().unwrap()).sum()
+}
+
+#[test]
+#[ignore]
+fn monotonic_counters_never_go_backwards() {
+    let config = config();
+    let counter = Arc::new(AtomicU64::new(0));
+    let signed = Arc::new(AtomicI64::new(i64::MIN));
+
+    let increments = {
+        let (counter, signed) = (counter.clone(), signed.clone());
+        let mix = config.mix;
+        run(&config, move |_, random, deadline| {
+            let mut last = 0;
+            let mut increments = 0;
+            while Instant::now() < deadline {
+                match mix.pick(random.next() as u32) {
+                    Op::Add => {
+                        let prev = counter.fetch_add(1, Ordering::AcqRel);
+                        assert!(prev >= last, "fetch_add returned {} after {}", prev, last);
+                        last = prev + 1;
+                        signed.fetch_add(1, Ordering::AcqRel);
+                        increments += 1;
+                    }
+                    Op::Cas => {
+                        let mut current = counter.load(Ordering::Acquire);
+                        loop {
+                            assert!(current >= last, "loaded {} after {}", current, last);
+                            match counter.compare_exchange_weak(
+                                current,
+                                current + 1,
+                                Ordering::AcqRel,
+                                Ordering::Acquire,
+                            ) {
+                                Ok(_) => break,
+                                Err(actual) => current = actual,
+                            }
+                        }
+                        last = current + 1;
+                        signed.fetch_add(1, Ordering::AcqRel);
+                        increments += 1;
+                    }
+                    Op::Load | Op::Store => {
+                        let current = counter.load(Ordering::Acquire);
+                        assert!(current >= last, "loaded {} after {}", current, last);
+                        last = current;
+                    }
+                }
+            }
+            increments
+        })
+    };
+
+    assert_eq!(counter.load(Ordering::SeqCst), increments);
+    assert_eq!(
+        signed.load(Ordering::SeqCst),
+        i64::MIN.wrapping_add(increments as i64)
+    );
+}
+
+#[test]
+#[ignore]
+fn compare_exchange_never_observes_torn_values() {
+    let config = config();
+    let value = Arc::new(AtomicU64::new(mirrored(0)));
+    let mix = config.mix;
+
+    run(&config, move |index, random, deadline| {
+        let mut operations = 0;
+        while Instant::now() < deadline {
+            let new = mirrored(random.next() as u32 ^ index as u32);
+            match mix.pick(random.next() as u32) {
+                Op::Add => assert_mirrored(value.swap(new, Ordering::AcqRel)),
+                Op::Cas => {
+                    let current = value.load(Ordering::Acquire);
+                    assert_mirrored(current);
+                    match value.compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+                    {
+                        Ok(prev) => assert_eq!(prev, current),
+                        Err(actual) => assert_mirrored(actual),
+                    }
+                }
+                Op::Load => assert_mirrored(value.load(Ordering::Acquire)),
+                Op::Store => value.store(new, Ordering::Release),
+            }
+            operations += 1;
+        }
+        operations
+    });
+}
+
+#[test]
+#[ignore]
+fn loads_never_observe_torn_stores() {
+    let config = config();
+    let value = Arc::new(AtomicU64::new(mirrored(0)));
+
+    // Half of the threads only store and the other half only load, so the loads race with the stores,
+    // instead of with the read-modify-writes checked above.
+    run(&config, move |index, random, deadline| {
+        let mut operations = 0;
+        while Instant::now() < deadline {
+            if index & 1 == 0 {
+                value.store(mirrored(random.next() as u32), Ordering::Release);
+            } else {
+                assert_mirrored(value.load(Ordering::Acquire));
+            }
+            operations += 1;
+        }
+        operations
+    });
+}
@@ -0,0 +1,49 @@
+//! Checks that a cancelled `wait_async` future doesn't leave its waker behind.
+#![cfg(feature = "async")]
+
+use atomic_shim::AtomicU64;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+struct Noop;
+
+impl Wake for Noop {
+    fn wake(self: Arc<Self>) {}
+}
+
+#[test]
+fn cancelled_future_releases_its_waker() {
+    let atomic = AtomicU64::new(0);
+    let noop = Arc::new(Noop);
+    let waker = Waker::from(noop.clone());
+
+    {
+        let mut future = pin!(atomic.wait_async(0, Ordering::Acquire));
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+        // the entry of the table holds a single clone of the waker
+        assert_eq!(Arc::strong_count(&noop), 3);
+    }
+
+    // the entry of the table holds no clone anymore
+    assert_eq!(Arc::strong_count(&noop), 2);
+}
+
+#[test]
+fn resolved_future_releases_its_waker() {
+    let atomic = AtomicU64::new(0);
+    let noop = Arc::new(Noop);
+    let waker = Waker::from(noop.clone());
+
+    let mut future = pin!(atomic.wait_async(0, Ordering::Acquire));
+    let mut cx = Context::from_waker(&waker);
+    assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+    // stored without a notification, so the waker stays in the entry until the future sees the change
+    atomic.store(1, Ordering::Release);
+    assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(()));
+    assert_eq!(Arc::strong_count(&noop), 2);
+}
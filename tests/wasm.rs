@@ -0,0 +1,36 @@
+//! Checks the implementation selected on WebAssembly targets, such as `wasm32-wasip1-threads`.
+//!
+//! Run with a WASI runtime, e.g. `CARGO_TARGET_WASM32_WASIP1_THREADS_RUNNER=wasmtime cargo test --target wasm32-wasip1-threads`.
+#![cfg(target_family = "wasm")]
+
+use atomic_shim::AtomicU64;
+use std::sync::atomic::Ordering;
+
+#[test]
+fn uses_native_atomics_only_with_the_atomics_feature() {
+    assert_eq!(AtomicU64::IS_LOCK_FREE, cfg!(target_feature = "atomics"));
+}
+
+#[test]
+fn counts_across_threads() {
+    if !cfg!(target_feature = "atomics") {
+        return;
+    }
+
+    let counter = std::sync::Arc::new(AtomicU64::new(0));
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let counter = counter.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(counter.load(Ordering::SeqCst), 4000);
+}
@@ -0,0 +1,114 @@
+//! Checks the weak memory simulation returns stale values where the memory model allows it, and only there.
+//!
+//! Run with `cargo test --features weak-memory,mutex --test weak_memory`.
+#![cfg(all(feature = "weak-memory", atomic_shim_fallback_u64))]
+
+use atomic_shim::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+const RUNS: usize = 100;
+
+/// Publishes `data` through `flag` with the given orderings, and returns what the reader saw in `data`.
+///
+/// The reader touches the atomics before they are written, so it doesn't start with the stores in its view.
+fn message_passing(store: Ordering, load: Ordering) -> u64 {
+    let data = Arc::new(AtomicU64::new(0));
+    let flag = Arc::new(AtomicU64::new(0));
+    let (ready, wait_ready) = mpsc::channel();
+    let (go, wait_go) = mpsc::channel();
+
+    let reader = {
+        let (data, flag) = (data.clone(), flag.clone());
+        thread::spawn(move || {
+            data.load(Ordering::Relaxed);
+            flag.load(Ordering::Relaxed);
+            ready.send(()).unwrap();
+            wait_go.recv().unwrap();
+            while flag.load(load) == 0 {}
+            data.load(Ordering::Relaxed)
+        })
+    };
+
+    wait_ready.recv().unwrap();
+    data.store(42, Ordering::Relaxed);
+    flag.store(1, store);
+    go.send(()).unwrap();
+    reader.join().unwrap()
+}
+
+#[test]
+fn relaxed_publication_can_read_stale_data() {
+    let stale = (0..RUNS)
+        .filter(|_| message_passing(Ordering::Relaxed, Ordering::Relaxed) == 0)
+        .count();
+    assert!(stale > 0, "the reader never saw stale data");
+}
+
+#[test]
+fn release_acquire_publication_never_reads_stale_data() {
+    for _ in 0..RUNS {
+        assert_eq!(message_passing(Ordering::Release, Ordering::Acquire), 42);
+    }
+}
+
+#[test]
+fn a_thread_never_reads_older_stores_than_it_saw() {
+    let value = Arc::new(AtomicU64::new(0));
+    let reader = {
+        let value = value.clone();
+        thread::spawn(move || {
+            let mut last = value.load(Ordering::Relaxed);
+            while last < 5 {
+                let current = value.load(Ordering::Relaxed);
+                assert!(current >= last, "read {} after {}", current, last);
+                last = current;
+            }
+        })
+    };
+    for i in 1..=5 {
+        value.store(i, Ordering::Relaxed);
+    }
+    reader.join().unwrap();
+}
+
+#[test]
+fn read_modify_writes_read_the_latest_store() {
+    let counter = Arc::new(AtomicU64::new(0));
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(counter.load(Ordering::Relaxed), 4000);
+}
+
+#[test]
+fn seq_cst_loads_read_the_latest_store() {
+    let value = Arc::new(AtomicU64::new(0));
+    let (ready, wait_ready) = mpsc::channel();
+    let (go, wait_go) = mpsc::channel();
+    let reader = {
+        let value = value.clone();
+        thread::spawn(move || {
+            value.load(Ordering::Relaxed);
+            ready.send(()).unwrap();
+            wait_go.recv().unwrap();
+            value.load(Ordering::SeqCst)
+        })
+    };
+    wait_ready.recv().unwrap();
+    value.store(7, Ordering::Relaxed);
+    go.send(()).unwrap();
+    assert_eq!(reader.join().unwrap(), 7);
+}